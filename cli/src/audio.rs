@@ -0,0 +1,248 @@
+use clap::ValueEnum;
+use rodio::{OutputStream, Sink, Source};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::time::Duration;
+
+const SAMPLE_RATE: u32 = 44_100;
+const MIN_VOLUME: f32 = 0.0;
+const MAX_VOLUME: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Waveform {
+    Square,
+    Sine,
+    Triangle,
+}
+
+impl Waveform {
+    // `phase` is the position within one period, in the range [0, 1).
+    pub fn sample(self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (phase * std::f32::consts::TAU).sin(),
+            Waveform::Triangle => 4.0 * (phase - 0.5).abs() - 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BeepConfig {
+    pub waveform: Waveform,
+    pub frequency_hz: f32,
+    volume: f32,
+}
+
+impl BeepConfig {
+    pub fn new(waveform: Waveform, frequency_hz: f32, volume: f32) -> Self {
+        BeepConfig {
+            waveform,
+            frequency_hz,
+            volume: volume.clamp(MIN_VOLUME, MAX_VOLUME),
+        }
+    }
+}
+
+// XO-CHIP's one-bit audio, played from the most significant bit of the first
+// byte and looping, at `bit_rate` bits a second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioPattern {
+    pub bits: [u8; 16],
+    pub bit_rate: f32,
+}
+
+impl AudioPattern {
+    fn sample(&self, position: f32) -> f32 {
+        let bit = position as usize % (self.bits.len() * 8);
+        if self.bits[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+}
+
+// Set by the interpreter for XO-CHIP programs. While it's unset, or the program
+// hasn't loaded a pattern, the beep plays instead.
+pub type SharedAudioPattern = Arc<Mutex<Option<AudioPattern>>>;
+
+struct BeepSource {
+    config: BeepConfig,
+    sound_active: Arc<AtomicBool>,
+    phase: f32,
+    pattern: SharedAudioPattern,
+    // Bits into the pattern, counting fractions of a bit.
+    pattern_position: f32,
+}
+
+impl Iterator for BeepSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if !self.sound_active.load(Ordering::Relaxed) {
+            self.phase = 0.0;
+            self.pattern_position = 0.0;
+            return Some(0.0);
+        }
+
+        let pattern = self.pattern.lock().ok().and_then(|pattern| *pattern);
+        if let Some(pattern) = pattern.filter(|pattern| pattern.bits != [0; 16]) {
+            let sample = self.config.volume * pattern.sample(self.pattern_position);
+            let pattern_bits = (pattern.bits.len() * 8) as f32;
+            self.pattern_position =
+                (self.pattern_position + pattern.bit_rate / SAMPLE_RATE as f32) % pattern_bits;
+            return Some(sample);
+        }
+
+        let sample = self.config.volume * self.config.waveform.sample(self.phase);
+        self.phase = (self.phase + self.config.frequency_hz / SAMPLE_RATE as f32).fract();
+        Some(sample)
+    }
+}
+
+impl Source for BeepSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+// The output stream stops playing when dropped, so this needs to outlive the
+// emulation. Sound is produced whenever `sound_active` is set.
+pub struct Beeper {
+    _stream: OutputStream,
+    _sink: Sink,
+}
+
+impl Beeper {
+    pub fn new(
+        config: BeepConfig,
+        sound_active: Arc<AtomicBool>,
+        pattern: SharedAudioPattern,
+    ) -> Result<Beeper, Box<dyn std::error::Error>> {
+        let (stream, stream_handle) = OutputStream::try_default()?;
+        let sink = Sink::try_new(&stream_handle)?;
+        sink.append(BeepSource {
+            config,
+            sound_active,
+            phase: 0.0,
+            pattern,
+            pattern_position: 0.0,
+        });
+
+        Ok(Beeper {
+            _stream: stream,
+            _sink: sink,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(lhs: f32, rhs: f32) {
+        assert!((lhs - rhs).abs() < 1e-5, "{} != {}", lhs, rhs);
+    }
+
+    #[test]
+    fn test_square_samples() {
+        assert_close(Waveform::Square.sample(0.0), 1.0);
+        assert_close(Waveform::Square.sample(0.25), 1.0);
+        assert_close(Waveform::Square.sample(0.5), -1.0);
+        assert_close(Waveform::Square.sample(0.75), -1.0);
+    }
+
+    #[test]
+    fn test_sine_samples() {
+        assert_close(Waveform::Sine.sample(0.0), 0.0);
+        assert_close(Waveform::Sine.sample(0.25), 1.0);
+        assert_close(Waveform::Sine.sample(0.5), 0.0);
+        assert_close(Waveform::Sine.sample(0.75), -1.0);
+    }
+
+    #[test]
+    fn test_triangle_samples() {
+        assert_close(Waveform::Triangle.sample(0.0), 1.0);
+        assert_close(Waveform::Triangle.sample(0.25), 0.0);
+        assert_close(Waveform::Triangle.sample(0.5), -1.0);
+        assert_close(Waveform::Triangle.sample(0.75), 0.0);
+    }
+
+    #[test]
+    fn test_volume_clamped() {
+        assert_close(BeepConfig::new(Waveform::Sine, 440.0, 3.0).volume, 1.0);
+        assert_close(BeepConfig::new(Waveform::Sine, 440.0, -1.0).volume, 0.0);
+        assert_close(BeepConfig::new(Waveform::Sine, 440.0, 0.3).volume, 0.3);
+    }
+
+    #[test]
+    fn test_source_scaled_and_gated() {
+        let sound_active = Arc::new(AtomicBool::new(false));
+        let mut source = BeepSource {
+            config: BeepConfig::new(Waveform::Square, 440.0, 0.5),
+            sound_active: sound_active.clone(),
+            phase: 0.0,
+            pattern: Arc::new(Mutex::new(None)),
+            pattern_position: 0.0,
+        };
+
+        assert_eq!(source.next(), Some(0.0));
+
+        sound_active.store(true, Ordering::Relaxed);
+        assert_eq!(source.next(), Some(0.5));
+
+        // half a period later the square wave has flipped
+        let half_period = (SAMPLE_RATE as f32 / 440.0 / 2.0).ceil() as usize;
+        assert_eq!(source.nth(half_period - 1), Some(-0.5));
+    }
+
+    #[test]
+    fn test_source_plays_pattern() {
+        let sound_active = Arc::new(AtomicBool::new(true));
+        let pattern = Arc::new(Mutex::new(Some(AudioPattern {
+            bits: [0; 16],
+            bit_rate: SAMPLE_RATE as f32 / 2.0,
+        })));
+        let mut source = BeepSource {
+            config: BeepConfig::new(Waveform::Sine, 440.0, 0.5),
+            sound_active,
+            phase: 0.0,
+            pattern: pattern.clone(),
+            pattern_position: 0.0,
+        };
+
+        // a blank pattern falls back to the beep
+        assert_eq!(source.next(), Some(0.0));
+
+        let mut bits = [0; 16];
+        bits[0] = 0b1010_0000;
+        pattern.lock().unwrap().as_mut().unwrap().bits = bits;
+        // two samples a bit
+        let samples: Vec<f32> = source.by_ref().take(8).collect();
+        assert_eq!(samples, [0.5, 0.5, -0.5, -0.5, 0.5, 0.5, -0.5, -0.5]);
+
+        // and it loops after 128 bits
+        let samples: Vec<f32> = source.by_ref().skip(248).take(2).collect();
+        assert_eq!(samples, [0.5, 0.5]);
+    }
+}
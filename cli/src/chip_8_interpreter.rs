@@ -1,72 +1,450 @@
+use std::fs;
+use std::path::PathBuf;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicBool, AtomicU64, Ordering},
     mpsc::{Receiver, Sender},
     Arc,
 };
+use std::time::{Duration, Instant};
 
 use grid::Grid;
 use interpreter::{
+    builtin_roms::BUILTIN_ROMS,
+    cheats::CheatList,
     display::Pixel,
+    emulator::Emulator,
     keypad::KeyStatus,
-    processor::{Processor, ProcessorError},
+    processor::{Config, ProcessorError, ProcessorWarning, Quirks, StepOutcome},
 };
 
+use crate::audio::{AudioPattern, SharedAudioPattern};
+use crate::clock::InstructionClock;
+use crate::demo::DemoSchedule;
+use crate::frame_gate::FrameGate;
+use crate::log_throttle::LogThrottle;
 use crate::utils::log_error;
+use log::{error, info, warn};
+
+const DEFAULT_REFRESH_HZ: f64 = 60.0;
+const DEFAULT_IPS: f64 = 700.0;
+const MAX_IDLE_SLEEP: Duration = Duration::from_millis(1);
 
 pub struct KeyUpdate {
     pub key: usize,
     pub status: KeyStatus,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlMessage {
+    Step,
+    Reload(Vec<u8>),
+    // Reads the ROM file again and restarts it, or just restarts the program
+    // if it didn't come from a file.
+    Restart,
+    SetSpeed(f64),
+    // Goes back to the start of the previous frame.
+    Rewind,
+    // Writes out memory and prints the registers, as set up by `set_dumps`.
+    Dump,
+}
+
 pub struct Chip8Interpreter {
-    processor: Processor,
+    emulator: Emulator,
     exit_requested: Arc<AtomicBool>,
     frame_channel: Sender<Grid<Pixel>>,
     keys_channel: Receiver<KeyUpdate>,
     timer_channel: Receiver<usize>,
+    sound_active: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    control_channel: Receiver<ControlMessage>,
+    demo: Option<DemoSchedule>,
+    cheats: CheatList,
+    warning_log: LogThrottle<ProcessorWarning>,
+    frame_gate: FrameGate,
+    clock: InstructionClock,
+    program_exited: bool,
+    program_idle: bool,
+    rom_path: Option<PathBuf>,
+    // Instructions run so far, for the frontend to measure the real speed.
+    cycles: Arc<AtomicU64>,
+    memory_dump_path: Option<PathBuf>,
+    dump_registers: bool,
+    // Kept up to date for the audio thread, for XO-CHIP programs.
+    audio_pattern: Option<SharedAudioPattern>,
 }
 
 impl Chip8Interpreter {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         program_data: Vec<u8>,
+        config: Config,
         exit_flag: Arc<AtomicBool>,
         frame_sender: Sender<Grid<Pixel>>,
         key_receiver: Receiver<KeyUpdate>,
         timer_receiver: Receiver<usize>,
+        sound_flag: Arc<AtomicBool>,
+        paused_flag: Arc<AtomicBool>,
+        control_receiver: Receiver<ControlMessage>,
     ) -> Result<Chip8Interpreter, ProcessorError> {
         Ok(Self {
-            processor: Processor::new(program_data)?,
+            emulator: Emulator::new_with_config(program_data, config, Quirks::default())?,
             exit_requested: exit_flag,
             frame_channel: frame_sender,
             keys_channel: key_receiver,
             timer_channel: timer_receiver,
+            sound_active: sound_flag,
+            paused: paused_flag,
+            control_channel: control_receiver,
+            demo: None,
+            cheats: CheatList::default(),
+            warning_log: LogThrottle::new(),
+            frame_gate: FrameGate::from_hz(DEFAULT_REFRESH_HZ),
+            clock: InstructionClock::from_ips(DEFAULT_IPS),
+            program_exited: false,
+            program_idle: false,
+            rom_path: None,
+            cycles: Arc::new(AtomicU64::new(0)),
+            memory_dump_path: None,
+            dump_registers: false,
+            audio_pattern: None,
         })
     }
 
-    pub fn run(&mut self) {
+    pub fn set_warn_vf_clobber(&mut self, enabled: bool) {
+        self.emulator.processor_mut().set_warn_vf_clobber(enabled);
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.emulator.processor_mut().set_quirks(quirks);
+    }
+
+    pub fn set_refresh_period(&mut self, period: Duration) {
+        self.frame_gate = FrameGate::new(period);
+    }
+
+    pub fn set_rewind_frames(&mut self, frames: usize) {
+        self.emulator
+            .processor_mut()
+            .set_frame_history_length(frames);
+    }
+
+    pub fn set_clock(&mut self, clock: InstructionClock) {
+        self.clock = clock;
+    }
+
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.emulator.processor_mut().seed_rng(seed);
+    }
+
+    pub fn set_rom_path(&mut self, path: PathBuf) {
+        self.rom_path = Some(path);
+    }
+
+    pub fn cycle_counter(&self) -> Arc<AtomicU64> {
+        self.cycles.clone()
+    }
+
+    pub fn set_breakpoints(&mut self, addresses: &[u16]) -> Result<(), ProcessorError> {
+        for address in addresses {
+            self.emulator.processor_mut().add_breakpoint(*address)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_dumps(&mut self, memory_path: Option<PathBuf>, registers: bool) {
+        self.memory_dump_path = memory_path;
+        self.dump_registers = registers;
+    }
+
+    pub fn set_audio_pattern(&mut self, audio_pattern: SharedAudioPattern) {
+        self.audio_pattern = Some(audio_pattern);
+    }
+
+    pub fn set_cheats(&mut self, cheats: CheatList) {
+        self.cheats = cheats;
+    }
+
+    // Rotates through the built-in ROMs, starting with the one currently loaded.
+    pub fn set_demo(&mut self, schedule: DemoSchedule) {
+        info!(
+            "Demo mode: running {}",
+            BUILTIN_ROMS[schedule.current()].name
+        );
+        self.demo = Some(schedule);
+    }
+
+    // Returns true if the program ended itself with 00FD, rather than failing or
+    // being stopped.
+    pub fn run(&mut self) -> bool {
         while !self.exit_requested.load(Ordering::SeqCst) {
-            if let Err(err) = self.processor.step() {
-                self.encountered_error(err);
-                return;
+            if !self.run_once() {
+                break;
+            }
+        }
+
+        // Otherwise a beep that was playing when the program stopped would carry
+        // on for as long as the window stays open.
+        self.sound_active.store(false, Ordering::Relaxed);
+        self.warning_log.flush(|msg| warn!("{}", msg));
+        self.program_exited
+    }
+
+    // Returns false once the interpreter can't continue.
+    fn run_once(&mut self) -> bool {
+        let mut steps = 0;
+        while let Ok(message) = self.control_channel.try_recv() {
+            match message {
+                ControlMessage::Step => steps += 1,
+                ControlMessage::Reload(program) => match self.emulator.load(&program) {
+                    Ok(()) => steps = 0,
+                    Err(err) => error!("Couldn't reload program: {}", err),
+                },
+                ControlMessage::Restart => {
+                    if self.restart() {
+                        steps = 0;
+                    }
+                }
+                ControlMessage::SetSpeed(instructions_per_second) => {
+                    self.clock = InstructionClock::from_ips(instructions_per_second);
+                }
+                ControlMessage::Rewind => {
+                    if self.emulator.processor_mut().rewind_frame(1) {
+                        self.program_idle = false;
+                        steps = 0;
+                    } else {
+                        info!("No more history to rewind");
+                    }
+                }
+                ControlMessage::Dump => self.dump(),
+            }
+        }
+
+        let paused = self.paused.load(Ordering::SeqCst);
+        if paused {
+            self.clock.reset();
+        } else {
+            steps = self.clock.steps_due(Instant::now());
+        }
+
+        for _ in 0..steps {
+            let outcome = if paused {
+                self.single_step()
+            } else {
+                self.emulator.step()
+            };
+            // Stopping at a breakpoint or while halted doesn't run anything.
+            if outcome.is_ok_and(|outcome| {
+                !matches!(
+                    outcome,
+                    StepOutcome::Breakpoint { .. } | StepOutcome::Halted
+                )
+            }) {
+                self.cycles.fetch_add(1, Ordering::Relaxed);
             }
+            match outcome {
+                Ok(StepOutcome::Running) => self.program_idle = false,
+                Ok(StepOutcome::Breakpoint { address }) => {
+                    info!("Paused at breakpoint {}", address);
+                    self.paused.store(true, Ordering::SeqCst);
+                    break;
+                }
+                Ok(StepOutcome::Watchpoint { address }) => {
+                    info!("Paused after a write to {:#05x}", address);
+                    self.paused.store(true, Ordering::SeqCst);
+                    break;
+                }
+                // Only the timers can change anything now, so don't spin.
+                Ok(StepOutcome::Idle) => {
+                    if !self.program_idle {
+                        info!(
+                            "Program finished, waiting at {}",
+                            self.emulator.processor().program_counter()
+                        );
+                        self.program_idle = true;
+                    }
+                    break;
+                }
+                // Halted until reset, so wait for that the same way.
+                Ok(StepOutcome::Halted) => {
+                    if !self.program_idle {
+                        info!(
+                            "Program halted on a zero opcode at {}",
+                            self.emulator.processor().program_counter()
+                        );
+                        self.program_idle = true;
+                    }
+                    break;
+                }
+                Ok(StepOutcome::Exited) => {
+                    if let Some(demo) = &mut self.demo {
+                        let next = demo.advance(Instant::now());
+                        self.load_demo_rom(next);
+                        return true;
+                    }
+                    info!("Program exited");
+                    self.program_exited = true;
+                    self.exit_requested.store(true, Ordering::SeqCst);
+                    return false;
+                }
+                Err(err) => {
+                    if self.demo.is_some() {
+                        self.skip_failed_demo_rom(err);
+                        return true;
+                    }
+                    error!("{}: {}", err.category(), err.explanation());
+                    self.encountered_error(err);
+                    return false;
+                }
+            }
+        }
 
-            if let Some(fresh_frame) = self.processor.get_display_buffer() {
-                if let Err(err) = self.frame_channel.send(fresh_frame.clone()) {
+        for warning in self.emulator.processor_mut().take_warnings() {
+            self.warning_log.observe(warning, |msg| warn!("{}", msg));
+        }
+
+        // The display stays dirty while the gate is closed, so the latest frame
+        // goes out once it opens.
+        let now = Instant::now();
+        if self.frame_gate.is_open(now) {
+            if let Some(frame) = self.emulator.take_frame() {
+                if let Err(err) = self.frame_channel.send(frame) {
                     self.encountered_error(err);
-                    return;
+                    return false;
                 }
+                self.frame_gate.frame_sent(now);
+            }
+        }
+
+        while let Ok(key_event) = self.keys_channel.try_recv() {
+            self.emulator.set_key(key_event.key, key_event.status);
+        }
+
+        // A timer tick marks the end of a frame. Time stands still while paused,
+        // so ticks are dropped rather than caught up on afterwards.
+        let ticks: usize = self.timer_channel.try_iter().sum();
+        if ticks > 0 && !paused {
+            self.cheats.apply(self.emulator.processor_mut());
+            for _ in 0..ticks {
+                self.emulator.tick_timers();
             }
+        }
 
-            while let Ok(key_event) = self.keys_channel.try_recv() {
-                self.processor
-                    .add_key_event(key_event.key, key_event.status);
+        self.sound_active.store(
+            !paused && self.emulator.processor().sound() > 0,
+            Ordering::Relaxed,
+        );
+        if let Some(audio_pattern) = &self.audio_pattern {
+            let processor = self.emulator.processor();
+            if let Ok(mut pattern) = audio_pattern.lock() {
+                *pattern = Some(AudioPattern {
+                    bits: *processor.audio_pattern(),
+                    bit_rate: processor.audio_bit_rate() as f32,
+                });
             }
+        }
 
-            if let Ok(timer_decrement) = self.timer_channel.try_recv() {
-                for _ in 0..timer_decrement {
-                    self.processor.decrement_timers();
+        if paused {
+            std::thread::sleep(MAX_IDLE_SLEEP);
+            return true;
+        }
+
+        if let Some(demo) = &mut self.demo {
+            let now = Instant::now();
+            if demo.is_due(now) {
+                let next = demo.advance(now);
+                self.load_demo_rom(next);
+            }
+        }
+
+        // Sleep until the next instruction is due, but wake up often enough to
+        // pick up keys and timer ticks promptly.
+        if self.program_idle {
+            std::thread::sleep(MAX_IDLE_SLEEP);
+        } else if steps == 0 {
+            std::thread::sleep(self.clock.until_next(Instant::now()).min(MAX_IDLE_SLEEP));
+        }
+
+        true
+    }
+
+    // Logs each instruction run while paused, to follow along one at a time.
+    fn single_step(&mut self) -> Result<StepOutcome, ProcessorError> {
+        match self.emulator.processor_mut().step_traced()? {
+            Some(executed) => {
+                info!("{}", executed);
+                if self.emulator.processor().is_idle() {
+                    Ok(StepOutcome::Idle)
+                } else {
+                    Ok(executed.outcome())
                 }
             }
+            None if self.emulator.processor().is_halted() => Ok(StepOutcome::Halted),
+            None => Ok(StepOutcome::Running),
+        }
+    }
+
+    // Returns false if the ROM couldn't be reloaded, leaving the old one running.
+    fn restart(&mut self) -> bool {
+        let Some(path) = &self.rom_path else {
+            self.emulator.processor_mut().reset();
+            return true;
+        };
+
+        let result = fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|program| self.emulator.load(&program).map_err(|err| err.to_string()));
+        match result {
+            Ok(()) => {
+                info!("Reloaded {}", path.display());
+                true
+            }
+            Err(err) => {
+                error!("Couldn't reload {}: {}", path.display(), err);
+                false
+            }
+        }
+    }
+
+    fn dump(&self) {
+        let processor = self.emulator.processor();
+        if let Some(path) = &self.memory_dump_path {
+            match fs::write(path, processor.memory_snapshot()) {
+                Ok(()) => info!("Dumped memory to {}", path.display()),
+                Err(err) => log_error(err),
+            }
+        }
+        if self.dump_registers {
+            eprintln!("{}", processor.register_dump());
+        }
+        if self.memory_dump_path.is_none() && !self.dump_registers {
+            info!("Nothing to dump, use --dump-memory or --dump-registers");
+        }
+    }
+
+    fn load_demo_rom(&mut self, index: usize) {
+        let rom = &BUILTIN_ROMS[index];
+        match self.emulator.load(rom.data) {
+            Ok(()) => info!("Demo mode: running {}", rom.name),
+            Err(err) => self.skip_failed_demo_rom(err),
+        }
+    }
+
+    fn skip_failed_demo_rom(&mut self, err: ProcessorError) {
+        let Some(demo) = &mut self.demo else {
+            return;
+        };
+
+        warn!(
+            "Demo mode: skipping {}: {}",
+            BUILTIN_ROMS[demo.current()].name,
+            err
+        );
+        match demo.skip_failed(Instant::now()) {
+            Some(next) => self.load_demo_rom(next),
+            None => {
+                error!("Demo mode: every built-in ROM failed");
+                self.exit_requested.store(true, Ordering::SeqCst);
+            }
         }
     }
 
@@ -75,3 +453,408 @@ impl Chip8Interpreter {
         self.exit_requested.store(true, Ordering::SeqCst);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use interpreter::types::{Address, GeneralRegister};
+    use std::sync::mpsc::{self, Receiver, Sender};
+
+    struct Harness {
+        interpreter: Chip8Interpreter,
+        frames: Receiver<Grid<Pixel>>,
+        controls: Sender<ControlMessage>,
+        sound: Arc<AtomicBool>,
+        paused: Arc<AtomicBool>,
+        timer: Sender<usize>,
+        _keys: Sender<KeyUpdate>,
+    }
+
+    fn harness(program: Vec<u8>, paused: bool) -> Harness {
+        let (frame_tx, frame_rx) = mpsc::channel();
+        let (key_tx, key_rx) = mpsc::channel();
+        let (timer_tx, timer_rx) = mpsc::channel();
+        let (control_tx, control_rx) = mpsc::channel();
+        let sound = Arc::new(AtomicBool::new(false));
+        let paused = Arc::new(AtomicBool::new(paused));
+        let mut interpreter = Chip8Interpreter::new(
+            program,
+            Config::default(),
+            Arc::new(AtomicBool::new(false)),
+            frame_tx,
+            key_rx,
+            timer_rx,
+            sound.clone(),
+            paused.clone(),
+            control_rx,
+        )
+        .unwrap();
+        interpreter.set_refresh_period(Duration::ZERO);
+        interpreter.set_clock(InstructionClock::new(Duration::ZERO));
+
+        Harness {
+            interpreter,
+            frames: frame_rx,
+            controls: control_tx,
+            sound,
+            paused,
+            timer: timer_tx,
+            _keys: key_tx,
+        }
+    }
+
+    fn lit_pixels(frame: &Grid<Pixel>) -> usize {
+        frame.iter().filter(|pixel| **pixel == Pixel::On).count()
+    }
+
+    #[test]
+    fn test_reload_sends_frame() {
+        let mut harness = harness(
+            vec![
+                0xD0, 0x05, // DRW V0, V0, 5
+                0x12, 0x02, // JP 0x202
+            ],
+            false,
+        );
+        for _ in 0..3 {
+            assert!(harness.interpreter.run_once());
+        }
+        let drawn = harness.frames.try_iter().last().unwrap();
+        assert_eq!(lit_pixels(&drawn), 14);
+
+        // the new program never draws, so the cleared frame has to come from
+        // the reload itself
+        harness
+            .controls
+            .send(ControlMessage::Reload(vec![0x12, 0x00]))
+            .unwrap();
+        assert!(harness.interpreter.run_once());
+        let reloaded = harness.frames.try_recv().unwrap();
+        assert_eq!(lit_pixels(&reloaded), 0);
+    }
+
+    #[test]
+    fn test_cycle_counter() {
+        let mut harness = harness(
+            vec![
+                0x60, 0x01, // LD V0, 0x01
+                0x12, 0x00, // JP 0x200
+            ],
+            false,
+        );
+        let cycles = harness.interpreter.cycle_counter();
+
+        for _ in 0..5 {
+            assert!(harness.interpreter.run_once());
+        }
+        assert_eq!(cycles.load(Ordering::Relaxed), 5);
+    }
+
+    #[test]
+    fn test_starts_paused() {
+        let mut harness = harness(
+            vec![
+                0xD0, 0x05, // DRW V0, V0, 5
+            ],
+            true,
+        );
+
+        assert!(harness.interpreter.run_once());
+        let first_frame = harness.frames.try_recv().unwrap();
+        assert_eq!(lit_pixels(&first_frame), 0);
+
+        assert!(harness.interpreter.run_once());
+        assert!(harness.frames.try_recv().is_err());
+
+        harness.controls.send(ControlMessage::Step).unwrap();
+        assert!(harness.interpreter.run_once());
+        let stepped_frame = harness.frames.try_recv().unwrap();
+        assert_eq!(lit_pixels(&stepped_frame), 14);
+    }
+
+    #[test]
+    fn test_exit_stops_cleanly() {
+        let mut harness = harness(
+            vec![
+                0x00, 0xFD, // EXIT
+            ],
+            false,
+        );
+
+        assert!(!harness.interpreter.run_once());
+        assert!(harness.interpreter.exit_requested.load(Ordering::SeqCst));
+        assert!(harness.interpreter.run());
+    }
+
+    #[test]
+    fn test_sound_stops_on_exit() {
+        let mut harness = harness(
+            vec![
+                0x60, 0x10, // LD V0, 0x10
+                0xF0, 0x18, // LD ST, V0
+                0x00, 0xFD, // EXIT
+            ],
+            false,
+        );
+
+        assert!(harness.interpreter.run_once());
+        assert!(harness.interpreter.run_once());
+        assert!(harness.sound.load(Ordering::Relaxed));
+
+        assert!(harness.interpreter.run());
+        assert!(!harness.sound.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_unpausing_skips_missed_ticks() {
+        let mut harness = harness(
+            vec![
+                0x60, 0x10, // LD V0, 0x10
+                0xF0, 0x15, // LD DT, V0
+                0x12, 0x04, // JP 0x204
+            ],
+            false,
+        );
+
+        assert!(harness.interpreter.run_once());
+        assert!(harness.interpreter.run_once());
+        assert_eq!(harness.interpreter.emulator.processor().delay(), 0x10);
+
+        harness.paused.store(true, Ordering::SeqCst);
+        harness.timer.send(5).unwrap();
+        assert!(harness.interpreter.run_once());
+
+        harness.paused.store(false, Ordering::SeqCst);
+        assert!(harness.interpreter.run_once());
+        assert_eq!(harness.interpreter.emulator.processor().delay(), 0x10);
+
+        harness.timer.send(1).unwrap();
+        assert!(harness.interpreter.run_once());
+        assert_eq!(harness.interpreter.emulator.processor().delay(), 0x0F);
+    }
+
+    #[test]
+    fn test_idle_program_keeps_running() {
+        let mut harness = harness(
+            vec![
+                0x60, 0x10, // LD V0, 0x10
+                0xF0, 0x15, // LD DT, V0
+                0x12, 0x04, // JP 0x204
+            ],
+            false,
+        );
+
+        for _ in 0..4 {
+            assert!(harness.interpreter.run_once());
+        }
+        assert!(harness.interpreter.program_idle);
+
+        harness.timer.send(1).unwrap();
+        assert!(harness.interpreter.run_once());
+        assert_eq!(harness.interpreter.emulator.processor().delay(), 0x0F);
+        assert!(!harness.interpreter.exit_requested.load(Ordering::SeqCst));
+
+        harness.controls.send(ControlMessage::Restart).unwrap();
+        assert!(harness.interpreter.run_once());
+        assert!(!harness.interpreter.program_idle);
+    }
+
+    #[test]
+    fn test_halted_program_waits() {
+        let mut harness = harness(
+            vec![
+                0x60, 0x01, // LD V0, 0x01
+                0x00, 0x00, // halts
+            ],
+            false,
+        );
+        harness
+            .interpreter
+            .emulator
+            .processor_mut()
+            .set_quirks(Quirks {
+                halt_on_zero_opcode: true,
+                ..Quirks::default()
+            });
+        let cycles = harness.interpreter.cycle_counter();
+
+        for _ in 0..4 {
+            assert!(harness.interpreter.run_once());
+        }
+        assert!(harness.interpreter.program_idle);
+        assert!(harness.interpreter.emulator.processor().is_halted());
+        assert_eq!(cycles.load(Ordering::Relaxed), 1);
+        assert!(!harness.interpreter.exit_requested.load(Ordering::SeqCst));
+
+        harness.paused.store(true, Ordering::SeqCst);
+        assert_eq!(harness.interpreter.single_step(), Ok(StepOutcome::Halted));
+        harness.paused.store(false, Ordering::SeqCst);
+
+        harness.controls.send(ControlMessage::Restart).unwrap();
+        assert!(harness.interpreter.run_once());
+        assert!(!harness.interpreter.emulator.processor().is_halted());
+        assert!(!harness.interpreter.program_idle);
+    }
+
+    #[test]
+    fn test_dump_memory() {
+        let mut harness = harness(
+            vec![
+                0x60, 0x2A, // LD V0, 0x2A
+                0xA3, 0x00, // LD I, 0x300
+                0xF0, 0x55, // LD [I], V0
+                0x12, 0x06, // JP 0x206
+            ],
+            false,
+        );
+        let path = std::env::temp_dir().join(format!("whip-8-dump-{}.bin", std::process::id()));
+        harness.interpreter.set_dumps(Some(path.clone()), false);
+
+        for _ in 0..3 {
+            assert!(harness.interpreter.run_once());
+        }
+        harness.controls.send(ControlMessage::Dump).unwrap();
+        assert!(harness.interpreter.run_once());
+
+        let dump = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(dump.len(), 0x1000);
+        assert_eq!(dump[0x200..0x202], [0x60, 0x2A]);
+        assert_eq!(dump[0x300], 0x2A);
+    }
+
+    #[test]
+    fn test_breakpoint_pauses() {
+        let mut harness = harness(
+            vec![
+                0x70, 0x01, // ADD V0, 0x01
+                0x12, 0x00, // JP 0x200
+            ],
+            false,
+        );
+        harness.interpreter.set_breakpoints(&[0x202]).unwrap();
+
+        for _ in 0..3 {
+            assert!(harness.interpreter.run_once());
+        }
+        assert!(harness.paused.load(Ordering::SeqCst));
+        let processor = harness.interpreter.emulator.processor();
+        assert_eq!(processor.program_counter(), Address::from(0x202));
+        assert_eq!(processor.general_register(GeneralRegister::V0), 1);
+
+        // stepping from the breakpoint runs the instruction there
+        harness.controls.send(ControlMessage::Step).unwrap();
+        assert!(harness.interpreter.run_once());
+        assert_eq!(
+            harness.interpreter.emulator.processor().program_counter(),
+            Address::from(0x200)
+        );
+    }
+
+    #[test]
+    fn test_rewind() {
+        let mut harness = harness(
+            vec![
+                0x70, 0x01, // ADD V0, 0x01
+                0x12, 0x00, // JP 0x200
+            ],
+            false,
+        );
+
+        assert!(harness.interpreter.run_once());
+        harness.timer.send(1).unwrap();
+        assert!(harness.interpreter.run_once());
+        assert!(harness.interpreter.run_once());
+        let v0 = |harness: &Harness| {
+            harness
+                .interpreter
+                .emulator
+                .processor()
+                .general_register(GeneralRegister::V0)
+        };
+        assert_eq!(v0(&harness), 2);
+
+        harness.paused.store(true, Ordering::SeqCst);
+        harness.controls.send(ControlMessage::Rewind).unwrap();
+        assert!(harness.interpreter.run_once());
+        assert_eq!(v0(&harness), 1);
+        assert_eq!(
+            harness.interpreter.emulator.processor().program_counter(),
+            Address::from(0x200)
+        );
+
+        // nothing left, so nothing changes
+        harness.controls.send(ControlMessage::Rewind).unwrap();
+        assert!(harness.interpreter.run_once());
+        assert_eq!(v0(&harness), 1);
+    }
+
+    #[test]
+    fn test_set_speed() {
+        let mut harness = harness(
+            vec![
+                0x12, 0x00, // JP 0x200
+            ],
+            false,
+        );
+
+        harness
+            .controls
+            .send(ControlMessage::SetSpeed(1.0))
+            .unwrap();
+        assert!(harness.interpreter.run_once());
+        assert!(harness.interpreter.clock.until_next(Instant::now()) > Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_restart_without_file() {
+        let mut harness = harness(
+            vec![
+                0x60, 0x10, // LD V0, 0x10
+                0x12, 0x02, // JP 0x202
+            ],
+            false,
+        );
+
+        assert!(harness.interpreter.run_once());
+        assert_eq!(
+            harness.interpreter.emulator.processor().program_counter(),
+            Address::from(0x202)
+        );
+
+        harness.paused.store(true, Ordering::SeqCst);
+        harness.controls.send(ControlMessage::Restart).unwrap();
+        assert!(harness.interpreter.run_once());
+        assert_eq!(
+            harness.interpreter.emulator.processor().program_counter(),
+            Address::from(0x200)
+        );
+    }
+
+    #[test]
+    fn test_restart_missing_file_keeps_running() {
+        let mut harness = harness(
+            vec![
+                0x60, 0x10, // LD V0, 0x10
+                0x12, 0x02, // JP 0x202
+            ],
+            false,
+        );
+        harness
+            .interpreter
+            .set_rom_path(PathBuf::from("/nonexistent/whip-8/rom.ch8"));
+
+        assert!(harness.interpreter.run_once());
+        harness.controls.send(ControlMessage::Restart).unwrap();
+        assert!(harness.interpreter.run_once());
+        assert!(!harness.interpreter.exit_requested.load(Ordering::SeqCst));
+        assert_eq!(
+            harness
+                .interpreter
+                .emulator
+                .processor()
+                .general_register(GeneralRegister::V0),
+            0x10
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use std::time::{Duration, Instant};
+
+// After a stall, such as the window being dragged, at most this many owed
+// instructions are run at once. The rest are dropped rather than raced through.
+const MAX_CATCH_UP: usize = 64;
+
+// Bounds and step size for changing the speed while running. Each press scales
+// the rate, so a few presses cover the range from crawling to flat out.
+const MIN_ADJUSTED_IPS: f64 = 30.0;
+const MAX_ADJUSTED_IPS: f64 = 50_000.0;
+const SPEED_FACTOR: f64 = 1.5;
+
+// An unthrottled clock (0 IPS) can't go any faster.
+pub fn speed_up(instructions_per_second: f64) -> f64 {
+    if instructions_per_second <= 0.0 {
+        return instructions_per_second;
+    }
+    (instructions_per_second * SPEED_FACTOR).clamp(MIN_ADJUSTED_IPS, MAX_ADJUSTED_IPS)
+}
+
+// Slowing an unthrottled clock starts from the fastest adjustable rate.
+pub fn slow_down(instructions_per_second: f64) -> f64 {
+    if instructions_per_second <= 0.0 {
+        return MAX_ADJUSTED_IPS;
+    }
+    (instructions_per_second / SPEED_FACTOR).clamp(MIN_ADJUSTED_IPS, MAX_ADJUSTED_IPS)
+}
+
+// Paces instructions to a fixed rate. Like Timer counting 60Hz ticks, every
+// instruction period that has passed since the last call makes a step due.
+pub struct InstructionClock {
+    period: Duration,
+    next: Option<Instant>,
+}
+
+impl InstructionClock {
+    // A zero period runs one instruction per call, as fast as the caller loops.
+    pub fn new(period: Duration) -> Self {
+        InstructionClock { period, next: None }
+    }
+
+    pub fn from_ips(instructions_per_second: f64) -> Self {
+        if instructions_per_second > 0.0 {
+            InstructionClock::new(Duration::from_secs_f64(1.0 / instructions_per_second))
+        } else {
+            InstructionClock::new(Duration::ZERO)
+        }
+    }
+
+    pub fn steps_due(&mut self, now: Instant) -> usize {
+        if self.period.is_zero() {
+            return 1;
+        }
+
+        let next = self.next.get_or_insert(now);
+        let mut steps = 0;
+        while now >= *next && steps < MAX_CATCH_UP {
+            steps += 1;
+            *next += self.period;
+        }
+        if now >= *next {
+            *next = now + self.period;
+        }
+        steps
+    }
+
+    pub fn until_next(&self, now: Instant) -> Duration {
+        match self.next {
+            Some(next) => next.saturating_duration_since(now),
+            None => Duration::ZERO,
+        }
+    }
+
+    // Starts counting afresh, so time spent paused isn't caught up on.
+    pub fn reset(&mut self) {
+        self.next = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_paced_by_period() {
+        let start = Instant::now();
+        let mut clock = InstructionClock::from_ips(1000.0);
+
+        assert_eq!(clock.steps_due(start), 1);
+        assert_eq!(clock.steps_due(start + Duration::from_micros(500)), 0);
+        assert_eq!(clock.steps_due(start + Duration::from_millis(1)), 1);
+        assert_eq!(clock.steps_due(start + Duration::from_millis(4)), 3);
+        assert_eq!(
+            clock.until_next(start + Duration::from_millis(4)),
+            Duration::from_millis(1)
+        );
+    }
+
+    #[test]
+    fn test_catch_up_limited() {
+        let start = Instant::now();
+        let mut clock = InstructionClock::from_ips(1000.0);
+        clock.steps_due(start);
+
+        let later = start + Duration::from_secs(1);
+        assert_eq!(clock.steps_due(later), MAX_CATCH_UP);
+        assert_eq!(clock.steps_due(later), 0);
+        assert_eq!(clock.steps_due(later + Duration::from_millis(1)), 1);
+    }
+
+    #[test]
+    fn test_unlimited_and_reset() {
+        let start = Instant::now();
+        let mut clock = InstructionClock::from_ips(0.0);
+        assert_eq!(clock.steps_due(start), 1);
+        assert_eq!(clock.steps_due(start), 1);
+
+        let mut clock = InstructionClock::from_ips(1000.0);
+        clock.steps_due(start);
+        clock.reset();
+        assert_eq!(clock.steps_due(start + Duration::from_secs(1)), 1);
+    }
+
+    #[test]
+    fn test_speed_adjustment() {
+        assert_eq!(speed_up(700.0), 1050.0);
+        assert_eq!(slow_down(1050.0), 700.0);
+
+        assert_eq!(speed_up(40_000.0), MAX_ADJUSTED_IPS);
+        assert_eq!(slow_down(40.0), MIN_ADJUSTED_IPS);
+        assert_eq!(speed_up(MAX_ADJUSTED_IPS), MAX_ADJUSTED_IPS);
+        assert_eq!(slow_down(MIN_ADJUSTED_IPS), MIN_ADJUSTED_IPS);
+
+        assert_eq!(speed_up(0.0), 0.0);
+        assert_eq!(slow_down(0.0), MAX_ADJUSTED_IPS);
+    }
+}
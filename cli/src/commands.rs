@@ -1,8 +1,559 @@
-use clap::Parser;
+use crate::audio::Waveform;
+use crate::controls::{parse_binding, ControlAction};
+use crate::keymap::{parse_key_mapping, KeyMapping};
+use clap::{Parser, ValueEnum};
+use interpreter::cheats::{parse_cheat, Cheat};
+use interpreter::instructions::InstructionSet;
+use interpreter::processor::{Config, Quirks};
 use std::path::PathBuf;
+use std::time::Duration;
+use winit::keyboard::KeyCode;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 pub struct Args {
-    pub path: PathBuf,
+    /// ROM file to run, or - to read it from stdin
+    #[arg(required_unless_present = "demo")]
+    pub path: Option<PathBuf>,
+
+    /// Cycle through the built-in ROMs instead of loading a file
+    #[arg(long, conflicts_with = "path")]
+    pub demo: bool,
+
+    /// How long each ROM runs in demo mode, in seconds
+    #[arg(long, default_value_t = 30.0, value_parser = parse_seconds)]
+    pub demo_seconds: f64,
+
+    /// Instructions run per second. 0 runs them as fast as possible
+    #[arg(long, default_value_t = 700.0, value_parser = parse_ips)]
+    pub ips: f64,
+
+    /// Maximum number of frames per second sent to the window, however fast the
+    /// program draws
+    #[arg(long, default_value_t = 60.0, value_parser = parse_frequency)]
+    pub refresh_hz: f64,
+
+    /// Reload and restart the ROM whenever the file changes
+    #[arg(long, requires = "path")]
+    pub watch: bool,
+
+    /// Start paused at the first instruction, ready to be single-stepped. Each
+    /// step logs the instruction it ran
+    #[arg(long, visible_alias = "step")]
+    pub debug: bool,
+
+    /// Run until the instruction at this hex address is next, then pause ready
+    /// to be single-stepped, e.g. `--break 0x2A8`. Can be given more than once.
+    /// Headless runs can't pause
+    #[arg(
+        long = "break",
+        value_name = "ADDR",
+        value_parser = parse_address,
+        conflicts_with_all = ["register_csv", "input_script", "trace_draws"]
+    )]
+    pub breakpoints: Vec<u16>,
+
+    /// Size the window for SUPER-CHIP hires if the ROM switches to it early
+    #[arg(long)]
+    pub detect_resolution: bool,
+
+    /// Warn when a value loaded into VF is overwritten by a flag before being read
+    #[arg(long)]
+    pub warn_vf_clobber: bool,
+
+    /// Draw the display in the terminal with block characters instead of
+    /// opening a window. Ctrl-C quits, and the controls work as in the window
+    /// apart from fullscreen
+    #[arg(long, conflicts_with = "virtual_keypad")]
+    pub term: bool,
+
+    /// Window pixels per CHIP-8 pixel when the window opens. Resizing the window
+    /// keeps pixels a whole number of window pixels across, with a border in the
+    /// background colour around the display
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    pub scale: u32,
+
+    /// Colour of lit pixels, as RRGGBB hex
+    #[arg(long, value_name = "RRGGBB", default_value = "5E48E8", value_parser = parse_colour)]
+    pub fg: [u8; 4],
+
+    /// Colour of unlit pixels, as RRGGBB hex
+    #[arg(long, value_name = "RRGGBB", default_value = "101010", value_parser = parse_colour)]
+    pub bg: [u8; 4],
+
+    /// Colour of pixels lit only in the second XO-CHIP plane, as RRGGBB hex
+    #[arg(long, value_name = "RRGGBB", default_value = "FF6600", value_parser = parse_colour)]
+    pub fg2: [u8; 4],
+
+    /// Colour of pixels lit in both XO-CHIP planes, as RRGGBB hex
+    #[arg(long, value_name = "RRGGBB", default_value = "662200", value_parser = parse_colour)]
+    pub blend: [u8; 4],
+
+    /// Show a clickable hex keypad below the display
+    #[arg(long)]
+    pub virtual_keypad: bool,
+
+    /// Warn about any instructions in the ROM beyond this instruction set:
+    /// chip-8, schip or xo-chip
+    #[arg(long, value_name = "SET", value_parser = parse_instruction_set)]
+    pub check_opcodes: Option<InstructionSet>,
+
+    /// Refuse to run ROMs with instructions beyond the `--check-opcodes` set,
+    /// rather than warning about them
+    #[arg(long, requires = "check_opcodes")]
+    pub reject_unsupported_opcodes: bool,
+
+    /// Stop the program at a 0x0000 opcode instead of ignoring it
+    #[arg(long)]
+    pub halt_on_zero_opcode: bool,
+
+    /// Treat 0NNN (SYS) opcodes as invalid instead of ignoring them, to catch
+    /// programs that jump into data
+    #[arg(long)]
+    pub sys_is_invalid: bool,
+
+    /// Wait for the next 60Hz frame before each draw, like the COSMAC VIP
+    #[arg(long)]
+    pub display_wait: bool,
+
+    /// Interpreter whose quirks to copy. `xo-chip` also turns on `--xo-chip`
+    #[arg(long, value_enum, default_value_t = Compat::Vip)]
+    pub compat: Compat,
+
+    /// Turn a single quirk on or off after `--compat`, e.g.
+    /// `--quirk clip-sprites=off`. A bare name turns it on. Can be given more
+    /// than once
+    #[arg(long = "quirk", value_name = "QUIRK[=on|off]", value_parser = parse_quirk)]
+    pub quirk_overrides: Vec<(Quirk, bool)>,
+
+    /// Run XO-CHIP programs, with 64KB of memory, a second display plane and
+    /// audio patterns
+    #[arg(long)]
+    pub xo_chip: bool,
+
+    /// Step over opcodes that don't decode, with a warning, instead of stopping
+    #[arg(long)]
+    pub skip_invalid_instructions: bool,
+
+    /// Waveform of the sound timer's beep
+    #[arg(long, value_enum, default_value_t = Waveform::Square)]
+    pub waveform: Waveform,
+
+    /// Pitch of the beep in Hz
+    #[arg(long, default_value_t = 440.0, value_parser = parse_tone_hz)]
+    pub tone_hz: f32,
+
+    /// Beep volume, from 0.0 to 1.0
+    #[arg(long, default_value_t = 0.25)]
+    pub volume: f32,
+
+    /// Frames of history kept for rewinding, one frame per press of the rewind
+    /// key. 0 turns rewinding off
+    #[arg(long, default_value_t = 600)]
+    pub rewind_frames: usize,
+
+    /// Directory screenshots are saved to
+    #[arg(long, default_value = ".")]
+    pub screenshot_dir: PathBuf,
+
+    /// File the dump key writes the whole of memory to, replacing what was there
+    #[arg(long, value_name = "FILE")]
+    pub dump_memory: Option<PathBuf>,
+
+    /// Make the dump key print V0-VF, I, PC, SP and the timers to stderr
+    #[arg(long)]
+    pub dump_registers: bool,
+
+    /// Rebind a control key, e.g. `--bind pause=k`. Actions: quit, pause, step,
+    /// reset, screenshot, fullscreen, faster, slower, rewind, dump
+    #[arg(long = "bind", value_name = "ACTION=KEY", value_parser = parse_binding)]
+    pub bindings: Vec<(ControlAction, KeyCode)>,
+
+    /// Map a keyboard key to a CHIP-8 key, e.g. `--map-key up=5`. Keys are named
+    /// as for `--bind` and CHIP-8 keys are hex. The rest of the default layout
+    /// stays in place
+    #[arg(long = "map-key", value_name = "KEY=CHIP8_KEY", value_parser = parse_key_mapping)]
+    pub key_mappings: Vec<KeyMapping>,
+
+    /// File of key mappings in the `--map-key` format, one per line. `#` starts
+    /// a comment
+    #[arg(long)]
+    pub keymap: Option<PathBuf>,
+
+    /// Poke a byte into memory every frame, e.g. `--cheat 0x3A0=09`. Append
+    /// `,once` to only poke it at startup. Both numbers are hex
+    #[arg(long = "cheat", value_name = "ADDR=VALUE", value_parser = parse_cheat)]
+    pub cheats: Vec<Cheat>,
+
+    /// File of cheats in the `--cheat` format, one per line. `#` starts a comment
+    #[arg(long)]
+    pub cheat_file: Option<PathBuf>,
+
+    /// Run without a window, writing PC, I, V0-VF and the timers to this CSV
+    /// file once per frame
+    #[arg(long, value_name = "FILE")]
+    pub register_csv: Option<PathBuf>,
+
+    /// Run without a window, pressing keys as listed in this file. Each line is
+    /// `FRAME KEY press|release`, e.g. `30 5 press`. Prints a hash of the final
+    /// state when done
+    #[arg(long, value_name = "FILE")]
+    pub input_script: Option<PathBuf>,
+
+    /// Run without a window, printing the whole display as text after every
+    /// draw instruction
+    #[arg(long)]
+    pub trace_draws: bool,
+
+    /// Number of frames to run for without a window
+    #[arg(long, default_value_t = 600)]
+    pub frames: usize,
+
+    /// Seed for the random number generator. Runs without a window use 0 when
+    /// this isn't given
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+// Accepts "RRGGBB", optionally with a leading '#'. Colours are always opaque.
+pub fn parse_colour(spec: &str) -> Result<[u8; 4], String> {
+    let hex = spec.strip_prefix('#').unwrap_or(spec);
+    let error = || format!("Expected a colour as RRGGBB hex, got '{}'", spec);
+    if hex.len() != 6 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(error());
+    }
+
+    let [_, red, green, blue] = u32::from_str_radix(hex, 16)
+        .map_err(|_| error())?
+        .to_be_bytes();
+    Ok([red, green, blue, 0xFF])
+}
+
+pub fn parse_instruction_set(spec: &str) -> Result<InstructionSet, String> {
+    match spec.to_ascii_lowercase().as_str() {
+        "chip-8" | "chip8" => Ok(InstructionSet::Chip8),
+        "schip" | "super-chip" => Ok(InstructionSet::SuperChip),
+        "xo-chip" | "xochip" => Ok(InstructionSet::XoChip),
+        _ => Err(format!("Expected chip-8, schip or xo-chip, got '{}'", spec)),
+    }
+}
+
+// Accepts up to four hex digits, optionally with a leading "0x".
+pub fn parse_address(spec: &str) -> Result<u16, String> {
+    let hex = spec.strip_prefix("0x").unwrap_or(spec);
+    if hex.is_empty() || hex.len() > 4 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(format!("Expected an address as hex, got '{}'", spec));
+    }
+    u16::from_str_radix(hex, 16).map_err(|err| err.to_string())
+}
+
+// Accepts a finite number above zero.
+fn parse_positive(spec: &str) -> Result<f64, String> {
+    match spec.parse::<f64>() {
+        Ok(value) if value.is_finite() && value > 0.0 => Ok(value),
+        _ => Err(format!("Expected a number above 0, got '{}'", spec)),
+    }
+}
+
+// Accepts a length of time above zero that fits in a Duration.
+pub fn parse_seconds(spec: &str) -> Result<f64, String> {
+    let seconds = parse_positive(spec)?;
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(_) => Ok(seconds),
+        Err(_) => Err(format!("'{}' is too long", spec)),
+    }
+}
+
+// Accepts a rate in Hz whose period fits in a Duration.
+pub fn parse_frequency(spec: &str) -> Result<f64, String> {
+    let hz = parse_positive(spec)?;
+    match Duration::try_from_secs_f64(1.0 / hz) {
+        Ok(_) => Ok(hz),
+        Err(_) => Err(format!("'{}' is too low a rate", spec)),
+    }
+}
+
+// Like parse_frequency, but 0 is allowed for running flat out.
+pub fn parse_ips(spec: &str) -> Result<f64, String> {
+    match spec.parse::<f64>() {
+        Ok(ips) if ips == 0.0 => Ok(ips),
+        _ => parse_frequency(spec),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compat {
+    Vip,
+    Schip,
+    XoChip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Quirk {
+    ShiftUsesVy,
+    IncrementIOnStore,
+    JumpUsesVx,
+    ResetVfOnLogic,
+    ClipSprites,
+    MemoryMappedDisplay,
+    WrapMemory,
+    HaltOnZeroOpcode,
+    SysIsInvalid,
+    DisplayWait,
+    AddISetsVfOnOverflow,
+}
+
+impl Quirk {
+    fn setting(self, quirks: &mut Quirks) -> &mut bool {
+        match self {
+            Quirk::ShiftUsesVy => &mut quirks.shift_uses_vy,
+            Quirk::IncrementIOnStore => &mut quirks.increment_i_on_store,
+            Quirk::JumpUsesVx => &mut quirks.jump_uses_vx,
+            Quirk::ResetVfOnLogic => &mut quirks.reset_vf_on_logic,
+            Quirk::ClipSprites => &mut quirks.clip_sprites,
+            Quirk::MemoryMappedDisplay => &mut quirks.memory_mapped_display,
+            Quirk::WrapMemory => &mut quirks.wrap_memory,
+            Quirk::HaltOnZeroOpcode => &mut quirks.halt_on_zero_opcode,
+            Quirk::SysIsInvalid => &mut quirks.sys_is_invalid,
+            Quirk::DisplayWait => &mut quirks.display_wait,
+            Quirk::AddISetsVfOnOverflow => &mut quirks.add_i_sets_vf_on_overflow,
+        }
+    }
+}
+
+pub fn parse_quirk(spec: &str) -> Result<(Quirk, bool), String> {
+    let (name, value) = spec.split_once('=').unwrap_or((spec, "on"));
+    let quirk = Quirk::from_str(name, true).map_err(|_| {
+        let names: Vec<_> = Quirk::value_variants()
+            .iter()
+            .filter_map(|quirk| quirk.to_possible_value())
+            .map(|value| value.get_name().to_string())
+            .collect();
+        format!(
+            "Unknown quirk '{}', expected one of {}",
+            name,
+            names.join(", ")
+        )
+    })?;
+    let enabled = match value {
+        "on" | "true" => true,
+        "off" | "false" => false,
+        _ => return Err(format!("Expected on or off for {}, got '{}'", name, value)),
+    };
+    Ok((quirk, enabled))
+}
+
+pub fn parse_tone_hz(spec: &str) -> Result<f32, String> {
+    let hz = parse_positive(spec)? as f32;
+    if hz.is_finite() {
+        Ok(hz)
+    } else {
+        Err(format!("'{}' is too high a pitch", spec))
+    }
+}
+
+impl Args {
+    pub fn config(&self) -> Config {
+        Config {
+            xo_chip: self.xo_chip || self.compat == Compat::XoChip,
+            skip_invalid_instructions: self.skip_invalid_instructions,
+            ..Config::default()
+        }
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        let mut quirks = match self.compat {
+            Compat::Vip => Quirks::default(),
+            Compat::Schip => Quirks::super_chip(),
+            Compat::XoChip => Quirks::xo_chip(),
+        };
+        quirks.halt_on_zero_opcode |= self.halt_on_zero_opcode;
+        quirks.sys_is_invalid |= self.sys_is_invalid;
+        quirks.display_wait |= self.display_wait;
+        for (quirk, enabled) in &self.quirk_overrides {
+            *quirk.setting(&mut quirks) = *enabled;
+        }
+        quirks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_colour() {
+        assert_eq!(parse_colour("5E48E8"), Ok([0x5E, 0x48, 0xE8, 0xFF]));
+        assert_eq!(parse_colour("#ff8000"), Ok([0xFF, 0x80, 0x00, 0xFF]));
+        assert_eq!(parse_colour("000000"), Ok([0x00, 0x00, 0x00, 0xFF]));
+    }
+
+    #[test]
+    fn test_parse_colour_errors() {
+        for spec in ["", "#", "FFF", "FFFFFFFF", "GG0000", "+12345", "#12 456"] {
+            assert_eq!(
+                parse_colour(spec),
+                Err(format!("Expected a colour as RRGGBB hex, got '{}'", spec))
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_opcodes_args() {
+        let args = Args::parse_from(["whip-8", "rom.ch8"]);
+        assert_eq!(args.check_opcodes, None);
+        let args = Args::parse_from(["whip-8", "rom.ch8", "--check-opcodes", "SCHIP"]);
+        assert_eq!(args.check_opcodes, Some(InstructionSet::SuperChip));
+
+        assert!(Args::try_parse_from(["whip-8", "rom.ch8", "--check-opcodes", "vip"]).is_err());
+        // rejecting needs a set to check against
+        assert!(
+            Args::try_parse_from(["whip-8", "rom.ch8", "--reject-unsupported-opcodes"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_config_args() {
+        let config = Args::parse_from(["whip-8", "rom.ch8"]).config();
+        assert!(!config.xo_chip);
+        assert!(!config.skip_invalid_instructions);
+
+        let args = [
+            "whip-8",
+            "rom.ch8",
+            "--xo-chip",
+            "--skip-invalid-instructions",
+        ];
+        let config = Args::parse_from(args).config();
+        assert!(config.xo_chip);
+        assert!(config.skip_invalid_instructions);
+    }
+
+    #[test]
+    fn test_parse_quirk() {
+        assert_eq!(
+            parse_quirk("clip-sprites=off"),
+            Ok((Quirk::ClipSprites, false))
+        );
+        assert_eq!(parse_quirk("jump-uses-vx"), Ok((Quirk::JumpUsesVx, true)));
+        assert_eq!(
+            parse_quirk("wrap-memory=true"),
+            Ok((Quirk::WrapMemory, true))
+        );
+        for spec in ["", "clip_sprites", "clip-sprites=maybe", "=on"] {
+            assert!(parse_quirk(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_quirk_args() {
+        assert_eq!(
+            Args::parse_from(["whip-8", "rom.ch8"]).quirks(),
+            Quirks::default()
+        );
+
+        let args = Args::parse_from(["whip-8", "rom.ch8", "--compat", "schip"]);
+        assert_eq!(args.quirks(), Quirks::super_chip());
+        assert!(!args.config().xo_chip);
+
+        let args = Args::parse_from([
+            "whip-8",
+            "rom.ch8",
+            "--compat",
+            "xo-chip",
+            "--display-wait",
+            "--quirk",
+            "clip-sprites",
+            "--quirk",
+            "shift-uses-vy=off",
+        ]);
+        let quirks = args.quirks();
+        assert!(quirks.display_wait);
+        assert!(quirks.clip_sprites);
+        assert!(!quirks.shift_uses_vy);
+        assert!(!quirks.reset_vf_on_logic);
+        assert!(args.config().xo_chip);
+    }
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(parse_address("0x2A8"), Ok(0x2A8));
+        assert_eq!(parse_address("2a8"), Ok(0x2A8));
+        assert_eq!(parse_address("FFFF"), Ok(0xFFFF));
+        for spec in ["", "0x", "10000", "0x2G8", "-1"] {
+            assert!(parse_address(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_breakpoint_args() {
+        let args = Args::parse_from(["whip-8", "rom.ch8", "--break", "0x2A8", "--break", "300"]);
+        assert_eq!(args.breakpoints, [0x2A8, 0x300]);
+
+        assert!(Args::try_parse_from(["whip-8", "rom.ch8", "--break", "300", "--term"]).is_ok());
+        assert!(
+            Args::try_parse_from(["whip-8", "rom.ch8", "--break", "300", "--trace-draws"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_seconds() {
+        assert_eq!(parse_seconds("30"), Ok(30.0));
+        assert_eq!(parse_seconds("0.25"), Ok(0.25));
+        for spec in ["0", "-30", "NaN", "inf", "1e300", "soon"] {
+            assert!(parse_seconds(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_parse_frequency() {
+        assert_eq!(parse_frequency("60"), Ok(60.0));
+        assert_eq!(parse_frequency("0.5"), Ok(0.5));
+        for spec in ["0", "-60", "NaN", "inf", "1e-30", "fast"] {
+            assert!(parse_frequency(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_parse_ips() {
+        assert_eq!(parse_ips("700"), Ok(700.0));
+        assert_eq!(parse_ips("0"), Ok(0.0));
+        for spec in ["-700", "NaN", "inf", "1e-300"] {
+            assert!(parse_ips(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_parse_tone_hz() {
+        assert_eq!(parse_tone_hz("440"), Ok(440.0));
+        assert_eq!(parse_tone_hz("27.5"), Ok(27.5));
+        for spec in ["0", "-440", "NaN", "inf", "1e300", "loud"] {
+            assert!(parse_tone_hz(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_colour_args() {
+        let args = Args::parse_from(["whip-8", "rom.ch8", "--fg", "FFFFFF"]);
+        assert_eq!(args.fg, [0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(args.bg, [0x10, 0x10, 0x10, 0xFF]);
+
+        assert!(Args::try_parse_from(["whip-8", "rom.ch8", "--bg", "nope"]).is_err());
+    }
+
+    #[test]
+    fn test_scale_arg() {
+        assert_eq!(Args::parse_from(["whip-8", "rom.ch8"]).scale, 10);
+        assert_eq!(
+            Args::parse_from(["whip-8", "rom.ch8", "--scale", "4"]).scale,
+            4
+        );
+        assert!(Args::try_parse_from(["whip-8", "rom.ch8", "--scale", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_stdin_path() {
+        let args = Args::parse_from(["whip-8", "-"]);
+        assert_eq!(args.path, Some(PathBuf::from("-")));
+    }
 }
@@ -0,0 +1,284 @@
+use winit::keyboard::KeyCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlAction {
+    Quit,
+    Pause,
+    Step,
+    Reset,
+    Screenshot,
+    Fullscreen,
+    SpeedUp,
+    SlowDown,
+    Rewind,
+    Dump,
+}
+
+impl ControlAction {
+    pub const ALL: [ControlAction; 10] = [
+        ControlAction::Quit,
+        ControlAction::Pause,
+        ControlAction::Step,
+        ControlAction::Reset,
+        ControlAction::Screenshot,
+        ControlAction::Fullscreen,
+        ControlAction::SpeedUp,
+        ControlAction::SlowDown,
+        ControlAction::Rewind,
+        ControlAction::Dump,
+    ];
+
+    fn from_name(name: &str) -> Option<ControlAction> {
+        match name.to_ascii_lowercase().as_str() {
+            "quit" => Some(ControlAction::Quit),
+            "pause" => Some(ControlAction::Pause),
+            "step" => Some(ControlAction::Step),
+            "reset" => Some(ControlAction::Reset),
+            "screenshot" => Some(ControlAction::Screenshot),
+            "fullscreen" => Some(ControlAction::Fullscreen),
+            "faster" => Some(ControlAction::SpeedUp),
+            "slower" => Some(ControlAction::SlowDown),
+            "rewind" => Some(ControlAction::Rewind),
+            "dump" => Some(ControlAction::Dump),
+            _ => None,
+        }
+    }
+}
+
+// Defaults stay clear of the keys used by the CHIP-8 keypad layout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlBindings {
+    pub quit: KeyCode,
+    pub pause: KeyCode,
+    pub step: KeyCode,
+    pub reset: KeyCode,
+    pub screenshot: KeyCode,
+    pub fullscreen: KeyCode,
+    pub speed_up: KeyCode,
+    pub slow_down: KeyCode,
+    pub rewind: KeyCode,
+    pub dump: KeyCode,
+}
+
+impl Default for ControlBindings {
+    fn default() -> Self {
+        ControlBindings {
+            quit: KeyCode::Escape,
+            pause: KeyCode::Space,
+            step: KeyCode::KeyN,
+            reset: KeyCode::F5,
+            screenshot: KeyCode::KeyP,
+            fullscreen: KeyCode::F11,
+            // the +/= key, so + works without shift
+            speed_up: KeyCode::Equal,
+            slow_down: KeyCode::Minus,
+            rewind: KeyCode::Backspace,
+            dump: KeyCode::KeyM,
+        }
+    }
+}
+
+impl ControlBindings {
+    pub fn key_for(&self, action: ControlAction) -> KeyCode {
+        match action {
+            ControlAction::Quit => self.quit,
+            ControlAction::Pause => self.pause,
+            ControlAction::Step => self.step,
+            ControlAction::Reset => self.reset,
+            ControlAction::Screenshot => self.screenshot,
+            ControlAction::Fullscreen => self.fullscreen,
+            ControlAction::SpeedUp => self.speed_up,
+            ControlAction::SlowDown => self.slow_down,
+            ControlAction::Rewind => self.rewind,
+            ControlAction::Dump => self.dump,
+        }
+    }
+
+    pub fn bind(&mut self, action: ControlAction, key: KeyCode) {
+        match action {
+            ControlAction::Quit => self.quit = key,
+            ControlAction::Pause => self.pause = key,
+            ControlAction::Step => self.step = key,
+            ControlAction::Reset => self.reset = key,
+            ControlAction::Screenshot => self.screenshot = key,
+            ControlAction::Fullscreen => self.fullscreen = key,
+            ControlAction::SpeedUp => self.speed_up = key,
+            ControlAction::SlowDown => self.slow_down = key,
+            ControlAction::Rewind => self.rewind = key,
+            ControlAction::Dump => self.dump = key,
+        }
+    }
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 33] = [
+    ("escape", KeyCode::Escape),
+    ("space", KeyCode::Space),
+    ("enter", KeyCode::Enter),
+    ("tab", KeyCode::Tab),
+    ("backspace", KeyCode::Backspace),
+    ("minus", KeyCode::Minus),
+    ("equal", KeyCode::Equal),
+    ("comma", KeyCode::Comma),
+    ("period", KeyCode::Period),
+    ("slash", KeyCode::Slash),
+    ("semicolon", KeyCode::Semicolon),
+    ("up", KeyCode::ArrowUp),
+    ("down", KeyCode::ArrowDown),
+    ("left", KeyCode::ArrowLeft),
+    ("right", KeyCode::ArrowRight),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("insert", KeyCode::Insert),
+    ("delete", KeyCode::Delete),
+    ("f1", KeyCode::F1),
+    ("f2", KeyCode::F2),
+    ("f3", KeyCode::F3),
+    ("f4", KeyCode::F4),
+    ("f5", KeyCode::F5),
+    ("f6", KeyCode::F6),
+    ("f7", KeyCode::F7),
+    ("f8", KeyCode::F8),
+    ("f9", KeyCode::F9),
+    ("f10", KeyCode::F10),
+    ("f11", KeyCode::F11),
+    ("f12", KeyCode::F12),
+];
+
+const LETTER_KEYS: [KeyCode; 26] = [
+    KeyCode::KeyA,
+    KeyCode::KeyB,
+    KeyCode::KeyC,
+    KeyCode::KeyD,
+    KeyCode::KeyE,
+    KeyCode::KeyF,
+    KeyCode::KeyG,
+    KeyCode::KeyH,
+    KeyCode::KeyI,
+    KeyCode::KeyJ,
+    KeyCode::KeyK,
+    KeyCode::KeyL,
+    KeyCode::KeyM,
+    KeyCode::KeyN,
+    KeyCode::KeyO,
+    KeyCode::KeyP,
+    KeyCode::KeyQ,
+    KeyCode::KeyR,
+    KeyCode::KeyS,
+    KeyCode::KeyT,
+    KeyCode::KeyU,
+    KeyCode::KeyV,
+    KeyCode::KeyW,
+    KeyCode::KeyX,
+    KeyCode::KeyY,
+    KeyCode::KeyZ,
+];
+
+const DIGIT_KEYS: [KeyCode; 10] = [
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+// Accepts single letters and digits ("p", "4"), plus the names in NAMED_KEYS.
+pub fn parse_key_code(name: &str) -> Option<KeyCode> {
+    let lower = name.trim().to_ascii_lowercase();
+    if let [byte] = lower.as_bytes() {
+        return match byte {
+            b'a'..=b'z' => Some(LETTER_KEYS[(byte - b'a') as usize]),
+            b'0'..=b'9' => Some(DIGIT_KEYS[(byte - b'0') as usize]),
+            _ => None,
+        };
+    }
+
+    NAMED_KEYS
+        .iter()
+        .find(|(key_name, _)| *key_name == lower)
+        .map(|(_, key_code)| *key_code)
+}
+
+pub fn parse_binding(spec: &str) -> Result<(ControlAction, KeyCode), String> {
+    let (action_name, key_name) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("Expected ACTION=KEY, got '{}'", spec))?;
+    let action = ControlAction::from_name(action_name.trim())
+        .ok_or_else(|| format!("Unknown control action '{}'", action_name.trim()))?;
+    let key = parse_key_code(key_name).ok_or_else(|| format!("Unknown key '{}'", key_name))?;
+    Ok((action, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_bindings() {
+        let bindings = ControlBindings::default();
+        assert_eq!(bindings.key_for(ControlAction::Quit), KeyCode::Escape);
+        assert_eq!(bindings.key_for(ControlAction::Pause), KeyCode::Space);
+        assert_eq!(bindings.key_for(ControlAction::Step), KeyCode::KeyN);
+        assert_eq!(bindings.key_for(ControlAction::Reset), KeyCode::F5);
+        assert_eq!(bindings.key_for(ControlAction::Screenshot), KeyCode::KeyP);
+        assert_eq!(bindings.key_for(ControlAction::Fullscreen), KeyCode::F11);
+        assert_eq!(bindings.key_for(ControlAction::SpeedUp), KeyCode::Equal);
+        assert_eq!(bindings.key_for(ControlAction::SlowDown), KeyCode::Minus);
+        assert_eq!(bindings.key_for(ControlAction::Rewind), KeyCode::Backspace);
+        assert_eq!(bindings.key_for(ControlAction::Dump), KeyCode::KeyM);
+    }
+
+    #[test]
+    fn test_default_bindings_unique() {
+        let bindings = ControlBindings::default();
+        for (idx, action) in ControlAction::ALL.iter().enumerate() {
+            for other in &ControlAction::ALL[idx + 1..] {
+                assert_ne!(bindings.key_for(*action), bindings.key_for(*other));
+            }
+        }
+    }
+
+    #[test]
+    fn test_rebind() {
+        let mut bindings = ControlBindings::default();
+        bindings.bind(ControlAction::Pause, KeyCode::KeyK);
+        assert_eq!(bindings.key_for(ControlAction::Pause), KeyCode::KeyK);
+        assert_eq!(bindings.key_for(ControlAction::Quit), KeyCode::Escape);
+    }
+
+    #[test]
+    fn test_parse_key_code() {
+        assert_eq!(parse_key_code("p"), Some(KeyCode::KeyP));
+        assert_eq!(parse_key_code("P"), Some(KeyCode::KeyP));
+        assert_eq!(parse_key_code("7"), Some(KeyCode::Digit7));
+        assert_eq!(parse_key_code("Space"), Some(KeyCode::Space));
+        assert_eq!(parse_key_code("F12"), Some(KeyCode::F12));
+        assert_eq!(parse_key_code("hyper"), None);
+        assert_eq!(parse_key_code(""), None);
+    }
+
+    #[test]
+    fn test_parse_binding() {
+        assert_eq!(
+            parse_binding("pause=k"),
+            Ok((ControlAction::Pause, KeyCode::KeyK))
+        );
+        assert_eq!(
+            parse_binding("Screenshot=F12"),
+            Ok((ControlAction::Screenshot, KeyCode::F12))
+        );
+        assert_eq!(
+            parse_binding("faster=up"),
+            Ok((ControlAction::SpeedUp, KeyCode::ArrowUp))
+        );
+        assert!(parse_binding("pause").is_err());
+        assert!(parse_binding("jump=k").is_err());
+        assert!(parse_binding("pause=hyper").is_err());
+    }
+}
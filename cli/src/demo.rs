@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+pub struct DemoSchedule {
+    rom_count: usize,
+    rom_duration: Duration,
+    current: usize,
+    switch_at: Instant,
+    consecutive_failures: usize,
+}
+
+impl DemoSchedule {
+    pub fn new(rom_count: usize, rom_duration: Duration, now: Instant) -> Self {
+        DemoSchedule {
+            rom_count,
+            rom_duration,
+            current: 0,
+            switch_at: now + rom_duration,
+            consecutive_failures: 0,
+        }
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn is_due(&self, now: Instant) -> bool {
+        now >= self.switch_at
+    }
+
+    pub fn advance(&mut self, now: Instant) -> usize {
+        self.consecutive_failures = 0;
+        self.rotate(now)
+    }
+
+    // Moves past a ROM that errored. Returns None once every ROM in the rotation
+    // has failed in a row, as there is nothing left worth showing.
+    pub fn skip_failed(&mut self, now: Instant) -> Option<usize> {
+        self.consecutive_failures += 1;
+        if self.consecutive_failures >= self.rom_count {
+            return None;
+        }
+
+        Some(self.rotate(now))
+    }
+
+    fn rotate(&mut self, now: Instant) -> usize {
+        self.current = (self.current + 1) % self.rom_count;
+        self.switch_at = now + self.rom_duration;
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM_DURATION: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn test_switches_after_duration() {
+        let start = Instant::now();
+        let schedule = DemoSchedule::new(3, ROM_DURATION, start);
+        assert_eq!(schedule.current(), 0);
+        assert!(!schedule.is_due(start));
+        assert!(!schedule.is_due(start + ROM_DURATION - Duration::from_millis(1)));
+        assert!(schedule.is_due(start + ROM_DURATION));
+    }
+
+    #[test]
+    fn test_rotation_loops() {
+        let mut now = Instant::now();
+        let mut schedule = DemoSchedule::new(3, ROM_DURATION, now);
+        let mut order = vec![schedule.current()];
+        for _ in 0..4 {
+            now += ROM_DURATION;
+            order.push(schedule.advance(now));
+            assert!(!schedule.is_due(now));
+        }
+
+        assert_eq!(order, vec![0, 1, 2, 0, 1]);
+    }
+
+    #[test]
+    fn test_skip_failed() {
+        let now = Instant::now();
+        let mut schedule = DemoSchedule::new(3, ROM_DURATION, now);
+        assert_eq!(schedule.skip_failed(now), Some(1));
+        assert_eq!(schedule.advance(now + ROM_DURATION), 2);
+
+        // a successful switch resets the failure count
+        assert_eq!(schedule.skip_failed(now), Some(0));
+        assert_eq!(schedule.skip_failed(now), Some(1));
+        assert_eq!(schedule.skip_failed(now), None);
+    }
+}
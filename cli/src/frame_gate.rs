@@ -0,0 +1,70 @@
+use std::time::{Duration, Instant};
+
+// Limits how often frames are sent to the frontend, so a fast CPU drawing many
+// times per refresh only produces one frame.
+pub struct FrameGate {
+    period: Duration,
+    last_sent: Option<Instant>,
+}
+
+impl FrameGate {
+    pub fn new(period: Duration) -> Self {
+        FrameGate {
+            period,
+            last_sent: None,
+        }
+    }
+
+    pub fn from_hz(refresh_hz: f64) -> Self {
+        FrameGate::new(Duration::from_secs_f64(1.0 / refresh_hz))
+    }
+
+    pub fn is_open(&self, now: Instant) -> bool {
+        match self.last_sent {
+            Some(last_sent) => now.duration_since(last_sent) >= self.period,
+            None => true,
+        }
+    }
+
+    pub fn frame_sent(&mut self, now: Instant) {
+        self.last_sent = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_frame_per_period() {
+        let start = Instant::now();
+        let mut gate = FrameGate::from_hz(50.0);
+        assert!(gate.is_open(start));
+        gate.frame_sent(start);
+
+        assert!(!gate.is_open(start + Duration::from_millis(1)));
+        assert!(!gate.is_open(start + Duration::from_millis(19)));
+        assert!(gate.is_open(start + Duration::from_millis(20)));
+
+        gate.frame_sent(start + Duration::from_millis(25));
+        assert!(!gate.is_open(start + Duration::from_millis(40)));
+        assert!(gate.is_open(start + Duration::from_millis(45)));
+    }
+
+    #[test]
+    fn test_draws_within_period_collapse() {
+        // a draw every millisecond for 100ms at 60Hz
+        let start = Instant::now();
+        let mut gate = FrameGate::from_hz(60.0);
+        let mut frames_sent = 0;
+        for ms in 0..100 {
+            let now = start + Duration::from_millis(ms);
+            if gate.is_open(now) {
+                gate.frame_sent(now);
+                frames_sent += 1;
+            }
+        }
+
+        assert_eq!(frames_sent, 6);
+    }
+}
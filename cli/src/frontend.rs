@@ -2,50 +2,126 @@
 // specifically https://github.com/parasyte/pixels/tree/main/examples/minimal-winit
 // See PIXELS_LICENSE.md for the license
 
-use crate::{chip_8_interpreter::KeyUpdate, utils::log_error};
+use crate::{
+    chip_8_interpreter::{ControlMessage, KeyUpdate},
+    clock,
+    controls::{ControlAction, ControlBindings},
+    rate_meter::{RateMeter, Rates},
+    screenshot,
+    utils::log_error,
+    virtual_keypad::{VirtualKeypad, KEYPAD_HEIGHT},
+};
 use grid::Grid;
 use interpreter::{display::Pixel, keypad::KeyStatus};
-use pixels::{Pixels, SurfaceTexture};
+use log::info;
+use pixels::{wgpu, Pixels, SurfaceTexture};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
+    atomic::{AtomicBool, AtomicU64, Ordering},
     mpsc::{Receiver, Sender},
     Arc,
 };
+use std::time::Instant;
 use winit::keyboard::KeyCode;
 use winit::{
-    dpi::LogicalSize,
+    dpi::{LogicalSize, PhysicalSize},
     event::{Event, WindowEvent},
     event_loop::EventLoop,
-    window::{Window, WindowBuilder},
+    window::{Fullscreen, Window, WindowBuilder},
 };
 use winit_input_helper::WinitInputHelper;
 
-const INITIAL_DISPLAY_SCALING: usize = 10;
-
-const KEY_BINDINGS: [KeyCode; 16] = [
-    KeyCode::KeyX,
-    KeyCode::Digit1,
-    KeyCode::Digit2,
-    KeyCode::Digit3,
-    KeyCode::KeyQ,
-    KeyCode::KeyW,
-    KeyCode::KeyE,
-    KeyCode::KeyA,
-    KeyCode::KeyS,
-    KeyCode::KeyD,
-    KeyCode::KeyZ,
-    KeyCode::KeyC,
-    KeyCode::Digit4,
-    KeyCode::KeyR,
-    KeyCode::KeyF,
-    KeyCode::KeyV,
-];
+// winit_input_helper takes mouse buttons as an index, not a MouseButton.
+const LEFT_MOUSE_BUTTON: usize = 0;
+
+// Window or GPU surface creation failed, usually because there's no display
+// server or working graphics driver.
+#[derive(Debug)]
+pub struct GraphicsUnavailable {
+    source: Box<dyn std::error::Error>,
+}
+
+impl GraphicsUnavailable {
+    fn new<E: Into<Box<dyn std::error::Error>>>(source: E) -> Self {
+        GraphicsUnavailable {
+            source: source.into(),
+        }
+    }
+}
+
+impl fmt::Display for GraphicsUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Couldn't open a window: {}. Without working graphics, the ROM can still run \
+             in the terminal with --term, or headless with --input-script or --register-csv.",
+            self.source
+        )
+    }
+}
+
+impl std::error::Error for GraphicsUnavailable {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+fn window_title(paused: bool, instructions_per_second: f64, measured: Option<Rates>) -> String {
+    let speed = if instructions_per_second > 0.0 {
+        format!("{:.0} IPS", instructions_per_second)
+    } else {
+        "unthrottled".to_string()
+    };
+    let measured = match measured {
+        Some(rates) => format!(
+            " ({:.0} FPS, {:.0} IPS measured)",
+            rates.frames_per_second, rates.instructions_per_second
+        ),
+        None => String::new(),
+    };
+    let paused = if paused { " [paused]" } else { "" };
+    format!("WHIP-8 - {}{}{}", speed, measured, paused)
+}
+
+// The surface is cleared in linear colour, while the frame is drawn in sRGB, so
+// the clear colour has to be converted for a border to match the background.
+fn clear_colour(rgba: [u8; 4]) -> wgpu::Color {
+    let linear = |channel: u8| {
+        let channel = channel as f64 / 255.0;
+        if channel <= 0.04045 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    wgpu::Color {
+        r: linear(rgba[0]),
+        g: linear(rgba[1]),
+        b: linear(rgba[2]),
+        a: rgba[3] as f64 / 255.0,
+    }
+}
 
 pub struct FrontendConfig {
     pub width: usize,
     pub height: usize,
+    pub window_width: usize,
+    pub window_height: usize,
     pub off_colour: [u8; 4],
     pub on_colour: [u8; 4],
+    // XO-CHIP programs can light pixels in a second plane, or in both
+    pub second_plane_colour: [u8; 4],
+    pub both_planes_colour: [u8; 4],
+    pub controls: ControlBindings,
+    pub virtual_keypad: bool,
+    pub instructions_per_second: f64,
+    pub screenshot_dir: PathBuf,
+    // Window pixels per CHIP-8 pixel when the window opens. Also used to scale
+    // screenshots.
+    pub scale: usize,
+    pub keymap: HashMap<KeyCode, usize>,
 }
 
 pub struct Frontend {
@@ -54,38 +130,75 @@ pub struct Frontend {
     input: WinitInputHelper,
     window: Window,
     exit_requested: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
     frame_channel: Receiver<Grid<Pixel>>,
     keys_channel: Sender<KeyUpdate>,
+    control_channel: Sender<ControlMessage>,
     image_buffer: Grid<Pixel>,
-    off_colour: [u8; 4],
-    on_colour: [u8; 4],
+    // Indexed by Pixel::planes.
+    palette: [[u8; 4]; 4],
+    controls: ControlBindings,
+    instructions_per_second: f64,
+    screenshot_dir: PathBuf,
+    scale: usize,
+    keymap: HashMap<KeyCode, usize>,
+    keypad: Option<VirtualKeypad>,
+    keypad_image: Grid<Pixel>,
+    clicked_key: Option<usize>,
+    // Instructions run so far, counted by the interpreter.
+    cycles: Arc<AtomicU64>,
+    rate_meter: RateMeter,
+    measured_rates: Option<Rates>,
+    // The window's size before going fullscreen, to go back to afterwards.
+    windowed_size: Option<PhysicalSize<u32>>,
 }
 
 impl Frontend {
     pub fn new(
         config: FrontendConfig,
         exit_flag: Arc<AtomicBool>,
+        paused_flag: Arc<AtomicBool>,
         frame_receiver: Receiver<Grid<Pixel>>,
         keys_sender: Sender<KeyUpdate>,
-    ) -> Result<Frontend, Box<dyn std::error::Error>> {
-        let event_loop = EventLoop::new()?;
+        control_sender: Sender<ControlMessage>,
+        cycle_counter: Arc<AtomicU64>,
+    ) -> Result<Frontend, GraphicsUnavailable> {
+        let event_loop = EventLoop::new().map_err(GraphicsUnavailable::new)?;
         let input = WinitInputHelper::new();
+        let keypad = config
+            .virtual_keypad
+            .then(|| VirtualKeypad::new(config.width));
+        let buffer_height = match keypad {
+            Some(_) => config.height + KEYPAD_HEIGHT,
+            None => config.height,
+        };
         let window = {
             let size = LogicalSize::new(
-                (INITIAL_DISPLAY_SCALING * config.width) as f64,
-                (INITIAL_DISPLAY_SCALING * config.height) as f64,
+                (config.scale * config.window_width) as f64,
+                (config.scale * config.window_height * buffer_height / config.height) as f64,
             );
             WindowBuilder::new()
-                .with_title("WHIP-8")
+                .with_title(window_title(
+                    paused_flag.load(Ordering::SeqCst),
+                    config.instructions_per_second,
+                    None,
+                ))
                 .with_inner_size(size)
                 .with_min_inner_size(size)
-                .build(&event_loop)?
+                .build(&event_loop)
+                .map_err(GraphicsUnavailable::new)?
         };
+        // Pixels scales the display by the largest whole number that fits the
+        // window and centres it, leaving a border that's the background colour.
         let pixels = {
             let window_size = window.inner_size();
             let surface_texture =
                 SurfaceTexture::new(window_size.width, window_size.height, &window);
-            Pixels::new(config.width as u32, config.height as u32, surface_texture)?
+            let mut pixels =
+                Pixels::new(config.width as u32, buffer_height as u32, surface_texture)
+                    .map_err(GraphicsUnavailable::new)?;
+            pixels.clear_color(clear_colour(config.off_colour));
+            pixels
         };
 
         Ok(Frontend {
@@ -94,11 +207,32 @@ impl Frontend {
             input,
             window,
             exit_requested: exit_flag,
+            paused: paused_flag,
             frame_channel: frame_receiver,
             keys_channel: keys_sender,
+            control_channel: control_sender,
             image_buffer: Grid::<Pixel>::init(config.height, config.width, Pixel::Off),
-            off_colour: config.off_colour,
-            on_colour: config.on_colour,
+            palette: [
+                config.off_colour,
+                config.on_colour,
+                config.second_plane_colour,
+                config.both_planes_colour,
+            ],
+            controls: config.controls,
+            instructions_per_second: config.instructions_per_second,
+            screenshot_dir: config.screenshot_dir,
+            scale: config.scale,
+            keymap: config.keymap,
+            keypad_image: match &keypad {
+                Some(keypad) => keypad.render(None),
+                None => Grid::init(0, 0, Pixel::Off),
+            },
+            keypad,
+            clicked_key: None,
+            rate_meter: RateMeter::new(Instant::now(), cycle_counter.load(Ordering::Relaxed)),
+            cycles: cycle_counter,
+            measured_rates: None,
+            windowed_size: None,
         })
     }
 
@@ -115,6 +249,22 @@ impl Frontend {
             } = event
             {
                 if let Ok(recv_frame) = self.frame_channel.try_recv() {
+                    // SCHIP programs switch between lores and hires, which
+                    // changes the size of the frames sent.
+                    if recv_frame.size() != self.image_buffer.size() {
+                        let width = recv_frame.cols();
+                        if let Some(keypad) = self.keypad.as_mut() {
+                            *keypad = VirtualKeypad::new(width);
+                            self.keypad_image = keypad.render(self.clicked_key);
+                        }
+                        let height = recv_frame.rows() + self.keypad_image.rows();
+                        if let Err(err) = self.pixels.resize_buffer(width as u32, height as u32) {
+                            log_error(err);
+                            self.exit_requested.store(true, Ordering::SeqCst);
+                            elwt.exit();
+                            return;
+                        }
+                    }
                     self.image_buffer = recv_frame
                 }
 
@@ -122,12 +272,9 @@ impl Frontend {
                     .pixels
                     .frame_mut()
                     .chunks_exact_mut(4)
-                    .zip(self.image_buffer.iter())
+                    .zip(self.image_buffer.iter().chain(self.keypad_image.iter()))
                 {
-                    dest.copy_from_slice(match src {
-                        Pixel::Off => &self.off_colour,
-                        Pixel::On => &self.on_colour,
-                    });
+                    dest.copy_from_slice(&self.palette[src.planes() as usize]);
                 }
 
                 if let Err(err) = self.pixels.render() {
@@ -136,18 +283,142 @@ impl Frontend {
                     elwt.exit();
                     return;
                 }
+
+                self.rate_meter.frame_rendered();
+                let cycles = self.cycles.load(Ordering::Relaxed);
+                if let Some(rates) = self.rate_meter.sample(Instant::now(), cycles) {
+                    self.measured_rates = Some(rates);
+                    self.window.set_title(&window_title(
+                        self.paused.load(Ordering::SeqCst),
+                        self.instructions_per_second,
+                        self.measured_rates,
+                    ));
+                }
             }
 
             if self.input.update(&event) {
-                if self.input.key_pressed(KeyCode::Escape) || self.input.close_requested() {
+                if self.input.close_requested() {
                     elwt.exit();
                     return;
                 }
 
-                for (idx, key_code) in KEY_BINDINGS.iter().enumerate() {
+                for action in ControlAction::ALL {
+                    if !self.input.key_pressed(self.controls.key_for(action)) {
+                        continue;
+                    }
+
+                    match action {
+                        ControlAction::Quit => {
+                            elwt.exit();
+                            return;
+                        }
+                        ControlAction::Pause => {
+                            let paused = !self.paused.fetch_xor(true, Ordering::SeqCst);
+                            self.window.set_title(&window_title(
+                                paused,
+                                self.instructions_per_second,
+                                self.measured_rates,
+                            ));
+                        }
+                        ControlAction::Step => {
+                            if let Err(err) = self.control_channel.send(ControlMessage::Step) {
+                                log_error(err);
+                                self.exit_requested.store(true, Ordering::SeqCst);
+                                elwt.exit();
+                                return;
+                            }
+                        }
+                        ControlAction::SpeedUp | ControlAction::SlowDown => {
+                            let instructions_per_second = if action == ControlAction::SpeedUp {
+                                clock::speed_up(self.instructions_per_second)
+                            } else {
+                                clock::slow_down(self.instructions_per_second)
+                            };
+                            if instructions_per_second == self.instructions_per_second {
+                                continue;
+                            }
+                            if let Err(err) = self
+                                .control_channel
+                                .send(ControlMessage::SetSpeed(instructions_per_second))
+                            {
+                                log_error(err);
+                                self.exit_requested.store(true, Ordering::SeqCst);
+                                elwt.exit();
+                                return;
+                            }
+                            self.instructions_per_second = instructions_per_second;
+                            self.window.set_title(&window_title(
+                                self.paused.load(Ordering::SeqCst),
+                                instructions_per_second,
+                                self.measured_rates,
+                            ));
+                        }
+                        ControlAction::Screenshot => {
+                            match screenshot::save_screenshot(
+                                &self.image_buffer,
+                                &self.palette,
+                                self.scale,
+                                &self.screenshot_dir,
+                            ) {
+                                Ok(path) => info!("Saved screenshot to {}", path.display()),
+                                Err(err) => log_error(err),
+                            }
+                        }
+                        ControlAction::Reset => {
+                            if let Err(err) = self.control_channel.send(ControlMessage::Restart) {
+                                log_error(err);
+                                self.exit_requested.store(true, Ordering::SeqCst);
+                                elwt.exit();
+                                return;
+                            }
+                        }
+                        ControlAction::Rewind => {
+                            if let Err(err) = self.control_channel.send(ControlMessage::Rewind) {
+                                log_error(err);
+                                self.exit_requested.store(true, Ordering::SeqCst);
+                                elwt.exit();
+                                return;
+                            }
+                        }
+                        ControlAction::Dump => {
+                            if let Err(err) = self.control_channel.send(ControlMessage::Dump) {
+                                log_error(err);
+                                self.exit_requested.store(true, Ordering::SeqCst);
+                                elwt.exit();
+                                return;
+                            }
+                        }
+                        // The resize that follows letterboxes the display
+                        // rather than stretching it.
+                        ControlAction::Fullscreen => match self.windowed_size.take() {
+                            Some(size) => {
+                                self.window.set_fullscreen(None);
+                                // a size applied straight away doesn't get a
+                                // resize event
+                                if let Some(size) = self.window.request_inner_size(size) {
+                                    if let Err(err) =
+                                        self.pixels.resize_surface(size.width, size.height)
+                                    {
+                                        log_error(err);
+                                        self.exit_requested.store(true, Ordering::SeqCst);
+                                        elwt.exit();
+                                        return;
+                                    }
+                                }
+                            }
+                            None => {
+                                self.windowed_size = Some(self.window.inner_size());
+                                self.window
+                                    .set_fullscreen(Some(Fullscreen::Borderless(None)));
+                            }
+                        },
+                    }
+                }
+
+                for (key_code, chip8_key) in &self.keymap {
                     if self.input.key_pressed(*key_code) {
                         if let Err(err) = self.keys_channel.send(KeyUpdate {
-                            key: idx,
+                            key: *chip8_key,
                             status: KeyStatus::Pressed,
                         }) {
                             log_error(err);
@@ -158,7 +429,7 @@ impl Frontend {
                     }
                     if self.input.key_released(*key_code) {
                         if let Err(err) = self.keys_channel.send(KeyUpdate {
-                            key: idx,
+                            key: *chip8_key,
                             status: KeyStatus::Released,
                         }) {
                             log_error(err);
@@ -168,6 +439,44 @@ impl Frontend {
                         }
                     }
                 }
+
+                if let Some(keypad) = &self.keypad {
+                    let mut update = None;
+                    if self.input.mouse_pressed(LEFT_MOUSE_BUTTON) {
+                        let display_height = self.image_buffer.rows();
+                        let key = self
+                            .input
+                            .cursor()
+                            .and_then(|cursor| self.pixels.window_pos_to_pixel(cursor).ok())
+                            .filter(|(_, y)| *y >= display_height)
+                            .and_then(|(x, y)| keypad.key_at(x, y - display_height));
+                        if let Some(key) = key {
+                            self.clicked_key = Some(key);
+                            update = Some(KeyUpdate {
+                                key,
+                                status: KeyStatus::Pressed,
+                            });
+                        }
+                    }
+                    if self.input.mouse_released(LEFT_MOUSE_BUTTON) {
+                        if let Some(key) = self.clicked_key.take() {
+                            update = Some(KeyUpdate {
+                                key,
+                                status: KeyStatus::Released,
+                            });
+                        }
+                    }
+
+                    if let Some(update) = update {
+                        self.keypad_image = keypad.render(self.clicked_key);
+                        if let Err(err) = self.keys_channel.send(update) {
+                            log_error(err);
+                            self.exit_requested.store(true, Ordering::SeqCst);
+                            elwt.exit();
+                            return;
+                        }
+                    }
+                }
             }
 
             if let Some(size) = self.input.window_resized() {
@@ -183,3 +492,52 @@ impl Frontend {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_graphics_unavailable_suggests_headless() {
+        let err = GraphicsUnavailable::new(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no suitable adapter",
+        ));
+
+        let message = err.to_string();
+        assert!(message.starts_with("Couldn't open a window: no suitable adapter."));
+        assert!(message.contains("--term"));
+        assert!(message.contains("--input-script"));
+        assert_eq!(err.source().unwrap().to_string(), "no suitable adapter");
+    }
+
+    #[test]
+    fn test_clear_colour() {
+        let colour = clear_colour([0x00, 0xFF, 0x80, 0xFF]);
+        assert_eq!((colour.r, colour.g, colour.a), (0.0, 1.0, 1.0));
+        assert!((colour.b - 0.2158).abs() < 0.0001);
+        assert!((clear_colour([0x08, 0, 0, 0]).r - 0.00243).abs() < 0.00001);
+    }
+
+    #[test]
+    fn test_window_title() {
+        assert_eq!(window_title(false, 700.0, None), "WHIP-8 - 700 IPS");
+        assert_eq!(window_title(true, 700.0, None), "WHIP-8 - 700 IPS [paused]");
+        assert_eq!(window_title(false, 2362.5, None), "WHIP-8 - 2362 IPS");
+        assert_eq!(window_title(false, 0.0, None), "WHIP-8 - unthrottled");
+
+        let measured = Some(Rates {
+            frames_per_second: 59.9,
+            instructions_per_second: 698.2,
+        });
+        assert_eq!(
+            window_title(true, 700.0, measured),
+            "WHIP-8 - 700 IPS (60 FPS, 698 IPS measured) [paused]"
+        );
+        assert_eq!(
+            window_title(false, 0.0, measured),
+            "WHIP-8 - unthrottled (60 FPS, 698 IPS measured)"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::io::Write;
+
+use interpreter::{
+    cheats::CheatList,
+    instructions::Instruction,
+    processor::{ExecutedInstruction, Processor, REGISTER_CSV_HEADER},
+};
+
+use crate::input_script::InputScript;
+
+// Without a display to pace it, a headless run treats this many instructions as
+// one 60Hz frame.
+pub const STEPS_PER_FRAME: usize = 10;
+
+// Runs `frames` frames without a window. Scripted keys for a frame are applied
+// before it starts, cheats and timers at the end, then `on_frame` is called.
+// With a `draw_trace`, the whole display is written to it after every draw. If
+// the program exits, the frame it exited in is the last.
+pub fn run_frames(
+    processor: &mut Processor,
+    cheats: &mut CheatList,
+    script: &InputScript,
+    frames: usize,
+    mut draw_trace: Option<&mut dyn Write>,
+    mut on_frame: impl FnMut(usize, &Processor) -> std::io::Result<()>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for frame in 0..frames {
+        for event in script.events_for(frame) {
+            processor.add_key_event(event.key, event.status);
+        }
+
+        for _ in 0..STEPS_PER_FRAME {
+            // nothing runs while halted
+            let Some(ExecutedInstruction {
+                address,
+                instruction,
+            }) = processor.step_traced()?
+            else {
+                continue;
+            };
+
+            match instruction {
+                Instruction::Exit => {
+                    on_frame(frame, processor)?;
+                    return Ok(());
+                }
+                Instruction::Draw { .. } => {
+                    if let Some(trace) = &mut draw_trace {
+                        write!(
+                            trace,
+                            "Frame {}, draw at {}:\n{}",
+                            frame,
+                            address,
+                            processor.display().to_ascii()
+                        )?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        cheats.apply(processor);
+        processor.tick();
+
+        on_frame(frame, processor)?;
+    }
+
+    Ok(())
+}
+
+pub fn run_register_csv<W: Write>(
+    processor: &mut Processor,
+    cheats: &mut CheatList,
+    script: &InputScript,
+    frames: usize,
+    draw_trace: Option<&mut dyn Write>,
+    output: &mut W,
+) -> Result<(), Box<dyn std::error::Error>> {
+    writeln!(output, "{}", REGISTER_CSV_HEADER)?;
+    run_frames(
+        processor,
+        cheats,
+        script,
+        frames,
+        draw_trace,
+        |frame, processor| writeln!(output, "{}", processor.register_csv_row(frame)),
+    )?;
+    output.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_csv_output() {
+        let mut processor = Processor::new(vec![
+            0x60, 0x05, // LD V0, 0x05
+            0xF0, 0x15, // LD DT, V0
+            0x70, 0x01, // ADD V0, 0x01
+            0x12, 0x04, // JP 0x204
+        ])
+        .unwrap();
+        let mut output = Vec::new();
+
+        run_register_csv(
+            &mut processor,
+            &mut CheatList::default(),
+            &InputScript::default(),
+            2,
+            None,
+            &mut output,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "frame,pc,i,v0,v1,v2,v3,v4,v5,v6,v7,v8,v9,va,vb,vc,vd,ve,vf,delay,sound",
+                "0,516,0,9,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,4,0",
+                "1,516,0,14,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,3,0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_register_csv_stops_on_exit() {
+        let mut processor = Processor::new(vec![
+            0x60, 0x05, // LD V0, 0x05
+            0x00, 0xFD, // EXIT
+        ])
+        .unwrap();
+        let mut output = Vec::new();
+
+        run_register_csv(
+            &mut processor,
+            &mut CheatList::default(),
+            &InputScript::default(),
+            5,
+            None,
+            &mut output,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), 2);
+        assert!(output.contains("\n0,514,0,5,"));
+    }
+
+    fn scripted_run_hash(script: &str) -> u64 {
+        let mut processor = Processor::new(vec![
+            0xF0, 0x0A, // LD V0, K
+            0xC1, 0x3F, // RND V1, 0x3F
+            0xF0, 0x29, // LD F, V0
+            0xD1, 0x15, // DRW V1, V1, 5
+            0x12, 0x08, // JP 0x208
+        ])
+        .unwrap();
+        processor.seed_rng(7);
+
+        run_frames(
+            &mut processor,
+            &mut CheatList::default(),
+            &InputScript::parse(script).unwrap(),
+            10,
+            None,
+            |_, _| Ok(()),
+        )
+        .unwrap();
+        processor.state_hash()
+    }
+
+    #[test]
+    fn test_draw_trace() {
+        let mut processor = Processor::new(vec![
+            0xA2, 0x08, // LD I, 0x208
+            0xD0, 0x02, // DRW V0, V0, 2
+            0x12, 0x04, // JP 0x204
+            0x00, 0x00, // padding
+            0xC0, 0x30, // sprite
+        ])
+        .unwrap();
+        let mut trace = Vec::new();
+
+        run_frames(
+            &mut processor,
+            &mut CheatList::default(),
+            &InputScript::default(),
+            2,
+            Some(&mut trace),
+            |_, _| Ok(()),
+        )
+        .unwrap();
+
+        let trace = String::from_utf8(trace).unwrap();
+        let lines: Vec<&str> = trace.lines().collect();
+        assert_eq!(lines.len(), 33);
+        assert_eq!(lines[0], "Frame 0, draw at 0x202:");
+        assert_eq!(lines[1], format!("##{}", ".".repeat(62)));
+        assert_eq!(lines[2], format!("..##{}", ".".repeat(60)));
+        assert!(lines[3..].iter().all(|line| *line == ".".repeat(64)));
+    }
+
+    #[test]
+    fn test_scripted_input_deterministic() {
+        let script = "2 5 press\n3 5 release\n";
+        assert_eq!(scripted_run_hash(script), scripted_run_hash(script));
+        assert_ne!(
+            scripted_run_hash(script),
+            scripted_run_hash("2 6 press\n3 6 release\n")
+        );
+        assert_ne!(scripted_run_hash(script), scripted_run_hash(""));
+    }
+}
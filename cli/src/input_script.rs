@@ -0,0 +1,98 @@
+use interpreter::keypad::{KeyStatus, NUM_KEYS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScriptedKey {
+    pub frame: usize,
+    pub key: usize,
+    pub status: KeyStatus,
+}
+
+// A list of key events to inject at fixed frames. Each line of a script is
+// `FRAME KEY press|release`, with the frame in decimal and the CHIP-8 key as a
+// single hex digit, e.g. `30 5 press`. Blank lines and anything after a `#`
+// are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputScript {
+    events: Vec<ScriptedKey>,
+}
+
+impl InputScript {
+    pub fn parse(contents: &str) -> Result<InputScript, String> {
+        let mut events = Vec::new();
+        for (line_idx, line) in contents.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let event = parse_line(line)
+                .map_err(|err| format!("Input script line {}: {}", line_idx + 1, err))?;
+            events.push(event);
+        }
+
+        // stable, so events on the same frame keep their order from the file
+        events.sort_by_key(|event| event.frame);
+        Ok(InputScript { events })
+    }
+
+    pub fn events_for(&self, frame: usize) -> impl Iterator<Item = &ScriptedKey> {
+        self.events.iter().filter(move |event| event.frame == frame)
+    }
+}
+
+fn parse_line(line: &str) -> Result<ScriptedKey, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [frame, key, status] = fields[..] else {
+        return Err(format!("expected FRAME KEY press|release, got '{}'", line));
+    };
+
+    let frame = frame
+        .parse::<usize>()
+        .map_err(|_| format!("invalid frame number '{}'", frame))?;
+    let key = usize::from_str_radix(key, 16)
+        .ok()
+        .filter(|key| *key < NUM_KEYS)
+        .ok_or_else(|| format!("invalid key '{}', expected 0-F", key))?;
+    let status = match status.to_ascii_lowercase().as_str() {
+        "press" => KeyStatus::Pressed,
+        "release" => KeyStatus::Released,
+        _ => return Err(format!("expected press or release, got '{}'", status)),
+    };
+
+    Ok(ScriptedKey { frame, key, status })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_script() {
+        let script =
+            InputScript::parse("# start\n30 5 press\n\n10 a press # early\n31 5 release\n")
+                .unwrap();
+
+        assert_eq!(
+            script.events_for(10).collect::<Vec<_>>(),
+            vec![&ScriptedKey {
+                frame: 10,
+                key: 0xA,
+                status: KeyStatus::Pressed,
+            }]
+        );
+        assert_eq!(script.events_for(31).count(), 1);
+        assert_eq!(script.events_for(11).count(), 0);
+    }
+
+    #[test]
+    fn test_parse_script_errors() {
+        assert!(InputScript::parse("30 5").is_err());
+        assert!(InputScript::parse("thirty 5 press").is_err());
+        assert!(InputScript::parse("30 10 press").is_err());
+        assert!(InputScript::parse("30 5 tap").is_err());
+        assert_eq!(
+            InputScript::parse("1 2 press\n30 G press"),
+            Err("Input script line 2: invalid key 'G', expected 0-F".to_string())
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use crate::controls::{parse_key_code, ControlAction, ControlBindings};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use winit::keyboard::KeyCode;
+
+const KEYPAD_SIZE: u32 = 16;
+
+// Indexed by CHIP-8 key. The left of a QWERTY keyboard stands in for the
+// COSMAC VIP keypad, keeping its shape:
+//
+//   keyboard      CHIP-8
+//   1 2 3 4       1 2 3 C
+//   Q W E R       4 5 6 D
+//   A S D F       7 8 9 E
+//   Z X C V       A 0 B F
+//
+// Keys are only forwarded when they go down or up, not while held.
+const DEFAULT_KEYS: [KeyCode; 16] = [
+    KeyCode::KeyX,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::KeyQ,
+    KeyCode::KeyW,
+    KeyCode::KeyE,
+    KeyCode::KeyA,
+    KeyCode::KeyS,
+    KeyCode::KeyD,
+    KeyCode::KeyZ,
+    KeyCode::KeyC,
+    KeyCode::Digit4,
+    KeyCode::KeyR,
+    KeyCode::KeyF,
+    KeyCode::KeyV,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyMapping {
+    pub key: KeyCode,
+    pub chip8_key: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeymapError {
+    Malformed { spec: String },
+    UnknownKey { name: String },
+    TargetOutOfRange { spec: String },
+    DuplicateKey { key: KeyCode },
+    ControlKey { key: KeyCode, action: ControlAction },
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeymapError::Malformed { spec } => write!(
+                f,
+                "Can't parse key mapping '{}', expected KEY=CHIP8_KEY with the CHIP-8 key in hex",
+                spec
+            ),
+            KeymapError::UnknownKey { name } => write!(f, "Unknown key '{}'", name),
+            KeymapError::TargetOutOfRange { spec } => write!(
+                f,
+                "Key mapping '{}' targets a key outside the CHIP-8 keypad, which runs from 0 to F",
+                spec
+            ),
+            KeymapError::DuplicateKey { key } => {
+                write!(f, "{:?} is mapped more than once", key)
+            }
+            KeymapError::ControlKey { key, action } => write!(
+                f,
+                "{:?} is bound to the {:?} control, so it can't also be a CHIP-8 key",
+                key, action
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KeymapError {}
+
+// Parses `KEY=CHIP8_KEY`, e.g. `up=2`. Keys are named as for `--bind`.
+pub fn parse_key_mapping(spec: &str) -> Result<KeyMapping, KeymapError> {
+    let (key_name, target) = spec.split_once('=').ok_or_else(|| KeymapError::Malformed {
+        spec: spec.to_string(),
+    })?;
+    let key = parse_key_code(key_name).ok_or_else(|| KeymapError::UnknownKey {
+        name: key_name.trim().to_string(),
+    })?;
+
+    let target = target.trim();
+    let digits = target
+        .strip_prefix("0x")
+        .or_else(|| target.strip_prefix("0X"))
+        .unwrap_or(target);
+    let chip8_key = u32::from_str_radix(digits, 16).map_err(|_| KeymapError::Malformed {
+        spec: spec.to_string(),
+    })?;
+    if chip8_key >= KEYPAD_SIZE {
+        return Err(KeymapError::TargetOutOfRange {
+            spec: spec.to_string(),
+        });
+    }
+
+    Ok(KeyMapping {
+        key,
+        chip8_key: chip8_key as usize,
+    })
+}
+
+// One mapping per line, blank lines and anything after a `#` are ignored.
+pub fn parse_keymap_file(contents: &str) -> Result<Vec<KeyMapping>, KeymapError> {
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(parse_key_mapping)
+        .collect()
+}
+
+// Starts from the default layout, with each mapping taking over its key. A key
+// mapped twice is an error, since only one of the mappings could win, and so is
+// mapping a key that's bound to a control. Controls rebound onto the default
+// layout take those keys out of it.
+pub fn build_keymap(
+    mappings: &[KeyMapping],
+    controls: &ControlBindings,
+) -> Result<HashMap<KeyCode, usize>, KeymapError> {
+    let control_keys: HashMap<KeyCode, ControlAction> = ControlAction::ALL
+        .iter()
+        .map(|action| (controls.key_for(*action), *action))
+        .collect();
+    let mut keymap: HashMap<KeyCode, usize> = DEFAULT_KEYS
+        .iter()
+        .enumerate()
+        .filter(|(_, key)| !control_keys.contains_key(key))
+        .map(|(chip8_key, key)| (*key, chip8_key))
+        .collect();
+
+    let mut mapped = HashSet::new();
+    for mapping in mappings {
+        if !mapped.insert(mapping.key) {
+            return Err(KeymapError::DuplicateKey { key: mapping.key });
+        }
+        if let Some(action) = control_keys.get(&mapping.key) {
+            return Err(KeymapError::ControlKey {
+                key: mapping.key,
+                action: *action,
+            });
+        }
+        keymap.insert(mapping.key, mapping.chip8_key);
+    }
+
+    Ok(keymap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_keys_follow_keypad_layout() {
+        let rows = [
+            [
+                KeyCode::Digit1,
+                KeyCode::Digit2,
+                KeyCode::Digit3,
+                KeyCode::Digit4,
+            ],
+            [KeyCode::KeyQ, KeyCode::KeyW, KeyCode::KeyE, KeyCode::KeyR],
+            [KeyCode::KeyA, KeyCode::KeyS, KeyCode::KeyD, KeyCode::KeyF],
+            [KeyCode::KeyZ, KeyCode::KeyX, KeyCode::KeyC, KeyCode::KeyV],
+        ];
+        let keypad = [
+            [0x1, 0x2, 0x3, 0xC],
+            [0x4, 0x5, 0x6, 0xD],
+            [0x7, 0x8, 0x9, 0xE],
+            [0xA, 0x0, 0xB, 0xF],
+        ];
+
+        let keymap = build_keymap(&[], &ControlBindings::default()).unwrap();
+        assert_eq!(keymap.len(), 16);
+        for (keys, chip8_keys) in rows.iter().zip(keypad.iter()) {
+            for (key_code, chip8_key) in keys.iter().zip(chip8_keys.iter()) {
+                assert_eq!(DEFAULT_KEYS[*chip8_key], *key_code);
+                assert_eq!(keymap[key_code], *chip8_key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_key_mapping() {
+        assert_eq!(
+            parse_key_mapping("up=2"),
+            Ok(KeyMapping {
+                key: KeyCode::ArrowUp,
+                chip8_key: 0x2,
+            })
+        );
+        assert_eq!(
+            parse_key_mapping("k = 0xF"),
+            Ok(KeyMapping {
+                key: KeyCode::KeyK,
+                chip8_key: 0xF,
+            })
+        );
+        assert_eq!(
+            parse_key_mapping("up"),
+            Err(KeymapError::Malformed {
+                spec: "up".to_string()
+            })
+        );
+        assert_eq!(
+            parse_key_mapping("up=x"),
+            Err(KeymapError::Malformed {
+                spec: "up=x".to_string()
+            })
+        );
+        assert_eq!(
+            parse_key_mapping("hyper=2"),
+            Err(KeymapError::UnknownKey {
+                name: "hyper".to_string()
+            })
+        );
+        assert_eq!(
+            parse_key_mapping("up=10"),
+            Err(KeymapError::TargetOutOfRange {
+                spec: "up=10".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_keymap_file() {
+        let contents = "\
+            # arrows for the usual movement keys
+            up=5
+            down=8   # 8 is down in most games
+
+            left=7
+        ";
+        assert_eq!(
+            parse_keymap_file(contents),
+            Ok(vec![
+                KeyMapping {
+                    key: KeyCode::ArrowUp,
+                    chip8_key: 0x5
+                },
+                KeyMapping {
+                    key: KeyCode::ArrowDown,
+                    chip8_key: 0x8
+                },
+                KeyMapping {
+                    key: KeyCode::ArrowLeft,
+                    chip8_key: 0x7
+                },
+            ])
+        );
+        assert!(parse_keymap_file("up=5\nleft=G").is_err());
+    }
+
+    #[test]
+    fn test_build_keymap_overrides_defaults() {
+        let keymap = build_keymap(
+            &[
+                parse_key_mapping("up=5").unwrap(),
+                parse_key_mapping("x=1").unwrap(),
+            ],
+            &ControlBindings::default(),
+        )
+        .unwrap();
+
+        assert_eq!(keymap[&KeyCode::ArrowUp], 0x5);
+        assert_eq!(keymap[&KeyCode::KeyW], 0x5);
+        assert_eq!(keymap[&KeyCode::KeyX], 0x1);
+        assert_eq!(keymap.len(), 17);
+    }
+
+    #[test]
+    fn test_build_keymap_rejects_duplicates() {
+        let err = build_keymap(
+            &[
+                parse_key_mapping("up=5").unwrap(),
+                parse_key_mapping("Up=8").unwrap(),
+            ],
+            &ControlBindings::default(),
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err,
+            KeymapError::DuplicateKey {
+                key: KeyCode::ArrowUp
+            }
+        );
+        assert_eq!(err.to_string(), "ArrowUp is mapped more than once");
+    }
+
+    #[test]
+    fn test_build_keymap_rejects_control_keys() {
+        let controls = ControlBindings::default();
+        for (spec, action) in [
+            ("backspace=5", ControlAction::Rewind),
+            ("m=0", ControlAction::Dump),
+        ] {
+            let err = build_keymap(&[parse_key_mapping(spec).unwrap()], &controls).unwrap_err();
+            assert_eq!(
+                err,
+                KeymapError::ControlKey {
+                    key: controls.key_for(action),
+                    action
+                }
+            );
+        }
+
+        let mut controls = ControlBindings::default();
+        controls.bind(ControlAction::Pause, KeyCode::KeyX);
+        let err = build_keymap(&[parse_key_mapping("x=0").unwrap()], &controls).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "KeyX is bound to the Pause control, so it can't also be a CHIP-8 key"
+        );
+
+        // a control rebound onto the default layout takes the key over, and
+        // CHIP-8 key 0 can be put somewhere else
+        let keymap = build_keymap(&[], &controls).unwrap();
+        assert!(!keymap.contains_key(&KeyCode::KeyX));
+        let keymap = build_keymap(&[parse_key_mapping("k=0").unwrap()], &controls).unwrap();
+        assert_eq!(keymap[&KeyCode::KeyK], 0x0);
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt::Display;
+
+const REPEAT_SUMMARY_INTERVAL: usize = 1000;
+
+// Collapses runs of identical messages, such as a lenient processor hitting
+// the same bad opcode over and over. The first occurrence is emitted as is,
+// repeats are summarised every REPEAT_SUMMARY_INTERVAL occurrences and when
+// the run ends.
+pub struct LogThrottle<T> {
+    last: Option<T>,
+    repeats: usize,
+}
+
+impl<T: PartialEq + Display> LogThrottle<T> {
+    pub fn new() -> Self {
+        LogThrottle {
+            last: None,
+            repeats: 0,
+        }
+    }
+
+    pub fn observe(&mut self, item: T, mut emit: impl FnMut(String)) {
+        if self.last.as_ref() == Some(&item) {
+            self.repeats += 1;
+            if self.repeats == REPEAT_SUMMARY_INTERVAL {
+                self.flush(&mut emit);
+                self.last = Some(item);
+            }
+            return;
+        }
+
+        self.flush(&mut emit);
+        emit(item.to_string());
+        self.last = Some(item);
+    }
+
+    pub fn flush(&mut self, mut emit: impl FnMut(String)) {
+        if let Some(last) = self.last.take() {
+            if self.repeats > 0 {
+                emit(format!("{} (repeated {} more times)", last, self.repeats));
+            }
+        }
+        self.repeats = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use interpreter::instructions::{DecodeError, InstructionBytePair};
+    use interpreter::processor::ProcessorError;
+
+    fn bad_opcode(word: u16) -> ProcessorError {
+        ProcessorError::DecodeFailure {
+            instruction: InstructionBytePair(word),
+            reason: DecodeError::Misc { byte: word as u8 },
+        }
+    }
+
+    #[test]
+    fn test_identical_messages_collapsed() {
+        let mut throttle = LogThrottle::new();
+        let mut records = Vec::new();
+        for _ in 0..50 {
+            throttle.observe(bad_opcode(0xF001), |msg| records.push(msg));
+        }
+        throttle.flush(|msg| records.push(msg));
+
+        assert_eq!(records.len(), 2);
+        assert!(records[1].ends_with("(repeated 49 more times)"));
+    }
+
+    #[test]
+    fn test_long_runs_bounded() {
+        let mut throttle = LogThrottle::new();
+        let mut records = 0;
+        for _ in 0..(10 * REPEAT_SUMMARY_INTERVAL) {
+            throttle.observe(bad_opcode(0xF001), |_| records += 1);
+        }
+
+        assert!(records <= 11);
+    }
+
+    #[test]
+    fn test_distinct_messages_kept() {
+        let mut throttle = LogThrottle::new();
+        let mut records = Vec::new();
+        for word in [0xF001, 0xF001, 0xF002, 0xF001] {
+            throttle.observe(bad_opcode(word), |msg| records.push(msg));
+        }
+        throttle.flush(|msg| records.push(msg));
+
+        assert_eq!(
+            records,
+            vec![
+                bad_opcode(0xF001).to_string(),
+                format!("{} (repeated 1 more times)", bad_opcode(0xF001)),
+                bad_opcode(0xF002).to_string(),
+                bad_opcode(0xF001).to_string(),
+            ]
+        );
+    }
+}
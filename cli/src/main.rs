@@ -1,81 +1,348 @@
+mod audio;
 mod chip_8_interpreter;
+mod clock;
 mod commands;
+mod controls;
+mod demo;
+mod frame_gate;
 mod frontend;
+mod headless;
+mod input_script;
+mod keymap;
+mod log_throttle;
+mod rate_meter;
+mod screenshot;
+mod terminal;
 mod timer;
 mod utils;
+mod virtual_keypad;
+mod watch;
 
 use crate::commands::Args;
+use audio::{BeepConfig, Beeper, SharedAudioPattern};
 use chip_8_interpreter::Chip8Interpreter;
 use clap::Parser;
+use clock::InstructionClock;
+use controls::ControlBindings;
+use demo::DemoSchedule;
 use frontend::{Frontend, FrontendConfig};
-use std::fs;
+use input_script::InputScript;
+use interpreter::builtin_roms::BUILTIN_ROMS;
+use interpreter::cheats::{self, Cheat, CheatList};
+use interpreter::instructions;
+use interpreter::processor::Processor;
+use log::{info, warn};
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use terminal::TerminalFrontend;
 use timer::Timer;
+use utils::log_error;
 
 const WIDTH: u32 = 64;
 const HEIGHT: u32 = 32;
-const OFF_COLOUR: [u8; 4] = [0x10, 0x10, 0x10, 0xFF];
-const ON_COLOUR: [u8; 4] = [0x5E, 0x48, 0xE8, 0xFF];
+const HIRES_WIDTH: u32 = 128;
+const HIRES_HEIGHT: u32 = 64;
+// Passed as the ROM path to read the program from stdin instead, so assembler
+// output can be piped straight in.
+const STDIN_PATH: &str = "-";
+#[cfg(feature = "profiling")]
+const PROFILE_REPORT_LINES: usize = 20;
+
+// Only one of these exists, for the life of the program, so its size doesn't matter.
+#[allow(clippy::large_enum_variant)]
+enum ActiveFrontend {
+    Window(Frontend),
+    Terminal(TerminalFrontend),
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
+    env_logger::init();
 
-    let program_data: Vec<u8> = fs::read(args.path.clone()).map_err(|err| {
-        format!(
-            "Error reading input file at {}: {}",
-            args.path.display(),
-            err
-        )
-    })?;
+    let program_data: Vec<u8> = match &args.path {
+        Some(path) => read_program(path)?,
+        None => BUILTIN_ROMS[0].data.to_vec(),
+    };
+    let rom_file = args.path.as_deref().filter(|path| !is_stdin(path));
+
+    if let Some(target) = args.check_opcodes {
+        let unsupported = instructions::find_unsupported_opcodes(
+            &program_data,
+            args.config().program_start as u16,
+            target,
+        );
+        for opcode in &unsupported {
+            warn!("{}", opcode);
+        }
+        if let (true, Some(first)) = (args.reject_unsupported_opcodes, unsupported.first()) {
+            return Err(format!(
+                "Refusing to run a ROM with {} instruction(s) beyond {}, the first being {}",
+                unsupported.len(),
+                target,
+                first
+            )
+            .into());
+        }
+    }
+
+    let (window_width, window_height) =
+        if args.detect_resolution && instructions::detect_hires(&program_data) {
+            (HIRES_WIDTH, HIRES_HEIGHT)
+        } else {
+            (WIDTH, HEIGHT)
+        };
+
+    let mut cheat_list = args.cheats.clone();
+    if let Some(path) = &args.cheat_file {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Error reading cheat file at {}: {}", path.display(), err))?;
+        cheat_list.extend(cheats::parse_cheat_file(&contents)?);
+    }
+
+    let mut key_mappings = args.key_mappings.clone();
+    if let Some(path) = &args.keymap {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Error reading keymap at {}: {}", path.display(), err))?;
+        key_mappings.extend(keymap::parse_keymap_file(&contents)?);
+    }
+    let mut controls = ControlBindings::default();
+    for (action, key) in &args.bindings {
+        controls.bind(*action, *key);
+    }
+    let keymap = keymap::build_keymap(&key_mappings, &controls)?;
+
+    if args.register_csv.is_some() || args.input_script.is_some() || args.trace_draws {
+        return run_headless(&args, program_data, cheat_list);
+    }
 
     // sync structures
     let (frame_tx, frame_rx) = std::sync::mpsc::channel();
     let (key_tx, key_rx) = std::sync::mpsc::channel();
     let (timer_tx, timer_rx) = std::sync::mpsc::channel();
+    let (control_tx, control_rx) = std::sync::mpsc::channel();
     let exit_requested = Arc::new(AtomicBool::new(false));
-
-    env_logger::init();
+    let sound_active = Arc::new(AtomicBool::new(false));
+    let paused = Arc::new(AtomicBool::new(args.debug));
 
     let mut chip8 = Chip8Interpreter::new(
         program_data,
+        args.config(),
         exit_requested.clone(),
         frame_tx,
         key_rx,
         timer_rx,
+        sound_active.clone(),
+        paused.clone(),
+        control_rx,
     )?;
+    chip8.set_warn_vf_clobber(args.warn_vf_clobber);
+    chip8.set_quirks(args.quirks());
+    chip8.set_rewind_frames(args.rewind_frames);
+    chip8.set_dumps(args.dump_memory.clone(), args.dump_registers);
+    chip8.set_breakpoints(&args.breakpoints)?;
+    chip8.set_cheats(CheatList::new(cheat_list));
+    // A program piped in can't be read again, so resetting just restarts it.
+    if let Some(path) = rom_file {
+        chip8.set_rom_path(path.to_path_buf());
+    }
+    if let Some(seed) = args.seed {
+        chip8.seed_rng(seed);
+    }
+    chip8.set_refresh_period(Duration::from_secs_f64(1.0 / args.refresh_hz));
+    chip8.set_clock(InstructionClock::from_ips(args.ips));
+    if args.demo {
+        chip8.set_demo(DemoSchedule::new(
+            BUILTIN_ROMS.len(),
+            Duration::from_secs_f64(args.demo_seconds),
+            Instant::now(),
+        ));
+    }
+
+    let audio_pattern = SharedAudioPattern::default();
+    if args.config().xo_chip {
+        chip8.set_audio_pattern(audio_pattern.clone());
+    }
+    let _beeper = match Beeper::new(
+        BeepConfig::new(args.waveform, args.tone_hz, args.volume),
+        sound_active,
+        audio_pattern,
+    ) {
+        Ok(beeper) => Some(beeper),
+        Err(err) => {
+            warn!(
+                "Audio output unavailable, continuing without sound: {}",
+                err
+            );
+            None
+        }
+    };
+
+    if args.debug {
+        info!(
+            "Paused at the first instruction, press {:?} to step or {:?} to resume",
+            controls.step, controls.pause
+        );
+    }
+
+    let _watcher = match (rom_file, args.watch) {
+        (Some(path), true) => Some(
+            watch::watch_rom(
+                path.to_path_buf(),
+                control_tx.clone(),
+                exit_requested.clone(),
+            )
+            .map_err(|err| format!("Error watching {}: {}", path.display(), err))?,
+        ),
+        (None, true) => return Err("Can't watch a ROM read from stdin".into()),
+        _ => None,
+    };
 
     let mut timer = Timer::new(timer_tx, exit_requested.clone(), 1.0 / 60.0);
 
-    let frontend = Frontend::new(
-        FrontendConfig {
-            width: WIDTH as usize,
-            height: HEIGHT as usize,
-            off_colour: OFF_COLOUR,
-            on_colour: ON_COLOUR,
-        },
-        exit_requested.clone(),
-        frame_rx,
-        key_tx,
-    )?;
+    let frontend_config = FrontendConfig {
+        width: WIDTH as usize,
+        height: HEIGHT as usize,
+        window_width: window_width as usize,
+        window_height: window_height as usize,
+        off_colour: args.bg,
+        on_colour: args.fg,
+        second_plane_colour: args.fg2,
+        both_planes_colour: args.blend,
+        controls,
+        virtual_keypad: args.virtual_keypad,
+        instructions_per_second: args.ips,
+        screenshot_dir: args.screenshot_dir.clone(),
+        scale: args.scale as usize,
+        keymap,
+    };
+    let frontend = if args.term {
+        ActiveFrontend::Terminal(TerminalFrontend::new(
+            &frontend_config,
+            exit_requested.clone(),
+            paused,
+            frame_rx,
+            key_tx,
+            control_tx,
+        ))
+    } else {
+        ActiveFrontend::Window(
+            Frontend::new(
+                frontend_config,
+                exit_requested.clone(),
+                paused,
+                frame_rx,
+                key_tx,
+                control_tx,
+                chip8.cycle_counter(),
+            )
+            .map_err(|err| {
+                log_error(err);
+                "Couldn't start the frontend"
+            })?,
+        )
+    };
 
-    let interpreter_thread = std::thread::spawn(move || {
-        chip8.run();
-    });
+    let interpreter_thread = std::thread::spawn(move || chip8.run());
 
     let timer_thread = std::thread::spawn(move || {
         timer.run();
     });
 
-    frontend.run()?;
+    match frontend {
+        ActiveFrontend::Window(frontend) => frontend.run()?,
+        ActiveFrontend::Terminal(frontend) => frontend.run()?,
+    }
 
     if exit_requested.load(std::sync::atomic::Ordering::SeqCst) {
-        interpreter_thread
+        let program_exited = interpreter_thread
             .join()
             .expect("Unable to join interpreter thread.");
         timer_thread.join().expect("Unable to join timer thread.");
-        return Err("Program exited unsuccessfully".into());
+        if !program_exited {
+            return Err("Program exited unsuccessfully".into());
+        }
     }
 
     Ok(())
 }
+
+fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == STDIN_PATH
+}
+
+// Programs too long for memory are rejected when they're loaded, wherever they
+// were read from.
+fn read_program(path: &Path) -> Result<Vec<u8>, String> {
+    if !is_stdin(path) {
+        return fs::read(path)
+            .map_err(|err| format!("Error reading input file at {}: {}", path.display(), err));
+    }
+
+    let mut program = Vec::new();
+    std::io::stdin()
+        .read_to_end(&mut program)
+        .map_err(|err| format!("Error reading ROM from stdin: {}", err))?;
+    Ok(program)
+}
+
+fn run_headless(
+    args: &Args,
+    program_data: Vec<u8>,
+    cheat_list: Vec<Cheat>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let script = match &args.input_script {
+        Some(path) => InputScript::parse(&fs::read_to_string(path).map_err(|err| {
+            format!("Error reading input script at {}: {}", path.display(), err)
+        })?)?,
+        None => InputScript::default(),
+    };
+
+    let mut processor = Processor::new_with_config(program_data, args.config(), args.quirks())?;
+    processor.seed_rng(args.seed.unwrap_or(0));
+    let mut cheats = CheatList::new(cheat_list);
+    let mut stdout = std::io::stdout().lock();
+    let draw_trace: Option<&mut dyn Write> = if args.trace_draws {
+        Some(&mut stdout)
+    } else {
+        None
+    };
+
+    match &args.register_csv {
+        Some(path) => {
+            let file = File::create(path)
+                .map_err(|err| format!("Error creating CSV file at {}: {}", path.display(), err))?;
+            headless::run_register_csv(
+                &mut processor,
+                &mut cheats,
+                &script,
+                args.frames,
+                draw_trace,
+                &mut BufWriter::new(file),
+            )?;
+        }
+        None => {
+            headless::run_frames(
+                &mut processor,
+                &mut cheats,
+                &script,
+                args.frames,
+                draw_trace,
+                |_, _| Ok(()),
+            )?;
+        }
+    }
+
+    writeln!(stdout, "Final state hash: {:016x}", processor.state_hash())?;
+    #[cfg(feature = "profiling")]
+    write!(
+        stdout,
+        "{}\n{}",
+        processor.profile().report(PROFILE_REPORT_LINES),
+        processor.profile().instruction_histogram()
+    )?;
+    Ok(())
+}
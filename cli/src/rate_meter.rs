@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+
+const SAMPLE_PERIOD: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rates {
+    pub frames_per_second: f64,
+    pub instructions_per_second: f64,
+}
+
+// Counts rendered frames, and how far the interpreter's cycle count has moved,
+// over each second. Readings only come out once a second so a title showing
+// them doesn't flicker.
+pub struct RateMeter {
+    period_start: Instant,
+    frames: u64,
+    start_cycles: u64,
+}
+
+impl RateMeter {
+    pub fn new(now: Instant, cycles: u64) -> Self {
+        RateMeter {
+            period_start: now,
+            frames: 0,
+            start_cycles: cycles,
+        }
+    }
+
+    pub fn frame_rendered(&mut self) {
+        self.frames += 1;
+    }
+
+    // Returns the rates since the last reading once a second has passed, then
+    // starts counting again.
+    pub fn sample(&mut self, now: Instant, cycles: u64) -> Option<Rates> {
+        let elapsed = now.saturating_duration_since(self.period_start);
+        if elapsed < SAMPLE_PERIOD {
+            return None;
+        }
+
+        let seconds = elapsed.as_secs_f64();
+        let rates = Rates {
+            frames_per_second: self.frames as f64 / seconds,
+            instructions_per_second: cycles.saturating_sub(self.start_cycles) as f64 / seconds,
+        };
+        *self = RateMeter::new(now, cycles);
+        Some(rates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        let start = Instant::now();
+        let mut meter = RateMeter::new(start, 100);
+        for _ in 0..30 {
+            meter.frame_rendered();
+        }
+
+        assert_eq!(meter.sample(start + Duration::from_millis(999), 500), None);
+        assert_eq!(
+            meter.sample(start + Duration::from_secs(2), 1500),
+            Some(Rates {
+                frames_per_second: 15.0,
+                instructions_per_second: 700.0,
+            })
+        );
+
+        // counting starts again from the last reading
+        meter.frame_rendered();
+        assert_eq!(
+            meter.sample(start + Duration::from_millis(2500), 1600),
+            None
+        );
+        assert_eq!(
+            meter.sample(start + Duration::from_secs(3), 1600),
+            Some(Rates {
+                frames_per_second: 1.0,
+                instructions_per_second: 100.0,
+            })
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use grid::Grid;
+use image::{ImageResult, Rgba, RgbaImage};
+use interpreter::display::Pixel;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Each CHIP-8 pixel becomes a `scale` by `scale` block, since a 64x32 image is
+// too small to look at.
+// The palette is indexed by Pixel::planes.
+fn render_image(frame: &Grid<Pixel>, palette: &[[u8; 4]; 4], scale: usize) -> RgbaImage {
+    let width = (frame.cols() * scale) as u32;
+    let height = (frame.rows() * scale) as u32;
+    RgbaImage::from_fn(width, height, |x, y| {
+        let pixel = frame[(y as usize / scale, x as usize / scale)];
+        Rgba(palette[pixel.planes() as usize])
+    })
+}
+
+// Named after the time in milliseconds, so screenshots sort in the order they
+// were taken.
+fn screenshot_path(dir: &Path, time: SystemTime) -> PathBuf {
+    let millis = time
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_millis())
+        .unwrap_or(0);
+    dir.join(format!("whip-8-{}.png", millis))
+}
+
+// Returns where the screenshot was saved.
+pub fn save_screenshot(
+    frame: &Grid<Pixel>,
+    palette: &[[u8; 4]; 4],
+    scale: usize,
+    dir: &Path,
+) -> ImageResult<PathBuf> {
+    let path = screenshot_path(dir, SystemTime::now());
+    render_image(frame, palette, scale).save(&path)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const OFF: [u8; 4] = [0x10, 0x10, 0x10, 0xFF];
+    const ON: [u8; 4] = [0xFF, 0x80, 0x00, 0xFF];
+    const SECOND: [u8; 4] = [0x00, 0x80, 0xFF, 0xFF];
+    const BOTH: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+    const PALETTE: [[u8; 4]; 4] = [OFF, ON, SECOND, BOTH];
+
+    #[test]
+    fn test_render_image_scaled() {
+        let mut frame = Grid::init(2, 3, Pixel::Off);
+        frame[(1, 2)] = Pixel::On;
+
+        let image = render_image(&frame, &PALETTE, 2);
+        assert_eq!(image.dimensions(), (6, 4));
+        assert_eq!(image.get_pixel(0, 0), &Rgba(OFF));
+        assert_eq!(image.get_pixel(3, 3), &Rgba(OFF));
+        for (x, y) in [(4, 2), (5, 2), (4, 3), (5, 3)] {
+            assert_eq!(image.get_pixel(x, y), &Rgba(ON));
+        }
+    }
+
+    #[test]
+    fn test_render_image_plane_colours() {
+        let frame = Grid::from_vec(
+            vec![Pixel::Off, Pixel::On, Pixel::SecondPlane, Pixel::BothPlanes],
+            4,
+        );
+
+        let image = render_image(&frame, &PALETTE, 1);
+        for (x, colour) in PALETTE.iter().enumerate() {
+            assert_eq!(image.get_pixel(x as u32, 0), &Rgba(*colour));
+        }
+    }
+
+    #[test]
+    fn test_screenshot_path() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        assert_eq!(
+            screenshot_path(Path::new("shots"), time),
+            Path::new("shots/whip-8-1700000000123.png")
+        );
+    }
+
+    #[test]
+    fn test_save_screenshot_missing_dir() {
+        let frame = Grid::init(32, 64, Pixel::Off);
+        let dir = Path::new("/nonexistent/whip-8/screenshots");
+        assert!(save_screenshot(&frame, &PALETTE, 1, dir).is_err());
+    }
+}
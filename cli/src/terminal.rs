@@ -0,0 +1,469 @@
+use crate::{
+    chip_8_interpreter::{ControlMessage, KeyUpdate},
+    clock,
+    controls::{parse_key_code, ControlAction, ControlBindings},
+    frontend::FrontendConfig,
+    screenshot,
+    utils::log_error,
+};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{self, ClearType},
+};
+use grid::Grid;
+use interpreter::{display::Pixel, keypad::KeyStatus};
+use log::info;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{Receiver, Sender},
+    Arc,
+};
+use std::time::{Duration, Instant};
+
+const POLL_PERIOD: Duration = Duration::from_millis(5);
+
+// Terminals only report key presses, and repeats while a key is held, so a key
+// counts as released once it hasn't been seen for this long. It's longer than
+// the usual delay before a held key starts repeating.
+const KEY_HOLD: Duration = Duration::from_millis(600);
+
+// The window's name for a key the terminal reports, so the terminal can share
+// its keymap and bindings. Terminals report characters rather than physical
+// keys, so punctuation assumes a US layout.
+fn window_key(code: KeyCode) -> Option<winit::keyboard::KeyCode> {
+    let name = match code {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char('-') => "minus".to_string(),
+        KeyCode::Char('=' | '+') => "equal".to_string(),
+        KeyCode::Char(',') => "comma".to_string(),
+        KeyCode::Char('.') => "period".to_string(),
+        KeyCode::Char('/') => "slash".to_string(),
+        KeyCode::Char(';') => "semicolon".to_string(),
+        KeyCode::Char(key_char) => key_char.to_string(),
+        KeyCode::Esc => "escape".to_string(),
+        KeyCode::Enter => "enter".to_string(),
+        KeyCode::Tab => "tab".to_string(),
+        KeyCode::Backspace => "backspace".to_string(),
+        KeyCode::Up => "up".to_string(),
+        KeyCode::Down => "down".to_string(),
+        KeyCode::Left => "left".to_string(),
+        KeyCode::Right => "right".to_string(),
+        KeyCode::Home => "home".to_string(),
+        KeyCode::End => "end".to_string(),
+        KeyCode::PageUp => "pageup".to_string(),
+        KeyCode::PageDown => "pagedown".to_string(),
+        KeyCode::Insert => "insert".to_string(),
+        KeyCode::Delete => "delete".to_string(),
+        KeyCode::F(number) => format!("f{}", number),
+        _ => return None,
+    };
+    parse_key_code(&name)
+}
+
+// One line of text per row of pixels. Lines end in "\r\n" since raw mode turns
+// off the terminal's own newline handling. XO-CHIP's other colours are shades.
+fn render_frame(frame: &Grid<Pixel>) -> String {
+    let mut text = String::with_capacity(frame.rows() * (frame.cols() * 3 + 2));
+    for row in frame.iter_rows() {
+        text.extend(row.map(|pixel| match pixel {
+            Pixel::On => '█',
+            Pixel::SecondPlane => '▒',
+            Pixel::BothPlanes => '▓',
+            Pixel::Off => ' ',
+        }));
+        text.push_str("\r\n");
+    }
+    text
+}
+
+fn is_interrupt(event: &KeyEvent) -> bool {
+    event.code == KeyCode::Char('c') && event.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+// Puts the terminal back how it was, even if the frontend bails out early.
+struct RawModeGuard;
+
+impl RawModeGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let guard = RawModeGuard;
+        execute!(
+            io::stdout(),
+            terminal::EnterAlternateScreen,
+            cursor::Hide,
+            terminal::Clear(ClearType::All)
+        )?;
+        Ok(guard)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        if let Err(err) = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen) {
+            log_error(err);
+        }
+        if let Err(err) = terminal::disable_raw_mode() {
+            log_error(err);
+        }
+    }
+}
+
+// Draws the display with block characters, for running over SSH or anywhere
+// else without a window server. The controls work as in the window, apart from
+// fullscreen.
+pub struct TerminalFrontend {
+    exit_requested: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    frame_channel: Receiver<Grid<Pixel>>,
+    keys_channel: Sender<KeyUpdate>,
+    control_channel: Sender<ControlMessage>,
+    image_buffer: Grid<Pixel>,
+    keymap: HashMap<winit::keyboard::KeyCode, usize>,
+    controls: ControlBindings,
+    instructions_per_second: f64,
+    // Screenshots are saved in colour, as from the window.
+    palette: [[u8; 4]; 4],
+    scale: usize,
+    screenshot_dir: PathBuf,
+    held_keys: [Option<Instant>; 16],
+}
+
+impl TerminalFrontend {
+    pub fn new(
+        config: &FrontendConfig,
+        exit_flag: Arc<AtomicBool>,
+        paused_flag: Arc<AtomicBool>,
+        frame_receiver: Receiver<Grid<Pixel>>,
+        keys_sender: Sender<KeyUpdate>,
+        control_sender: Sender<ControlMessage>,
+    ) -> TerminalFrontend {
+        TerminalFrontend {
+            exit_requested: exit_flag,
+            paused: paused_flag,
+            frame_channel: frame_receiver,
+            keys_channel: keys_sender,
+            control_channel: control_sender,
+            image_buffer: Grid::init(config.height, config.width, Pixel::Off),
+            keymap: config.keymap.clone(),
+            controls: config.controls.clone(),
+            instructions_per_second: config.instructions_per_second,
+            palette: [
+                config.off_colour,
+                config.on_colour,
+                config.second_plane_colour,
+                config.both_planes_colour,
+            ],
+            scale: config.scale,
+            screenshot_dir: config.screenshot_dir.clone(),
+            held_keys: [None; 16],
+        }
+    }
+
+    pub fn run(mut self) -> io::Result<()> {
+        let _guard = RawModeGuard::enter()?;
+        let mut stdout = io::stdout();
+        self.draw(&mut stdout)?;
+
+        while !self.exit_requested.load(Ordering::SeqCst) {
+            if event::poll(POLL_PERIOD)? {
+                if let Event::Key(key_event) = event::read()? {
+                    if is_interrupt(&key_event) {
+                        self.exit_requested.store(true, Ordering::SeqCst);
+                        break;
+                    }
+                    self.key_event(key_event);
+                }
+            }
+
+            self.release_expired_keys(Instant::now());
+
+            if let Some(recv_frame) = self.frame_channel.try_iter().last() {
+                // SCHIP programs switch between lores and hires, so clear any
+                // leftovers of a larger frame.
+                if recv_frame.size() != self.image_buffer.size() {
+                    queue!(stdout, terminal::Clear(ClearType::All))?;
+                }
+                self.image_buffer = recv_frame;
+                self.draw(&mut stdout)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw(&self, stdout: &mut io::Stdout) -> io::Result<()> {
+        queue!(
+            stdout,
+            cursor::MoveTo(0, 0),
+            Print(render_frame(&self.image_buffer))
+        )?;
+        stdout.flush()
+    }
+
+    fn key_event(&mut self, key_event: KeyEvent) {
+        let Some(window_key) = window_key(key_event.code) else {
+            return;
+        };
+        let action = ControlAction::ALL
+            .into_iter()
+            .find(|action| self.controls.key_for(*action) == window_key);
+        if let Some(action) = action {
+            if key_event.kind == KeyEventKind::Press {
+                self.control(action);
+            }
+            return;
+        }
+        let Some(key) = self.keymap.get(&window_key).copied() else {
+            return;
+        };
+
+        match key_event.kind {
+            KeyEventKind::Press | KeyEventKind::Repeat => {
+                if self.held_keys[key].is_none() {
+                    self.send_key(key, KeyStatus::Pressed);
+                }
+                self.held_keys[key] = Some(Instant::now() + KEY_HOLD);
+            }
+            KeyEventKind::Release => {
+                if self.held_keys[key].take().is_some() {
+                    self.send_key(key, KeyStatus::Released);
+                }
+            }
+        }
+    }
+
+    fn control(&mut self, action: ControlAction) {
+        let message = match action {
+            ControlAction::Quit => {
+                self.exit_requested.store(true, Ordering::SeqCst);
+                return;
+            }
+            ControlAction::Pause => {
+                self.paused.fetch_xor(true, Ordering::SeqCst);
+                return;
+            }
+            ControlAction::Screenshot => {
+                match screenshot::save_screenshot(
+                    &self.image_buffer,
+                    &self.palette,
+                    self.scale,
+                    &self.screenshot_dir,
+                ) {
+                    Ok(path) => info!("Saved screenshot to {}", path.display()),
+                    Err(err) => log_error(err),
+                }
+                return;
+            }
+            ControlAction::Fullscreen => return,
+            ControlAction::SpeedUp | ControlAction::SlowDown => {
+                let instructions_per_second = if action == ControlAction::SpeedUp {
+                    clock::speed_up(self.instructions_per_second)
+                } else {
+                    clock::slow_down(self.instructions_per_second)
+                };
+                if instructions_per_second == self.instructions_per_second {
+                    return;
+                }
+                self.instructions_per_second = instructions_per_second;
+                ControlMessage::SetSpeed(instructions_per_second)
+            }
+            ControlAction::Step => ControlMessage::Step,
+            ControlAction::Reset => ControlMessage::Restart,
+            ControlAction::Rewind => ControlMessage::Rewind,
+            ControlAction::Dump => ControlMessage::Dump,
+        };
+        if let Err(err) = self.control_channel.send(message) {
+            log_error(err);
+            self.exit_requested.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn release_expired_keys(&mut self, now: Instant) {
+        for key in 0..self.held_keys.len() {
+            if self.held_keys[key].is_some_and(|deadline| deadline <= now) {
+                self.held_keys[key] = None;
+                self.send_key(key, KeyStatus::Released);
+            }
+        }
+    }
+
+    fn send_key(&mut self, key: usize, status: KeyStatus) {
+        if let Err(err) = self.keys_channel.send(KeyUpdate { key, status }) {
+            log_error(err);
+            self.exit_requested.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::ControlBindings;
+    use crate::keymap::{build_keymap, parse_key_mapping};
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_render_frame() {
+        let mut frame = Grid::init(2, 3, Pixel::Off);
+        frame[(0, 0)] = Pixel::On;
+        frame[(1, 2)] = Pixel::On;
+
+        assert_eq!(render_frame(&frame), "█  \r\n  █\r\n");
+
+        frame[(0, 1)] = Pixel::SecondPlane;
+        frame[(0, 2)] = Pixel::BothPlanes;
+        assert_eq!(render_frame(&frame), "█▒▓\r\n  █\r\n");
+    }
+
+    #[test]
+    fn test_window_key() {
+        use winit::keyboard::KeyCode as WindowKey;
+
+        assert_eq!(window_key(KeyCode::Char('q')), Some(WindowKey::KeyQ));
+        assert_eq!(window_key(KeyCode::Char('Q')), Some(WindowKey::KeyQ));
+        assert_eq!(window_key(KeyCode::Char('4')), Some(WindowKey::Digit4));
+        assert_eq!(window_key(KeyCode::Char(' ')), Some(WindowKey::Space));
+        assert_eq!(window_key(KeyCode::Char('+')), Some(WindowKey::Equal));
+        assert_eq!(window_key(KeyCode::Backspace), Some(WindowKey::Backspace));
+        assert_eq!(window_key(KeyCode::Up), Some(WindowKey::ArrowUp));
+        assert_eq!(window_key(KeyCode::F(5)), Some(WindowKey::F5));
+        assert_eq!(window_key(KeyCode::Char('#')), None);
+        assert_eq!(window_key(KeyCode::CapsLock), None);
+    }
+
+    struct Harness {
+        frontend: TerminalFrontend,
+        keys: Receiver<KeyUpdate>,
+        controls: Receiver<ControlMessage>,
+        paused: Arc<AtomicBool>,
+        exit: Arc<AtomicBool>,
+    }
+
+    fn harness(keymap: HashMap<winit::keyboard::KeyCode, usize>) -> Harness {
+        let (_, frame_rx) = mpsc::channel();
+        let (key_tx, key_rx) = mpsc::channel();
+        let (control_tx, control_rx) = mpsc::channel();
+        let config = FrontendConfig {
+            width: 64,
+            height: 32,
+            window_width: 64,
+            window_height: 32,
+            off_colour: [0; 4],
+            on_colour: [0xFF; 4],
+            second_plane_colour: [0xFF, 0x66, 0x00, 0xFF],
+            both_planes_colour: [0x66, 0x22, 0x00, 0xFF],
+            controls: ControlBindings::default(),
+            virtual_keypad: false,
+            instructions_per_second: 700.0,
+            screenshot_dir: PathBuf::new(),
+            scale: 10,
+            keymap,
+        };
+        let paused = Arc::new(AtomicBool::new(false));
+        let exit = Arc::new(AtomicBool::new(false));
+        let frontend = TerminalFrontend::new(
+            &config,
+            exit.clone(),
+            paused.clone(),
+            frame_rx,
+            key_tx,
+            control_tx,
+        );
+        Harness {
+            frontend,
+            keys: key_rx,
+            controls: control_rx,
+            paused,
+            exit,
+        }
+    }
+
+    #[test]
+    fn test_keys_follow_keymap() {
+        let mappings = [parse_key_mapping("up=5").unwrap()];
+        let keymap = build_keymap(&mappings, &ControlBindings::default()).unwrap();
+        let Harness {
+            mut frontend,
+            keys: key_rx,
+            ..
+        } = harness(keymap);
+
+        for (code, chip8_key) in [(KeyCode::Up, 0x5), (KeyCode::Char('Q'), 0x4)] {
+            frontend.key_event(KeyEvent::new(code, KeyModifiers::NONE));
+            assert_eq!(key_rx.try_recv().unwrap().key, chip8_key);
+        }
+        frontend.key_event(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::NONE));
+        assert!(key_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_controls() {
+        let keymap = build_keymap(&[], &ControlBindings::default()).unwrap();
+        let mut harness = harness(keymap);
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        harness.frontend.key_event(press(KeyCode::Char(' ')));
+        assert!(harness.paused.load(Ordering::SeqCst));
+        harness.frontend.key_event(press(KeyCode::Char('n')));
+        harness.frontend.key_event(press(KeyCode::Backspace));
+        harness.frontend.key_event(press(KeyCode::Char('+')));
+        let messages: Vec<ControlMessage> = harness.controls.try_iter().collect();
+        assert!(matches!(
+            messages[..],
+            [
+                ControlMessage::Step,
+                ControlMessage::Rewind,
+                ControlMessage::SetSpeed(ips)
+            ] if ips > 700.0
+        ));
+        assert!(harness.keys.try_recv().is_err());
+
+        harness.frontend.key_event(press(KeyCode::Esc));
+        assert!(harness.exit.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_held_keys_release_after_timeout() {
+        let keymap = build_keymap(&[], &ControlBindings::default()).unwrap();
+        let Harness {
+            mut frontend,
+            keys: key_rx,
+            ..
+        } = harness(keymap);
+
+        let press = KeyEvent::new(KeyCode::Char('w'), KeyModifiers::NONE);
+        frontend.key_event(press);
+        frontend.key_event(press);
+        let updates: Vec<KeyUpdate> = key_rx.try_iter().collect();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].key, 0x5);
+        assert_eq!(updates[0].status, KeyStatus::Pressed);
+
+        frontend.release_expired_keys(Instant::now());
+        assert!(key_rx.try_recv().is_err());
+
+        frontend.release_expired_keys(Instant::now() + KEY_HOLD);
+        let released = key_rx.try_recv().unwrap();
+        assert_eq!(released.key, 0x5);
+        assert_eq!(released.status, KeyStatus::Released);
+    }
+
+    #[test]
+    fn test_ctrl_c_is_an_interrupt() {
+        assert!(is_interrupt(&KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::CONTROL
+        )));
+        assert!(!is_interrupt(&KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::NONE
+        )));
+    }
+}
@@ -3,38 +3,105 @@ use std::sync::{
     mpsc::Sender,
     Arc,
 };
+use std::time::{Duration, Instant};
+
+// The longest the timer sleeps before checking whether it should exit, so
+// shutting down doesn't wait out a whole period.
+const EXIT_POLL: Duration = Duration::from_millis(5);
 
 pub struct Timer {
     timer_channel: Sender<usize>,
     exit_requested: Arc<AtomicBool>,
-    period: f64,
+    period: Duration,
+    next_tick: Instant,
 }
 
 impl Timer {
     pub fn new(timer_sender: Sender<usize>, exit_flag: Arc<AtomicBool>, period: f64) -> Self {
+        let period = Duration::from_secs_f64(period);
         Timer {
             timer_channel: timer_sender,
             exit_requested: exit_flag,
             period,
+            next_tick: Instant::now() + period,
         }
     }
 
     pub fn run(&mut self) {
-        let timer_duration = std::time::Duration::from_secs_f64(self.period);
-        let mut timer = std::time::Instant::now() + timer_duration;
+        self.next_tick = Instant::now() + self.period;
         while !self.exit_requested.load(Ordering::SeqCst) {
-            let now = std::time::Instant::now();
-            let mut ticks = 0;
-            while now > timer {
-                ticks += 1;
-                timer += timer_duration;
+            let now = Instant::now();
+            let ticks = self.ticks_due(now);
+            if ticks != 0 && self.timer_channel.send(ticks).is_err() {
+                return;
             }
 
-            if ticks != 0 {
-                let _ = self.timer_channel.send(ticks);
+            let now = Instant::now();
+            if self.next_tick > now {
+                std::thread::sleep((self.next_tick - now).min(EXIT_POLL));
             }
+        }
+    }
 
-            std::thread::sleep(std::time::Duration::from_millis(1));
+    // Every deadline passed since the last call is a tick, so ticks missed while
+    // the thread wasn't scheduled arrive together rather than being lost. Later
+    // deadlines stay on the original schedule, so lateness doesn't accumulate.
+    fn ticks_due(&mut self, now: Instant) -> usize {
+        if now < self.next_tick {
+            return 0;
         }
+        let overdue = (now - self.next_tick).as_nanos() / self.period.as_nanos();
+        let ticks = overdue as u32 + 1;
+        self.next_tick += self.period * ticks;
+        ticks as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+
+    const PERIOD: f64 = 0.01;
+
+    fn timer() -> (Timer, mpsc::Receiver<usize>, Arc<AtomicBool>) {
+        let (sender, receiver) = mpsc::channel();
+        let exit = Arc::new(AtomicBool::new(false));
+        (Timer::new(sender, exit.clone(), PERIOD), receiver, exit)
+    }
+
+    #[test]
+    fn test_ticks_due() {
+        let (mut timer, _receiver, _exit) = timer();
+        let start = timer.next_tick;
+        let period = timer.period;
+
+        assert_eq!(timer.ticks_due(start - period / 2), 0);
+        assert_eq!(timer.ticks_due(start), 1);
+        assert_eq!(timer.ticks_due(start + period / 2), 0);
+        // a stall of several periods comes out as one batch
+        assert_eq!(timer.ticks_due(start + period * 4 + period / 2), 4);
+        assert_eq!(timer.next_tick, start + period * 5);
+    }
+
+    #[test]
+    fn test_tick_rate() {
+        let (mut timer, receiver, exit) = timer();
+        let runtime = Duration::from_millis(300);
+        let handle = thread::spawn(move || timer.run());
+        thread::sleep(runtime);
+        exit.store(true, Ordering::SeqCst);
+        handle.join().unwrap();
+
+        let ticks: usize = receiver.try_iter().sum();
+        let expected = (runtime.as_secs_f64() / PERIOD) as usize;
+        assert!(
+            ticks.abs_diff(expected) <= 3,
+            "{} ticks in {:?}, expected about {}",
+            ticks,
+            runtime,
+            expected
+        );
     }
 }
@@ -0,0 +1,133 @@
+use grid::Grid;
+use interpreter::display::Pixel;
+use interpreter::processor::{HEX_SPRITE_DATA, HEX_SPRITE_STRIDE};
+
+pub const KEYPAD_HEIGHT: usize = KEY_ROWS * CELL_HEIGHT;
+
+const KEY_ROWS: usize = 4;
+const KEY_COLUMNS: usize = 4;
+const CELL_HEIGHT: usize = 8;
+const GLYPH_WIDTH: usize = 4;
+
+// The COSMAC VIP keypad, as laid out on the physical device.
+const LAYOUT: [[usize; KEY_COLUMNS]; KEY_ROWS] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+// A 4x4 grid of outlined keys drawn below the display, each labelled with its
+// hex digit from the interpreter's font. The held key is drawn inverted.
+pub struct VirtualKeypad {
+    width: usize,
+}
+
+impl VirtualKeypad {
+    pub fn new(width: usize) -> Self {
+        VirtualKeypad { width }
+    }
+
+    fn cell_width(&self) -> usize {
+        self.width / KEY_COLUMNS
+    }
+
+    // `x` and `y` are buffer pixel coordinates relative to the top left of the
+    // keypad.
+    pub fn key_at(&self, x: usize, y: usize) -> Option<usize> {
+        let column = x / self.cell_width();
+        let row = y / CELL_HEIGHT;
+        if column >= KEY_COLUMNS || row >= KEY_ROWS {
+            return None;
+        }
+
+        Some(LAYOUT[row][column])
+    }
+
+    pub fn render(&self, held: Option<usize>) -> Grid<Pixel> {
+        let cell_width = self.cell_width();
+        let mut image = Grid::init(KEYPAD_HEIGHT, self.width, Pixel::Off);
+
+        for (row, keys) in LAYOUT.iter().enumerate() {
+            for (column, key) in keys.iter().enumerate() {
+                let (background, foreground) = if held == Some(*key) {
+                    (Pixel::On, Pixel::Off)
+                } else {
+                    (Pixel::Off, Pixel::On)
+                };
+                let top = row * CELL_HEIGHT;
+                let left = column * cell_width;
+
+                for y in 0..CELL_HEIGHT {
+                    for x in 0..cell_width {
+                        let edge = y == 0 || y == CELL_HEIGHT - 1 || x == 0 || x == cell_width - 1;
+                        image[(top + y, left + x)] = if edge { Pixel::On } else { background };
+                    }
+                }
+
+                let glyph = &HEX_SPRITE_DATA[key * HEX_SPRITE_STRIDE..][..HEX_SPRITE_STRIDE];
+                let glyph_left = left + (cell_width - GLYPH_WIDTH) / 2;
+                let glyph_top = top + (CELL_HEIGHT - HEX_SPRITE_STRIDE) / 2;
+                for (y, bits) in glyph.iter().enumerate() {
+                    for x in 0..GLYPH_WIDTH {
+                        if bits & (0x80 >> x) != 0 {
+                            image[(glyph_top + y, glyph_left + x)] = foreground;
+                        }
+                    }
+                }
+            }
+        }
+
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_at_follows_layout() {
+        let keypad = VirtualKeypad::new(64);
+
+        assert_eq!(keypad.key_at(0, 0), Some(0x1));
+        assert_eq!(keypad.key_at(15, 7), Some(0x1));
+        assert_eq!(keypad.key_at(16, 0), Some(0x2));
+        assert_eq!(keypad.key_at(63, 0), Some(0xC));
+        assert_eq!(keypad.key_at(20, 12), Some(0x5));
+        assert_eq!(keypad.key_at(40, 20), Some(0x9));
+        assert_eq!(keypad.key_at(16, 24), Some(0x0));
+        assert_eq!(keypad.key_at(63, 31), Some(0xF));
+        assert_eq!(keypad.key_at(64, 0), None);
+        assert_eq!(keypad.key_at(0, 32), None);
+    }
+
+    #[test]
+    fn test_every_key_reachable_once() {
+        let keypad = VirtualKeypad::new(64);
+        let mut seen = [0; 16];
+        for row in 0..KEY_ROWS {
+            for column in 0..KEY_COLUMNS {
+                let key = keypad
+                    .key_at(column * 16 + 8, row * CELL_HEIGHT + 4)
+                    .unwrap();
+                seen[key] += 1;
+            }
+        }
+        assert_eq!(seen, [1; 16]);
+    }
+
+    #[test]
+    fn test_held_key_inverted() {
+        let keypad = VirtualKeypad::new(64);
+        let idle = keypad.render(None);
+        let held = keypad.render(Some(0x1));
+
+        assert_eq!(idle.size(), (KEYPAD_HEIGHT, 64));
+        assert_eq!(idle[(0, 0)], Pixel::On);
+        assert_eq!(idle[(1, 1)], Pixel::Off);
+        assert_eq!(held[(1, 1)], Pixel::On);
+        // other keys are unchanged
+        assert_eq!(idle[(9, 17)], held[(9, 17)]);
+    }
+}
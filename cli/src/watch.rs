@@ -0,0 +1,154 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{self, RecvTimeoutError, Sender},
+    Arc,
+};
+use std::time::{Duration, Instant};
+
+use log::{error, info};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::chip_8_interpreter::ControlMessage;
+
+// Assemblers often write a file in several chunks, so wait for the writes to
+// settle before reloading.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(250);
+const POLL_PERIOD: Duration = Duration::from_millis(50);
+
+pub struct ReloadDebouncer {
+    delay: Duration,
+    last_change: Option<Instant>,
+}
+
+impl ReloadDebouncer {
+    pub fn new(delay: Duration) -> Self {
+        ReloadDebouncer {
+            delay,
+            last_change: None,
+        }
+    }
+
+    pub fn changed(&mut self, now: Instant) {
+        self.last_change = Some(now);
+    }
+
+    // True once per burst of changes, when none have arrived for the delay.
+    pub fn should_reload(&mut self, now: Instant) -> bool {
+        match self.last_change {
+            Some(last_change) if now.duration_since(last_change) >= self.delay => {
+                self.last_change = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+pub fn is_rom_change(event: &Event, rom_path: &Path) -> bool {
+    matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Any
+    ) && event
+        .paths
+        .iter()
+        .any(|path| path.file_name() == rom_path.file_name())
+}
+
+// Watches the ROM's directory rather than the file itself, as editors and
+// assemblers often replace the file instead of writing to it in place. The
+// returned watcher must be kept alive for as long as reloads are wanted.
+pub fn watch_rom(
+    rom_path: PathBuf,
+    control_channel: Sender<ControlMessage>,
+    exit_requested: Arc<AtomicBool>,
+) -> notify::Result<RecommendedWatcher> {
+    let (event_tx, event_rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(event_tx)?;
+    let watch_dir = match rom_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    watcher.watch(&watch_dir, RecursiveMode::NonRecursive)?;
+
+    std::thread::spawn(move || {
+        let mut debouncer = ReloadDebouncer::new(RELOAD_DEBOUNCE);
+        while !exit_requested.load(Ordering::SeqCst) {
+            match event_rx.recv_timeout(POLL_PERIOD) {
+                Ok(Ok(event)) => {
+                    if is_rom_change(&event, &rom_path) {
+                        debouncer.changed(Instant::now());
+                    }
+                }
+                Ok(Err(err)) => error!("Error watching {}: {}", rom_path.display(), err),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+
+            if !debouncer.should_reload(Instant::now()) {
+                continue;
+            }
+
+            match fs::read(&rom_path) {
+                Ok(program) => {
+                    info!("{} changed, reloading", rom_path.display());
+                    if control_channel
+                        .send(ControlMessage::Reload(program))
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+                Err(err) => error!("Error reloading {}: {}", rom_path.display(), err),
+            }
+        }
+    });
+
+    Ok(watcher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, ModifyKind};
+
+    const DELAY: Duration = Duration::from_millis(100);
+
+    fn modified(path: &str) -> Event {
+        Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any))).add_path(path.into())
+    }
+
+    #[test]
+    fn test_rom_change_detected() {
+        let rom = Path::new("roms/game.ch8");
+        assert!(is_rom_change(&modified("/home/me/roms/game.ch8"), rom));
+        assert!(is_rom_change(
+            &Event::new(EventKind::Create(CreateKind::File)).add_path("roms/game.ch8".into()),
+            rom
+        ));
+        assert!(!is_rom_change(&modified("roms/other.ch8"), rom));
+        assert!(!is_rom_change(
+            &Event::new(EventKind::Access(notify::event::AccessKind::Any))
+                .add_path("roms/game.ch8".into()),
+            rom
+        ));
+    }
+
+    #[test]
+    fn test_reload_debounced() {
+        let start = Instant::now();
+        let mut debouncer = ReloadDebouncer::new(DELAY);
+        assert!(!debouncer.should_reload(start));
+
+        // a burst of writes only reloads once, after the last one settles
+        debouncer.changed(start);
+        debouncer.changed(start + Duration::from_millis(60));
+        assert!(!debouncer.should_reload(start + Duration::from_millis(120)));
+        assert!(debouncer.should_reload(start + Duration::from_millis(160)));
+        assert!(!debouncer.should_reload(start + Duration::from_millis(400)));
+
+        debouncer.changed(start + Duration::from_millis(500));
+        assert!(debouncer.should_reload(start + Duration::from_millis(600)));
+    }
+}
@@ -0,0 +1,523 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::instructions::{encode, Instruction};
+use crate::types::{Address, GeneralRegister, Nibble};
+
+const MAX_ADDRESS: u16 = 0x0FFF;
+const MAX_LONG_ADDRESS: u16 = 0xFFFF;
+
+// Operands that are spelled out rather than being a register, number or label.
+const KEYWORDS: [&str; 10] = ["I", "DT", "ST", "K", "F", "HF", "B", "[I]", "R", "LONG"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssembleError {
+    UnknownInstruction { line: usize, text: String },
+    InvalidOperand { line: usize, operand: String },
+    UnknownLabel { line: usize, label: String },
+    DuplicateLabel { line: usize, label: String },
+    InvalidLabel { line: usize, label: String },
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssembleError::UnknownInstruction { line, text } => {
+                write!(f, "Line {}: '{}' isn't an instruction", line, text)
+            }
+            AssembleError::InvalidOperand { line, operand } => {
+                write!(f, "Line {}: '{}' doesn't fit here", line, operand)
+            }
+            AssembleError::UnknownLabel { line, label } => {
+                write!(f, "Line {}: no label called '{}'", line, label)
+            }
+            AssembleError::DuplicateLabel { line, label } => {
+                write!(f, "Line {}: '{}' is already a label", line, label)
+            }
+            AssembleError::InvalidLabel { line, label } => {
+                write!(f, "Line {}: '{}' can't be a label", line, label)
+            }
+        }
+    }
+}
+
+impl core::error::Error for AssembleError {}
+
+struct Statement<'a> {
+    line: usize,
+    tokens: Vec<&'a str>,
+}
+
+// Assembles one statement per line, in the syntax instructions display with,
+// into a program loaded at `start`. Commas are optional, `name:` labels the next
+// address, `;` starts a comment and `DB` lays out data bytes. Keywords and
+// registers are case-insensitive but labels aren't.
+pub fn assemble(source: &str, start: u16) -> Result<Vec<u8>, AssembleError> {
+    let mut labels = BTreeMap::new();
+    let mut statements = Vec::new();
+    let mut address = start;
+
+    for (idx, text) in source.lines().enumerate() {
+        let line = idx + 1;
+        let mut text = text.split(';').next().unwrap_or_default();
+        while let Some((label, rest)) = text.split_once(':') {
+            let label = label.trim();
+            if label.is_empty() || label.contains(char::is_whitespace) {
+                return Err(AssembleError::InvalidLabel {
+                    line,
+                    label: label.to_string(),
+                });
+            }
+            if labels.insert(label, address).is_some() {
+                return Err(AssembleError::DuplicateLabel {
+                    line,
+                    label: label.to_string(),
+                });
+            }
+            text = rest;
+        }
+
+        let tokens: Vec<&str> = text
+            .split(|c: char| c.is_whitespace() || c == ',' || c == '-')
+            .filter(|token| !token.is_empty())
+            .collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        address = address.wrapping_add(statement_size(&tokens));
+        statements.push(Statement { line, tokens });
+    }
+
+    let mut program = Vec::new();
+    for statement in statements {
+        let assembler = Assembler {
+            labels: &labels,
+            line: statement.line,
+        };
+        if statement.tokens[0].eq_ignore_ascii_case("DB") {
+            for token in &statement.tokens[1..] {
+                program.push(assembler.byte(token)?);
+            }
+        } else {
+            program.extend(encode(&assembler.instruction(&statement.tokens)?));
+        }
+    }
+    Ok(program)
+}
+
+fn statement_size(tokens: &[&str]) -> u16 {
+    if tokens[0].eq_ignore_ascii_case("DB") {
+        tokens.len() as u16 - 1
+    } else if tokens
+        .get(2)
+        .is_some_and(|token| token.eq_ignore_ascii_case("LONG"))
+    {
+        4
+    } else {
+        2
+    }
+}
+
+fn parse_register(token: &str) -> Option<GeneralRegister> {
+    let digit = token.strip_prefix(['V', 'v'])?;
+    if digit.len() != 1 {
+        return None;
+    }
+    let value = u8::from_str_radix(digit, 16).ok()?;
+    Nibble::try_from(value).ok().map(GeneralRegister::from)
+}
+
+fn parse_number(token: &str) -> Option<u32> {
+    match token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => token.parse().ok(),
+    }
+}
+
+struct Assembler<'a> {
+    labels: &'a BTreeMap<&'a str, u16>,
+    line: usize,
+}
+
+impl Assembler<'_> {
+    fn instruction(&self, tokens: &[&str]) -> Result<Instruction, AssembleError> {
+        let mnemonic = tokens[0].to_ascii_uppercase();
+        // keywords and registers are matched in upper case, labels as written
+        let operands: Vec<String> = tokens[1..]
+            .iter()
+            .map(|token| {
+                let upper = token.to_ascii_uppercase();
+                if KEYWORDS.contains(&upper.as_str()) || parse_register(token).is_some() {
+                    upper
+                } else {
+                    token.to_string()
+                }
+            })
+            .collect();
+        let operands: Vec<&str> = operands.iter().map(String::as_str).collect();
+        let is_register = |token: &str| parse_register(token).is_some();
+
+        let instruction = match (mnemonic.as_str(), operands.as_slice()) {
+            ("CLS", []) => Instruction::Clear,
+            ("RET", []) => Instruction::Return,
+            ("LOW", []) => Instruction::LowResolution,
+            ("HIGH", []) => Instruction::HighResolution,
+            ("SCR", []) => Instruction::ScrollRight,
+            ("SCL", []) => Instruction::ScrollLeft,
+            ("EXIT", []) => Instruction::Exit,
+            ("AUDIO", []) => Instruction::LoadAudioPattern,
+            ("SCD", [rows]) => Instruction::ScrollDown {
+                rows: self.nibble(rows)?,
+            },
+            ("SYS", [addr]) => Instruction::Sys {
+                addr: self.address(addr)?,
+            },
+            ("JP", ["V0", addr]) => Instruction::JumpPlusV0 {
+                addr: self.address(addr)?,
+            },
+            ("JP", [addr]) => Instruction::Jump {
+                addr: self.address(addr)?,
+            },
+            ("CALL", [addr]) => Instruction::Call {
+                addr: self.address(addr)?,
+            },
+            ("SE", [lhs, rhs]) if is_register(rhs) => Instruction::SkipIfEqReg {
+                lhs: self.register(lhs)?,
+                rhs: self.register(rhs)?,
+            },
+            ("SE", [reg, value]) => Instruction::SkipIfEqByte {
+                reg: self.register(reg)?,
+                value: self.byte(value)?,
+            },
+            ("SNE", [lhs, rhs]) if is_register(rhs) => Instruction::SkipIfNeqReg {
+                lhs: self.register(lhs)?,
+                rhs: self.register(rhs)?,
+            },
+            ("SNE", [reg, value]) => Instruction::SkipIfNeqByte {
+                reg: self.register(reg)?,
+                value: self.byte(value)?,
+            },
+            ("LD", ["I", "LONG", addr]) => Instruction::LoadLongI {
+                addr: Address::extended(self.label_or_value(addr, MAX_LONG_ADDRESS)?),
+            },
+            ("LD", ["I", addr]) => Instruction::LoadI {
+                addr: self.address(addr)?,
+            },
+            ("LD", ["DT", source]) => Instruction::SetDelayTimer {
+                source: self.register(source)?,
+            },
+            ("LD", ["ST", source]) => Instruction::SetSoundTimer {
+                source: self.register(source)?,
+            },
+            ("LD", ["F", digit]) => Instruction::LoadSpriteLocation {
+                digit: self.register(digit)?,
+            },
+            ("LD", ["HF", digit]) => Instruction::LoadHighSpriteLocation {
+                digit: self.register(digit)?,
+            },
+            ("LD", ["B", source]) => Instruction::LoadBcd {
+                source: self.register(source)?,
+            },
+            ("LD", ["[I]", last]) => Instruction::StoreRegisterRangeAtI {
+                last: self.register(last)?,
+            },
+            ("LD", ["R", last]) => Instruction::StoreFlags {
+                last: self.register(last)?,
+            },
+            ("LD", [dest, "DT"]) => Instruction::LoadFromDelayTimer {
+                dest: self.register(dest)?,
+            },
+            ("LD", [dest, "K"]) => Instruction::LoadFromKey {
+                dest: self.register(dest)?,
+            },
+            ("LD", [last, "[I]"]) => Instruction::LoadRegisterRangeFromI {
+                last: self.register(last)?,
+            },
+            ("LD", [last, "R"]) => Instruction::LoadFlags {
+                last: self.register(last)?,
+            },
+            ("LD", [dest, source]) if is_register(source) => Instruction::LoadRegister {
+                dest: self.register(dest)?,
+                source: self.register(source)?,
+            },
+            ("LD", [dest, value]) => Instruction::LoadValue {
+                dest: self.register(dest)?,
+                value: self.byte(value)?,
+            },
+            ("ADD", ["I", source]) => Instruction::AddI {
+                source: self.register(source)?,
+            },
+            ("ADD", [dest, source]) if is_register(source) => Instruction::AddRegister {
+                dest: self.register(dest)?,
+                source: self.register(source)?,
+            },
+            ("ADD", [dest, value]) => Instruction::AddValue {
+                dest: self.register(dest)?,
+                value: self.byte(value)?,
+            },
+            ("OR", [dest, source]) => Instruction::Or {
+                dest: self.register(dest)?,
+                source: self.register(source)?,
+            },
+            ("AND", [dest, source]) => Instruction::And {
+                dest: self.register(dest)?,
+                source: self.register(source)?,
+            },
+            ("XOR", [dest, source]) => Instruction::Xor {
+                dest: self.register(dest)?,
+                source: self.register(source)?,
+            },
+            ("SUB", [dest, source]) => Instruction::Subtract {
+                dest: self.register(dest)?,
+                source: self.register(source)?,
+            },
+            ("SUBN", [dest, source]) => Instruction::SubtractNegate {
+                dest: self.register(dest)?,
+                source: self.register(source)?,
+            },
+            ("SHR", [dest, source]) => Instruction::ShiftRight {
+                dest: self.register(dest)?,
+                source: self.register(source)?,
+            },
+            ("SHL", [dest, source]) => Instruction::ShiftLeft {
+                dest: self.register(dest)?,
+                source: self.register(source)?,
+            },
+            ("RND", [dest, mask]) => Instruction::Random {
+                dest: self.register(dest)?,
+                mask: self.byte(mask)?,
+            },
+            ("DRW", [x, y, num_bytes]) => Instruction::Draw {
+                x: self.register(x)?,
+                y: self.register(y)?,
+                num_bytes: self.nibble(num_bytes)?,
+            },
+            ("SKP", [key_val]) => Instruction::SkipIfKeyDown {
+                key_val: self.register(key_val)?,
+            },
+            ("SKNP", [key_val]) => Instruction::SkipIfKeyUp {
+                key_val: self.register(key_val)?,
+            },
+            ("PLANE", [mask]) => Instruction::SelectPlanes {
+                mask: self.nibble(mask)?,
+            },
+            ("PITCH", [source]) => Instruction::SetPitch {
+                source: self.register(source)?,
+            },
+            ("SAVE", [first, last]) => Instruction::StoreRegisterRange {
+                first: self.register(first)?,
+                last: self.register(last)?,
+            },
+            ("LOAD", [first, last]) => Instruction::LoadRegisterRange {
+                first: self.register(first)?,
+                last: self.register(last)?,
+            },
+            _ => {
+                return Err(AssembleError::UnknownInstruction {
+                    line: self.line,
+                    text: tokens.join(" "),
+                })
+            }
+        };
+        Ok(instruction)
+    }
+
+    fn invalid(&self, operand: &str) -> AssembleError {
+        AssembleError::InvalidOperand {
+            line: self.line,
+            operand: operand.to_string(),
+        }
+    }
+
+    fn register(&self, token: &str) -> Result<GeneralRegister, AssembleError> {
+        parse_register(token).ok_or_else(|| self.invalid(token))
+    }
+
+    fn value(&self, token: &str, max: u16) -> Result<u16, AssembleError> {
+        parse_number(token)
+            .filter(|value| *value <= max as u32)
+            .map(|value| value as u16)
+            .ok_or_else(|| self.invalid(token))
+    }
+
+    fn byte(&self, token: &str) -> Result<u8, AssembleError> {
+        self.value(token, u8::MAX as u16).map(|value| value as u8)
+    }
+
+    fn nibble(&self, token: &str) -> Result<Nibble, AssembleError> {
+        self.value(token, 0x0F)
+            .map(|value| Nibble::from_lower(value as u8))
+    }
+
+    fn label_or_value(&self, token: &str, max: u16) -> Result<u16, AssembleError> {
+        if let Some(address) = self.labels.get(token) {
+            return Ok(*address);
+        }
+        if token.starts_with(|c: char| c.is_ascii_digit()) {
+            return self.value(token, max);
+        }
+        Err(AssembleError::UnknownLabel {
+            line: self.line,
+            label: token.to_string(),
+        })
+    }
+
+    fn address(&self, token: &str) -> Result<Address, AssembleError> {
+        let address = self.label_or_value(token, MAX_ADDRESS)?;
+        if address > MAX_ADDRESS {
+            return Err(self.invalid(token));
+        }
+        Ok(Address::from(address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instructions::disassemble;
+    use alloc::vec;
+    use GeneralRegister::*;
+
+    #[test]
+    fn test_assemble_program() {
+        let source = "
+            ; draws a digit and waits
+                    LD V1, 0x20
+            loop:   ld i, sprite    ; lower case works too
+                    DRW V0 V1 5
+                    ADD V0, 8
+                    SE V0, V2
+                    JP loop
+            done:   JP done
+            sprite: DB 0xF0, 0x90, 0xF0, 0x90, 0x90
+        ";
+        let program = assemble(source, 0x200).unwrap();
+        let instructions: Vec<_> = disassemble(&program, 0x200)
+            .into_iter()
+            .take(7)
+            .map(|(_, instruction)| instruction.unwrap())
+            .collect();
+
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::LoadValue {
+                    dest: V1,
+                    value: 0x20
+                },
+                Instruction::LoadI {
+                    addr: Address::from(0x20E)
+                },
+                Instruction::Draw {
+                    x: V0,
+                    y: V1,
+                    num_bytes: Nibble::Five
+                },
+                Instruction::AddValue { dest: V0, value: 8 },
+                Instruction::SkipIfEqReg { lhs: V0, rhs: V2 },
+                Instruction::Jump {
+                    addr: Address::from(0x202)
+                },
+                Instruction::Jump {
+                    addr: Address::from(0x20C)
+                },
+            ]
+        );
+        assert_eq!(program[14..], [0xF0, 0x90, 0xF0, 0x90, 0x90]);
+    }
+
+    #[test]
+    fn test_assemble_displayed_instructions() {
+        let instructions = [
+            Instruction::LoadFromKey { dest: VA },
+            Instruction::LoadRegisterRangeFromI { last: V3 },
+            Instruction::StoreFlags { last: V7 },
+            Instruction::JumpPlusV0 {
+                addr: Address::from(0x300),
+            },
+            Instruction::ShiftLeft {
+                dest: V1,
+                source: V2,
+            },
+            Instruction::ScrollDown { rows: Nibble::Four },
+            Instruction::StoreRegisterRange {
+                first: V4,
+                last: V1,
+            },
+            Instruction::SelectPlanes {
+                mask: Nibble::Three,
+            },
+            Instruction::LoadLongI {
+                addr: Address::extended(0xBEEF),
+            },
+        ];
+        for instruction in instructions {
+            assert_eq!(
+                assemble(&instruction.to_string(), 0x200),
+                Ok(encode(&instruction)),
+                "{}",
+                instruction
+            );
+        }
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        assert_eq!(
+            assemble("CLS\nFOO V1", 0x200),
+            Err(AssembleError::UnknownInstruction {
+                line: 2,
+                text: "FOO V1".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("LD V1, 0x100", 0x200),
+            Err(AssembleError::InvalidOperand {
+                line: 1,
+                operand: "0x100".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("LD VG, 1", 0x200),
+            Err(AssembleError::InvalidOperand {
+                line: 1,
+                operand: "VG".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("JP nowhere", 0x200),
+            Err(AssembleError::UnknownLabel {
+                line: 1,
+                label: "nowhere".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("a: CLS\na: RET", 0x200),
+            Err(AssembleError::DuplicateLabel {
+                line: 2,
+                label: "a".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("two words: CLS", 0x200),
+            Err(AssembleError::InvalidLabel {
+                line: 1,
+                label: "two words".to_string()
+            })
+        );
+        assert_eq!(assemble("; nothing here\n\n", 0x200), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_assemble_at_start() {
+        assert_eq!(
+            assemble("CLS\nloop: JP loop", 0x600),
+            Ok(vec![0x00, 0xE0, 0x16, 0x02])
+        );
+    }
+}
@@ -0,0 +1,97 @@
+pub struct BuiltinRom {
+    pub name: &'static str,
+    pub data: &'static [u8],
+}
+
+pub const BUILTIN_ROMS: [BuiltinRom; 3] = [
+    BuiltinRom {
+        name: "hex-font",
+        data: &HEX_FONT_ROM,
+    },
+    BuiltinRom {
+        name: "bounce",
+        data: &BOUNCE_ROM,
+    },
+    BuiltinRom {
+        name: "static",
+        data: &STATIC_ROM,
+    },
+];
+
+// Draws the digits 0-F in rows of eight, then spins.
+const HEX_FONT_ROM: [u8; 28] = [
+    0x60, 0x00, // 0x200: LD V0, 0x00   ; digit
+    0x61, 0x00, // 0x202: LD V1, 0x00   ; x
+    0x62, 0x00, // 0x204: LD V2, 0x00   ; y
+    0xF0, 0x29, // 0x206: LD F, V0
+    0xD1, 0x25, // 0x208: DRW V1, V2, 5
+    0x70, 0x01, // 0x20A: ADD V0, 0x01
+    0x71, 0x08, // 0x20C: ADD V1, 0x08
+    0x31, 0x40, // 0x20E: SE V1, 0x40
+    0x12, 0x16, // 0x210: JP 0x216
+    0x61, 0x00, // 0x212: LD V1, 0x00
+    0x72, 0x06, // 0x214: ADD V2, 0x06
+    0x30, 0x10, // 0x216: SE V0, 0x10
+    0x12, 0x06, // 0x218: JP 0x206
+    0x12, 0x1A, // 0x21A: JP 0x21A
+];
+
+// Moves a ball back and forth across the middle of the screen, paced by the
+// delay timer.
+const BOUNCE_ROM: [u8; 42] = [
+    0xA2, 0x22, // 0x200: LD I, 0x222
+    0x60, 0x00, // 0x202: LD V0, 0x00   ; x
+    0x61, 0x0C, // 0x204: LD V1, 0x0C   ; y
+    0x62, 0x01, // 0x206: LD V2, 0x01   ; dx
+    0xD0, 0x18, // 0x208: DRW V0, V1, 8
+    0x63, 0x03, // 0x20A: LD V3, 0x03
+    0xF3, 0x15, // 0x20C: LD DT, V3
+    0xF3, 0x07, // 0x20E: LD V3, DT
+    0x33, 0x00, // 0x210: SE V3, 0x00
+    0x12, 0x0E, // 0x212: JP 0x20E
+    0xD0, 0x18, // 0x214: DRW V0, V1, 8
+    0x80, 0x24, // 0x216: ADD V0, V2
+    0x40, 0x38, // 0x218: SNE V0, 0x38
+    0x62, 0xFF, // 0x21A: LD V2, 0xFF
+    0x40, 0x00, // 0x21C: SNE V0, 0x00
+    0x62, 0x01, // 0x21E: LD V2, 0x01
+    0x12, 0x08, // 0x220: JP 0x208
+    0x3C, 0x7E, 0xFF, 0xFF, 0xFF, 0xFF, 0x7E, 0x3C, // 0x222: ball sprite
+];
+
+// Toggles random pixels forever.
+const STATIC_ROM: [u8; 12] = [
+    0xA2, 0x0A, // 0x200: LD I, 0x20A
+    0xC0, 0x3F, // 0x202: RND V0, 0x3F
+    0xC1, 0x1F, // 0x204: RND V1, 0x1F
+    0xD0, 0x11, // 0x206: DRW V0, V1, 1
+    0x12, 0x02, // 0x208: JP 0x202
+    0x80, 0x00, // 0x20A: dot sprite
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::Processor;
+
+    #[test]
+    fn test_builtin_roms_run() {
+        for rom in &BUILTIN_ROMS {
+            let mut proc = Processor::new(rom.data.to_vec()).unwrap();
+            for _ in 0..1000 {
+                proc.step()
+                    .unwrap_or_else(|err| panic!("{} failed: {}", rom.name, err));
+                proc.tick();
+            }
+        }
+    }
+
+    #[test]
+    fn test_builtin_rom_names_unique() {
+        for (idx, rom) in BUILTIN_ROMS.iter().enumerate() {
+            assert!(BUILTIN_ROMS[idx + 1..]
+                .iter()
+                .all(|other| other.name != rom.name));
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::processor::{Processor, MEMORY_SIZE_BYTES};
+use crate::types::Address;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheatMode {
+    EveryFrame,
+    Once,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cheat {
+    pub address: Address,
+    pub value: u8,
+    pub mode: CheatMode,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheatError {
+    Malformed { spec: String },
+    AddressOutOfRange { address: u16 },
+}
+
+impl fmt::Display for CheatError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CheatError::Malformed { spec } => write!(
+                f,
+                "Can't parse cheat '{}', expected ADDR=VALUE or ADDR=VALUE,once in hex",
+                spec
+            ),
+            CheatError::AddressOutOfRange { address } => write!(
+                f,
+                "Cheat address {:#05x} is outside of memory, max is {:#05x}",
+                address,
+                MEMORY_SIZE_BYTES - 1
+            ),
+        }
+    }
+}
+
+impl core::error::Error for CheatError {}
+
+fn parse_hex<T: TryFrom<u32>>(text: &str) -> Option<T> {
+    let digits = text.trim();
+    let digits = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+        .unwrap_or(digits);
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .and_then(|value| T::try_from(value).ok())
+}
+
+// Parses `ADDR=VALUE` (applied every frame) or `ADDR=VALUE,once`, with both
+// numbers in hex, e.g. `0x3A0=09`.
+pub fn parse_cheat(spec: &str) -> Result<Cheat, CheatError> {
+    let malformed = || CheatError::Malformed {
+        spec: spec.to_string(),
+    };
+
+    let (poke, mode) = match spec.trim().split_once(',') {
+        Some((poke, modifier)) if modifier.trim().eq_ignore_ascii_case("once") => {
+            (poke, CheatMode::Once)
+        }
+        Some(_) => return Err(malformed()),
+        None => (spec, CheatMode::EveryFrame),
+    };
+
+    let (address, value) = poke.split_once('=').ok_or_else(malformed)?;
+    let address: u16 = parse_hex(address).ok_or_else(malformed)?;
+    let value: u8 = parse_hex(value).ok_or_else(malformed)?;
+
+    if address as usize >= MEMORY_SIZE_BYTES {
+        return Err(CheatError::AddressOutOfRange { address });
+    }
+
+    Ok(Cheat {
+        address: Address::from(address),
+        value,
+        mode,
+    })
+}
+
+// One cheat per line, blank lines and anything after a `#` are ignored.
+pub fn parse_cheat_file(contents: &str) -> Result<Vec<Cheat>, CheatError> {
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(parse_cheat)
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CheatList {
+    cheats: Vec<Cheat>,
+}
+
+impl CheatList {
+    pub fn new(cheats: Vec<Cheat>) -> Self {
+        CheatList { cheats }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    // Meant to be called once per frame, after the frame's instructions have
+    // run, so the pokes win over anything the ROM wrote during the frame.
+    // One-shot cheats are dropped after their first application.
+    pub fn apply(&mut self, processor: &mut Processor) {
+        for cheat in &self.cheats {
+            processor.write_memory(u16::from(cheat.address), cheat.value);
+        }
+
+        self.cheats
+            .retain(|cheat| cheat.mode == CheatMode::EveryFrame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cheat() {
+        assert_eq!(
+            parse_cheat("0x3A0=09"),
+            Ok(Cheat {
+                address: Address::from(0x3A0),
+                value: 0x09,
+                mode: CheatMode::EveryFrame,
+            })
+        );
+        assert_eq!(
+            parse_cheat("3a0=ff,once"),
+            Ok(Cheat {
+                address: Address::from(0x3A0),
+                value: 0xFF,
+                mode: CheatMode::Once,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_cheat_invalid() {
+        assert!(matches!(
+            parse_cheat("3A0"),
+            Err(CheatError::Malformed { .. })
+        ));
+        assert!(matches!(
+            parse_cheat("3A0=100"),
+            Err(CheatError::Malformed { .. })
+        ));
+        assert!(matches!(
+            parse_cheat("3A0=01,twice"),
+            Err(CheatError::Malformed { .. })
+        ));
+        assert_eq!(
+            parse_cheat("1000=01"),
+            Err(CheatError::AddressOutOfRange { address: 0x1000 })
+        );
+    }
+
+    #[test]
+    fn test_parse_cheat_file() {
+        let cheats =
+            parse_cheat_file("# infinite lives\n3A0=09\n\n3A1=00,once # skip intro\n").unwrap();
+        assert_eq!(cheats.len(), 2);
+        assert_eq!(cheats[0].mode, CheatMode::EveryFrame);
+        assert_eq!(cheats[1].address, Address::from(0x3A1));
+        assert_eq!(cheats[1].mode, CheatMode::Once);
+    }
+}
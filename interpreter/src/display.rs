@@ -1,23 +1,39 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt::{self, Write};
+
 use grid::Grid;
 use strum_macros::Display;
 
+// Which of XO-CHIP's two planes are lit, which picks the pixel's colour. Classic
+// CHIP-8 and SCHIP only draw to the first plane, so only use Off and On.
 #[derive(Debug, Display, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Pixel {
     Off,
     On,
+    SecondPlane,
+    BothPlanes,
 }
 
 impl Pixel {
-    fn flip(&mut self) -> bool {
+    // Bit 0 is the first plane and bit 1 the second, as in XO-CHIP's plane masks.
+    pub fn from_planes(planes: u8) -> Pixel {
+        match planes & ALL_PLANES {
+            0 => Pixel::Off,
+            FIRST_PLANE => Pixel::On,
+            SECOND_PLANE => Pixel::SecondPlane,
+            _ => Pixel::BothPlanes,
+        }
+    }
+
+    pub fn planes(self) -> u8 {
         match self {
-            Pixel::Off => {
-                *self = Pixel::On;
-                false
-            }
-            Pixel::On => {
-                *self = Pixel::Off;
-                true
-            }
+            Pixel::Off => 0,
+            Pixel::On => FIRST_PLANE,
+            Pixel::SecondPlane => SECOND_PLANE,
+            Pixel::BothPlanes => ALL_PLANES,
         }
     }
 }
@@ -28,77 +44,712 @@ pub enum PixelsDisabled {
     SomePixels,
 }
 
+// What a draw changed, for frontends that want to do more than show the new
+// frame, like flashing the pixels a sprite erased. Pixels are (row, col) in
+// row-major order, and count as lit when they're lit in any plane, so a pixel
+// that only changes colour between planes isn't listed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DrawReport {
+    pub turned_on: Vec<(usize, usize)>,
+    pub turned_off: Vec<(usize, usize)>,
+    pub pixels_disabled: PixelsDisabled,
+}
+
 #[derive(Debug, PartialEq, Eq)]
+pub enum PbmError {
+    NotPlainPbm,
+    BadDimensions,
+    BadPixel { value: char },
+    WrongPixelCount { expected: usize, found: usize },
+}
+
+impl fmt::Display for PbmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PbmError::NotPlainPbm => write!(f, "Expected a plain (P1) PBM image"),
+            PbmError::BadDimensions => write!(f, "Missing or invalid PBM width and height"),
+            PbmError::BadPixel { value } => write!(f, "Invalid PBM pixel '{}'", value),
+            PbmError::WrongPixelCount { expected, found } => {
+                write!(f, "Expected {} PBM pixels, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl core::error::Error for PbmError {}
+
+// Reads a plain PBM image, where 1 is a lit pixel. Comments start with '#'
+// and, as the format allows, pixels needn't be separated by whitespace.
+pub fn parse_pbm(text: &str) -> Result<Grid<Pixel>, PbmError> {
+    let mut tokens = text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(str::split_whitespace);
+
+    if tokens.next() != Some("P1") {
+        return Err(PbmError::NotPlainPbm);
+    }
+    let mut dimension = || -> Result<usize, PbmError> {
+        tokens
+            .next()
+            .and_then(|token| token.parse().ok())
+            .filter(|value| *value > 0)
+            .ok_or(PbmError::BadDimensions)
+    };
+    let width = dimension()?;
+    let height = dimension()?;
+
+    let pixels = tokens
+        .flat_map(str::chars)
+        .map(|value| match value {
+            '0' => Ok(Pixel::Off),
+            '1' => Ok(Pixel::On),
+            value => Err(PbmError::BadPixel { value }),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if pixels.len() != width * height {
+        return Err(PbmError::WrongPixelCount {
+            expected: width * height,
+            found: pixels.len(),
+        });
+    }
+    Ok(Grid::from_vec(pixels, width))
+}
+
+// Bytes per row of a SCHIP 16x16 sprite.
+const WIDE_SPRITE_ROW_BYTES: usize = 2;
+// SCHIP's 00FB and 00FC always scroll by four pixels.
+const SCROLL_COLUMNS: usize = 4;
+
+pub const FIRST_PLANE: u8 = 0b01;
+pub const SECOND_PLANE: u8 = 0b10;
+pub const ALL_PLANES: u8 = FIRST_PLANE | SECOND_PLANE;
+const PLANE_COUNT: usize = 2;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+// The pixels each byte of a bitline stands for, so turning the display back
+// into a grid is a copy per byte rather than a test per pixel.
+const BYTE_PIXELS: [[Pixel; 8]; 256] = {
+    let mut table = [[Pixel::Off; 8]; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut bit = 0;
+        while bit < 8 {
+            if byte & (0x80 >> bit) != 0 {
+                table[byte][bit] = Pixel::On;
+            }
+            bit += 1;
+        }
+        byte += 1;
+    }
+    table
+};
+
+// Each row is packed into words with the leftmost pixel in the most significant
+// bit, so drawing a sprite byte is an XOR and checking for collisions an AND.
+// XO-CHIP's two planes are kept apart, and drawing, clearing and scrolling only
+// touch the selected ones.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SavedDisplay", into = "SavedDisplay")
+)]
 pub struct Display {
-    display_buffer: Grid<Pixel>,
+    planes: [Vec<u64>; PLANE_COUNT],
+    selected_planes: u8,
+    width: usize,
+    height: usize,
+    words_per_row: usize,
+    hires: bool,
     dirty: bool,
 }
 
+// The display as saved, checked on load so a corrupt save can't produce a
+// ragged grid.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedDisplay {
+    cols: usize,
+    pixels: Vec<Pixel>,
+    hires: bool,
+    selected_planes: u8,
+}
+
+#[cfg(feature = "serde")]
+impl From<Display> for SavedDisplay {
+    fn from(display: Display) -> Self {
+        SavedDisplay {
+            cols: display.width,
+            pixels: display.to_grid().into_vec(),
+            hires: display.hires,
+            selected_planes: display.selected_planes,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SavedDisplay> for Display {
+    type Error = &'static str;
+
+    fn try_from(saved: SavedDisplay) -> Result<Self, Self::Error> {
+        if saved.cols == 0
+            || saved.pixels.is_empty()
+            || !saved.pixels.len().is_multiple_of(saved.cols)
+        {
+            return Err("display pixels don't fill whole rows");
+        }
+        if saved.selected_planes & !ALL_PLANES != 0 {
+            return Err("display has planes selected that don't exist");
+        }
+        let mut display = Display::from_vec(saved.pixels, saved.cols);
+        display.hires = saved.hires;
+        display.selected_planes = saved.selected_planes;
+        Ok(display)
+    }
+}
+
 impl Display {
     pub fn new(width: usize, height: usize) -> Self {
+        let words_per_row = width.div_ceil(WORD_BITS);
         Display {
-            display_buffer: Grid::<Pixel>::init(height, width, Pixel::Off),
+            planes: core::array::from_fn(|_| vec![0; words_per_row * height]),
+            selected_planes: FIRST_PLANE,
+            width,
+            height,
+            words_per_row,
+            hires: false,
             dirty: true,
         }
     }
 
     pub fn from_vec(vec: Vec<Pixel>, cols: usize) -> Self {
-        Display {
-            display_buffer: Grid::<Pixel>::from_vec(vec, cols),
-            dirty: true,
+        let grid = Grid::from_vec(vec, cols);
+        let mut display = Display::new(grid.cols(), grid.rows());
+        for ((row, col), pixel) in grid.indexed_iter() {
+            display.set_pixel(row, col, *pixel);
         }
+        display
     }
 
+    // The display is only marked dirty when its contents change, so frontends
+    // aren't sent identical frames.
     pub fn clear(&mut self) {
-        self.display_buffer.fill(Pixel::Off);
-        self.dirty = true;
+        for plane in self.selected_plane_indices() {
+            let bitlines = &mut self.planes[plane];
+            if bitlines.iter().any(|word| *word != 0) {
+                bitlines.fill(0);
+                self.dirty = true;
+            }
+        }
     }
 
+    // Back to how the display is at power on: lores, blank and drawing to the
+    // first plane.
+    pub(crate) fn reset(&mut self) {
+        self.set_hires(false);
+        self.selected_planes = ALL_PLANES;
+        self.clear();
+        self.selected_planes = FIRST_PLANE;
+    }
+
+    // XO-CHIP's FN01. Bit 0 selects the first plane and bit 1 the second.
+    pub fn select_planes(&mut self, planes: u8) {
+        self.selected_planes = planes & ALL_PLANES;
+    }
+
+    pub fn selected_planes(&self) -> u8 {
+        self.selected_planes
+    }
+
+    // Sprites hold data for each selected plane in turn, so this many times
+    // the bytes of a single plane's sprite are needed to draw one.
+    pub fn selected_plane_count(&self) -> usize {
+        self.selected_planes.count_ones() as usize
+    }
+
+    fn selected_plane_indices(&self) -> impl Iterator<Item = usize> {
+        let selected_planes = self.selected_planes;
+        (0..PLANE_COUNT).filter(move |plane| selected_planes & (1 << plane) != 0)
+    }
+
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // The (rows, cols) the display has in lores, whichever mode it's in now.
+    #[cfg(feature = "serde")]
+    pub(crate) fn lores_size(&self) -> (usize, usize) {
+        if self.hires {
+            (self.height / 2, self.width / 2)
+        } else {
+            (self.height, self.width)
+        }
+    }
+
+    // SCHIP's hires mode doubles the display in both directions, so 64x32
+    // becomes 128x64. Switching resolution clears the display, and sprite
+    // coordinates are always in pixels of the current resolution.
+    pub fn set_hires(&mut self, hires: bool) {
+        if hires == self.hires {
+            return;
+        }
+
+        let (width, height) = if hires {
+            (self.width * 2, self.height * 2)
+        } else {
+            (self.width / 2, self.height / 2)
+        };
+        let selected_planes = self.selected_planes;
+        *self = Display::new(width, height);
+        self.hires = hires;
+        self.selected_planes = selected_planes;
+    }
+
+    // The start coordinates wrap, as on the COSMAC VIP and later interpreters,
+    // so a sprite starting at x == cols or y == rows is drawn from the left or
+    // top edge. Only the sprite's body is clipped at the edges.
     pub fn draw_sprite(&mut self, x: usize, y: usize, data: &[u8]) -> PixelsDisabled {
-        let leftmost_column = x % self.display_buffer.cols();
-        let mut row = y % self.display_buffer.rows();
+        self.draw(x, y, data, 1, true)
+    }
+
+    // Draws like draw_sprite, reporting each pixel the sprite turned on or off.
+    // Only the rows the sprite covers are compared.
+    pub fn draw_sprite_report(&mut self, x: usize, y: usize, data: &[u8]) -> DrawReport {
+        let top_row = y % self.height;
+        let sprite_rows = data.len() / self.selected_plane_count().max(1);
+        let bottom_row = (top_row + sprite_rows.max(1)).min(self.height);
+        let words = top_row * self.words_per_row..bottom_row * self.words_per_row;
+        let lit = |planes: &[Vec<u64>; PLANE_COUNT], word: usize| {
+            planes.iter().fold(0, |lit, plane| lit | plane[word])
+        };
+        let before: Vec<u64> = words.clone().map(|word| lit(&self.planes, word)).collect();
+        let pixels_disabled = self.draw_sprite(x, y, data);
+
+        let mut report = DrawReport {
+            turned_on: Vec::new(),
+            turned_off: Vec::new(),
+            pixels_disabled,
+        };
+        for (word, was_lit) in words.zip(before) {
+            let now_lit = lit(&self.planes, word);
+            if was_lit == now_lit {
+                continue;
+            }
+            let row = word / self.words_per_row;
+            let first_col = (word % self.words_per_row) * WORD_BITS;
+            for bit in 0..WORD_BITS {
+                let mask = 1 << (WORD_BITS - 1 - bit);
+                if now_lit & !was_lit & mask != 0 {
+                    report.turned_on.push((row, first_col + bit));
+                } else if was_lit & !now_lit & mask != 0 {
+                    report.turned_off.push((row, first_col + bit));
+                }
+            }
+        }
+        report
+    }
+
+    // Like draw_sprite, but the parts of the sprite past the right or bottom
+    // edge wrap around to the opposite edge instead of being clipped.
+    pub fn draw_sprite_wrapping(&mut self, x: usize, y: usize, data: &[u8]) -> PixelsDisabled {
+        self.draw(x, y, data, 1, false)
+    }
+
+    // Draws a SCHIP 16x16 sprite, where each row is two bytes wide.
+    pub fn draw_wide_sprite(&mut self, x: usize, y: usize, data: &[u8]) -> PixelsDisabled {
+        self.draw(x, y, data, WIDE_SPRITE_ROW_BYTES, true)
+    }
+
+    pub fn draw_wide_sprite_wrapping(&mut self, x: usize, y: usize, data: &[u8]) -> PixelsDisabled {
+        self.draw(x, y, data, WIDE_SPRITE_ROW_BYTES, false)
+    }
+
+    // With more than one plane selected, data holds a sprite for each of them,
+    // one after the other.
+    fn draw(
+        &mut self,
+        x: usize,
+        y: usize,
+        data: &[u8],
+        row_bytes: usize,
+        clip: bool,
+    ) -> PixelsDisabled {
+        let plane_count = self.selected_plane_count();
+        if plane_count == 0 {
+            return PixelsDisabled::NoPixels;
+        }
+
+        let sprite_len = (data.len() / plane_count).max(1);
+        let mut pixels_disabled = PixelsDisabled::NoPixels;
+        for (plane, sprite) in self.selected_plane_indices().zip(data.chunks(sprite_len)) {
+            if self.draw_plane(plane, x, y, sprite, row_bytes, clip) == PixelsDisabled::SomePixels {
+                pixels_disabled = PixelsDisabled::SomePixels;
+            }
+        }
+        pixels_disabled
+    }
+
+    fn draw_plane(
+        &mut self,
+        plane: usize,
+        x: usize,
+        y: usize,
+        data: &[u8],
+        row_bytes: usize,
+        clip: bool,
+    ) -> PixelsDisabled {
+        let rows = self.height;
+        let leftmost_column = x % self.width;
+        let top_row = y % rows;
         let mut pixels_disabled = PixelsDisabled::NoPixels;
 
-        for datum in data {
-            if row >= self.display_buffer.rows() {
+        for (offset, row_data) in data.chunks(row_bytes).enumerate() {
+            let row = top_row + offset;
+            if clip && row >= rows {
                 break;
             }
 
-            if self.draw_byte(leftmost_column, row, *datum) == PixelsDisabled::SomePixels {
-                pixels_disabled = PixelsDisabled::SomePixels;
+            for (idx, datum) in row_data.iter().enumerate() {
+                if self.draw_byte(plane, leftmost_column + 8 * idx, row % rows, *datum, clip)
+                    == PixelsDisabled::SomePixels
+                {
+                    pixels_disabled = PixelsDisabled::SomePixels;
+                }
+            }
+        }
+
+        pixels_disabled
+    }
+
+    // The scrolls move the contents of the selected planes, filling the space
+    // left behind with unlit pixels. Anything scrolled off the edge is lost.
+    pub fn scroll_down(&mut self, rows: usize) {
+        let words_per_row = self.words_per_row;
+        for plane in self.selected_plane_indices() {
+            // from the bottom up, so each row is read before it's overwritten
+            for word in (0..self.planes[plane].len()).rev() {
+                let shifted = match word.checked_sub(rows * words_per_row) {
+                    Some(source) => self.planes[plane][source],
+                    None => 0,
+                };
+                self.set_word(plane, word, shifted);
+            }
+        }
+    }
+
+    pub fn scroll_right(&mut self) {
+        let last_word_mask = self.last_word_mask();
+        for plane in self.selected_plane_indices() {
+            for row in 0..self.height {
+                let first = row * self.words_per_row;
+                // right to left, pulling in the low bits of the word before
+                for word in (first..first + self.words_per_row).rev() {
+                    let carried = if word > first {
+                        self.planes[plane][word - 1] << (WORD_BITS - SCROLL_COLUMNS)
+                    } else {
+                        0
+                    };
+                    let mut shifted = self.planes[plane][word] >> SCROLL_COLUMNS | carried;
+                    if word == first + self.words_per_row - 1 {
+                        shifted &= last_word_mask;
+                    }
+                    self.set_word(plane, word, shifted);
+                }
+            }
+        }
+    }
+
+    pub fn scroll_left(&mut self) {
+        for plane in self.selected_plane_indices() {
+            for row in 0..self.height {
+                let first = row * self.words_per_row;
+                let last = first + self.words_per_row - 1;
+                // left to right, pulling in the high bits of the word after
+                for word in first..=last {
+                    let carried = if word < last {
+                        self.planes[plane][word + 1] >> (WORD_BITS - SCROLL_COLUMNS)
+                    } else {
+                        0
+                    };
+                    let shifted = self.planes[plane][word] << SCROLL_COLUMNS | carried;
+                    self.set_word(plane, word, shifted);
+                }
             }
+        }
+    }
 
-            row += 1;
+    // Clears the padding past the right edge in the last word of each row,
+    // which has to stay unlit.
+    fn last_word_mask(&self) -> u64 {
+        match self.width % WORD_BITS {
+            0 => u64::MAX,
+            used => u64::MAX << (WORD_BITS - used),
         }
+    }
 
+    fn set_word(&mut self, plane: usize, word: usize, value: u64) {
+        if self.planes[plane][word] != value {
+            self.planes[plane][word] = value;
+            self.dirty = true;
+        }
+    }
+
+    // The word holding a pixel, and the mask for its bit within that word.
+    fn locate(&self, row: usize, col: usize) -> (usize, u64) {
+        let word = row * self.words_per_row + col / WORD_BITS;
+        let mask = 1 << (WORD_BITS - 1 - col % WORD_BITS);
+        (word, mask)
+    }
+
+    fn plane_pixel(&self, plane: usize, row: usize, col: usize) -> bool {
+        let (word, mask) = self.locate(row, col);
+        self.planes[plane][word] & mask != 0
+    }
+
+    fn set_plane_pixel(&mut self, plane: usize, row: usize, col: usize, lit: bool) {
+        if self.plane_pixel(plane, row, col) != lit {
+            let (word, mask) = self.locate(row, col);
+            self.planes[plane][word] ^= mask;
+            self.dirty = true;
+        }
+    }
+
+    fn pixel(&self, row: usize, col: usize) -> Pixel {
+        let planes = (0..PLANE_COUNT)
+            .filter(|plane| self.plane_pixel(*plane, row, col))
+            .fold(0, |planes, plane| planes | 1 << plane);
+        Pixel::from_planes(planes)
+    }
+
+    fn set_pixel(&mut self, row: usize, col: usize, pixel: Pixel) {
+        for plane in 0..PLANE_COUNT {
+            let lit = pixel.planes() & (1 << plane) != 0;
+            self.set_plane_pixel(plane, row, col, lit);
+        }
+    }
+
+    pub(crate) fn mark_dirty(&mut self) {
         self.dirty = true;
-        pixels_disabled
     }
 
-    pub fn get_display_buffer(&mut self) -> Option<&Grid<Pixel>> {
+    // Frontends get the display as a grid of pixels, which is only built when
+    // there's a new frame to send.
+    pub fn get_display_buffer(&mut self) -> Option<Grid<Pixel>> {
         if self.dirty {
             self.dirty = false;
-            Some(&self.display_buffer)
+            Some(self.to_grid())
         } else {
             None
         }
     }
 
-    fn draw_byte(&mut self, col: usize, row: usize, value: u8) -> PixelsDisabled {
-        let mut draw_column = col;
-        let mut turned_any_off = false;
-
-        for shift in 0..8 {
-            match self.display_buffer.get_mut(row, draw_column) {
-                Some(pixel) => {
-                    if (value >> (7 - shift)) & 1 == 1 {
-                        turned_any_off |= pixel.flip();
+    pub fn to_grid(&self) -> Grid<Pixel> {
+        let [first, second] = &self.planes;
+        let mut pixels = Vec::with_capacity(first.len() * WORD_BITS);
+        for (first_line, second_line) in first
+            .chunks(self.words_per_row)
+            .zip(second.chunks(self.words_per_row))
+        {
+            let row_start = pixels.len();
+            for (first_word, second_word) in first_line.iter().zip(second_line) {
+                for (first_byte, second_byte) in first_word
+                    .to_be_bytes()
+                    .into_iter()
+                    .zip(second_word.to_be_bytes())
+                {
+                    if second_byte == 0 {
+                        pixels.extend_from_slice(&BYTE_PIXELS[first_byte as usize]);
+                        continue;
                     }
-                    draw_column += 1;
+                    pixels.extend((0..8).map(|bit| {
+                        let first_lit = (first_byte << bit) >> 7;
+                        let second_lit = (second_byte << bit) >> 7;
+                        Pixel::from_planes(first_lit | second_lit << 1)
+                    }));
                 }
-                None => break,
             }
+            // The last word is padded out past the right edge.
+            pixels.truncate(row_start + self.width);
         }
+        Grid::from_vec(pixels, self.width)
+    }
+
+    // Packs the display into bytes the way the COSMAC VIP laid out display RAM:
+    // row by row from the top, eight pixels per byte with the leftmost pixel in
+    // the most significant bit. The VIP only had one plane, so only the first is
+    // packed.
+    pub(crate) fn to_packed_bytes(&self) -> Vec<u8> {
+        if !self.width.is_multiple_of(8) {
+            return self.to_packed_bytes_unaligned();
+        }
+        let row_bytes = self.width / 8;
+        let mut bytes = Vec::with_capacity(self.packed_len() + WORD_BITS / 8);
+        for line in self.planes[0].chunks(self.words_per_row) {
+            let row_start = bytes.len();
+            for word in line {
+                bytes.extend_from_slice(&word.to_be_bytes());
+            }
+            // The last word is padded out past the right edge.
+            bytes.truncate(row_start + row_bytes);
+        }
+        bytes
+    }
+
+    // Rows that don't end on a byte boundary share a byte with the next row,
+    // so these go pixel by pixel.
+    fn to_packed_bytes_unaligned(&self) -> Vec<u8> {
+        let mut bytes = vec![0_u8; self.packed_len()];
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if self.plane_pixel(0, row, col) {
+                    let bit = row * self.width + col;
+                    bytes[bit / 8] |= 0x80 >> (bit % 8);
+                }
+            }
+        }
+        bytes
+    }
+
+    // Missing bytes at the end count as unlit.
+    pub(crate) fn load_packed_bytes(&mut self, bytes: &[u8]) {
+        if !self.width.is_multiple_of(8) {
+            self.load_packed_bytes_unaligned(bytes);
+            return;
+        }
+        let row_bytes = self.width / 8;
+        let words_per_row = self.words_per_row;
+        for (row, line) in self.planes[0].chunks_mut(words_per_row).enumerate() {
+            let row_data = bytes.get(row * row_bytes..).unwrap_or_default();
+            let row_data = &row_data[..row_data.len().min(row_bytes)];
+            line.fill(0);
+            for (word, chunk) in line.iter_mut().zip(row_data.chunks(WORD_BITS / 8)) {
+                let mut word_bytes = [0; WORD_BITS / 8];
+                word_bytes[..chunk.len()].copy_from_slice(chunk);
+                *word = u64::from_be_bytes(word_bytes);
+            }
+        }
+        self.dirty = true;
+    }
+
+    fn load_packed_bytes_unaligned(&mut self, bytes: &[u8]) {
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let bit = row * self.width + col;
+                let byte = bytes.get(bit / 8).copied().unwrap_or(0);
+                self.set_plane_pixel(0, row, col, byte & (0x80 >> (bit % 8)) != 0);
+            }
+        }
+        self.dirty = true;
+    }
+
+    pub(crate) fn packed_len(&self) -> usize {
+        (self.height * self.width).div_ceil(8)
+    }
+
+    pub fn equals_grid(&self, other: &Grid<Pixel>) -> bool {
+        self.to_grid() == *other
+    }
+
+    pub fn diff_grid(&self, other: &Grid<Pixel>) -> String {
+        if (self.height, self.width) != other.size() {
+            return format!(
+                "Size mismatch: display is {}x{}, other is {}x{}",
+                self.width,
+                self.height,
+                other.cols(),
+                other.rows()
+            );
+        }
+
+        let mut diff = String::new();
+        for ((row, col), other_pixel) in other.indexed_iter() {
+            let pixel = self.pixel(row, col);
+            if pixel != *other_pixel {
+                let _ = writeln!(
+                    diff,
+                    "row {}, col {}: display is {}, other is {}",
+                    row, col, pixel, other_pixel
+                );
+            }
+        }
+        diff
+    }
+
+    // One line per row, '#' for lit pixels and '.' for unlit ones. Pixels lit
+    // only in XO-CHIP's second plane are '+', and those lit in both '%'.
+    pub fn to_ascii(&self) -> String {
+        self.render_rows(['.', '#', '+', '%'])
+    }
+
+    // Writes the display as a plain PBM image with one line per row. PBM only
+    // has two colours, so a pixel lit in either plane is lit.
+    pub fn to_pbm(&self) -> String {
+        format!(
+            "P1\n{} {}\n{}",
+            self.width,
+            self.height,
+            self.render_rows(['0', '1', '1', '1'])
+        )
+    }
+
+    // Indexed by the planes lit, as in Pixel::planes.
+    fn render_rows(&self, chars: [char; 4]) -> String {
+        let [first, second] = &self.planes;
+        let mut text = String::with_capacity(self.height * (self.width + 1));
+        for (first_line, second_line) in first
+            .chunks(self.words_per_row)
+            .zip(second.chunks(self.words_per_row))
+        {
+            for col in 0..self.width {
+                let shift = WORD_BITS - 1 - col % WORD_BITS;
+                let first_lit = first_line[col / WORD_BITS] >> shift & 1;
+                let second_lit = second_line[col / WORD_BITS] >> shift & 1;
+                text.push(chars[(first_lit | second_lit << 1) as usize]);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn draw_byte(
+        &mut self,
+        plane: usize,
+        col: usize,
+        row: usize,
+        value: u8,
+        clip: bool,
+    ) -> PixelsDisabled {
+        let turned_any_off = if col + 8 <= self.width {
+            // The byte lined up against this word and the next, which it only
+            // spills into when it doesn't start on a word boundary.
+            let word = row * self.words_per_row + col / WORD_BITS;
+            let spread = (value as u128) << (2 * WORD_BITS - 8 - col % WORD_BITS);
+            let mut turned_any_off = false;
+            for (idx, mask) in [(spread >> WORD_BITS) as u64, spread as u64]
+                .into_iter()
+                .enumerate()
+            {
+                if mask != 0 {
+                    turned_any_off |= self.planes[plane][word + idx] & mask != 0;
+                    self.planes[plane][word + idx] ^= mask;
+                }
+            }
+            self.dirty |= value != 0;
+            turned_any_off
+        } else {
+            self.draw_byte_at_edge(plane, col, row, value, clip)
+        };
 
         if turned_any_off {
             PixelsDisabled::SomePixels
@@ -106,6 +757,31 @@ impl Display {
             PixelsDisabled::NoPixels
         }
     }
+
+    // Pixel by pixel, for bytes that run past the right edge.
+    fn draw_byte_at_edge(
+        &mut self,
+        plane: usize,
+        col: usize,
+        row: usize,
+        value: u8,
+        clip: bool,
+    ) -> bool {
+        let mut turned_any_off = false;
+        for shift in 0..8 {
+            let draw_column = col + shift;
+            if clip && draw_column >= self.width {
+                break;
+            }
+            if (value >> (7 - shift)) & 1 == 1 {
+                let (word, mask) = self.locate(row, draw_column % self.width);
+                turned_any_off |= self.planes[plane][word] & mask != 0;
+                self.planes[plane][word] ^= mask;
+                self.dirty = true;
+            }
+        }
+        turned_any_off
+    }
 }
 
 #[cfg(test)]
@@ -115,7 +791,7 @@ mod tests {
     #[test]
     fn test_create_display_all_off() {
         let display = Display::new(8, 8);
-        for pixel in display.display_buffer.iter() {
+        for pixel in display.to_grid().iter() {
             assert_eq!(*pixel, Pixel::Off);
         }
     }
@@ -123,7 +799,7 @@ mod tests {
     #[test]
     fn test_create_display_all_on() {
         let display = Display::from_vec(vec![Pixel::On; 64], 8);
-        for pixel in display.display_buffer.iter() {
+        for pixel in display.to_grid().iter() {
             assert_eq!(*pixel, Pixel::On);
         }
     }
@@ -132,7 +808,7 @@ mod tests {
     fn test_clear() {
         let mut display = Display::from_vec(vec![Pixel::On; 64], 8);
         display.clear();
-        for pixel in display.display_buffer.iter() {
+        for pixel in display.to_grid().iter() {
             assert_eq!(*pixel, Pixel::Off);
         }
     }
@@ -305,4 +981,546 @@ mod tests {
 
         assert_eq!(display, expected);
     }
+
+    #[test]
+    fn test_draw_sprite_report() {
+        let mut display = Display::new(8, 8);
+        assert_eq!(
+            display.draw_sprite_report(2, 1, &[0xC0, 0x40]),
+            DrawReport {
+                turned_on: vec![(1, 2), (1, 3), (2, 3)],
+                turned_off: vec![],
+                pixels_disabled: PixelsDisabled::NoPixels,
+            }
+        );
+
+        // redrawn one row lower, the overlap is erased
+        assert_eq!(
+            display.draw_sprite_report(2, 2, &[0xC0, 0x40]),
+            DrawReport {
+                turned_on: vec![(2, 2), (3, 3)],
+                turned_off: vec![(2, 3)],
+                pixels_disabled: PixelsDisabled::SomePixels,
+            }
+        );
+        assert_eq!(
+            display.draw_sprite_report(2, 1, &[0xC0, 0x40]),
+            DrawReport {
+                turned_on: vec![(2, 3)],
+                turned_off: vec![(1, 2), (1, 3)],
+                pixels_disabled: PixelsDisabled::SomePixels,
+            }
+        );
+
+        // clipped at the bottom, with a sprite for each of two planes
+        let mut display = Display::new(8, 4);
+        display.select_planes(ALL_PLANES);
+        assert_eq!(
+            display.draw_sprite_report(0, 7, &[0x80, 0x80, 0x01, 0x01]),
+            DrawReport {
+                turned_on: vec![(3, 0), (3, 7)],
+                turned_off: vec![],
+                pixels_disabled: PixelsDisabled::NoPixels,
+            }
+        );
+    }
+
+    #[test]
+    fn test_draw_sprite_report_across_words() {
+        let mut display = Display::new(128, 2);
+        display.draw_sprite(60, 0, &[0x01]);
+        let report = display.draw_sprite_report(60, 0, &[0xFF]);
+        assert_eq!(
+            report.turned_on,
+            [
+                (0, 60),
+                (0, 61),
+                (0, 62),
+                (0, 63),
+                (0, 64),
+                (0, 65),
+                (0, 66)
+            ]
+        );
+        assert_eq!(report.turned_off, [(0, 67)]);
+        assert_eq!(report.pixels_disabled, PixelsDisabled::SomePixels);
+    }
+
+    #[test]
+    fn test_equals_grid() {
+        let mut display = Display::new(8, 8);
+        display.draw_sprite(0, 0, &[0xFF]);
+
+        let mut grid = Grid::<Pixel>::init(8, 8, Pixel::Off);
+        assert!(!display.equals_grid(&grid));
+
+        for col in 0..8 {
+            grid[(0, col)] = Pixel::On;
+        }
+        assert!(display.equals_grid(&grid));
+        assert!(display.diff_grid(&grid).is_empty());
+    }
+
+    #[test]
+    fn test_diff_grid_single_cell() {
+        let display = Display::new(64, 32);
+        let mut grid = Grid::<Pixel>::init(32, 64, Pixel::Off);
+        grid[(17, 42)] = Pixel::On;
+
+        assert!(!display.equals_grid(&grid));
+        assert_eq!(
+            display.diff_grid(&grid),
+            "row 17, col 42: display is Off, other is On\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_grid_size_mismatch() {
+        let display = Display::new(64, 32);
+        let grid = Grid::<Pixel>::init(64, 128, Pixel::Off);
+
+        assert!(!display.equals_grid(&grid));
+        assert_eq!(
+            display.diff_grid(&grid),
+            "Size mismatch: display is 64x32, other is 128x64"
+        );
+    }
+
+    #[test]
+    fn test_draw_starting_at_right_edge_wraps() {
+        let mut display = Display::new(16, 4);
+        display.draw_sprite(16, 1, &[0xC3]);
+
+        let mut expected = Display::new(16, 4);
+        expected.draw_sprite(0, 1, &[0xC3]);
+        assert_eq!(display, expected);
+        assert_eq!(display.pixel(1, 0), Pixel::On);
+        assert_eq!(display.pixel(1, 7), Pixel::On);
+    }
+
+    #[test]
+    fn test_draw_starting_at_bottom_edge_wraps() {
+        let mut display = Display::new(16, 4);
+        display.draw_sprite(2, 4, &[0x80, 0x80]);
+
+        assert_eq!(display.pixel(0, 2), Pixel::On);
+        assert_eq!(display.pixel(1, 2), Pixel::On);
+        assert_eq!(display.pixel(3, 2), Pixel::Off);
+    }
+
+    #[test]
+    fn test_draw_sprite_wrapping_at_edges() {
+        let mut clipped = Display::new(16, 4);
+        clipped.draw_sprite(12, 3, &[0xFF, 0x81]);
+
+        let mut wrapped = Display::new(16, 4);
+        wrapped.draw_sprite_wrapping(12, 3, &[0xFF, 0x81]);
+
+        assert_eq!(clipped.pixel(3, 15), Pixel::On);
+        assert_eq!(clipped.pixel(3, 0), Pixel::Off);
+        assert_eq!(clipped.pixel(0, 12), Pixel::Off);
+
+        assert_eq!(wrapped.pixel(3, 15), Pixel::On);
+        assert_eq!(wrapped.pixel(3, 0), Pixel::On);
+        assert_eq!(wrapped.pixel(3, 3), Pixel::On);
+        assert_eq!(wrapped.pixel(0, 12), Pixel::On);
+        assert_eq!(wrapped.pixel(0, 3), Pixel::On);
+        assert_eq!(wrapped.pixel(0, 13), Pixel::Off);
+    }
+
+    #[test]
+    fn test_draw_starting_at_corner_wraps() {
+        let mut display = Display::new(16, 4);
+        display.draw_sprite(16, 4, &[0x80]);
+
+        assert_eq!(display.pixel(0, 0), Pixel::On);
+        assert_eq!(
+            display
+                .to_grid()
+                .iter()
+                .filter(|pixel| **pixel == Pixel::On)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_to_ascii() {
+        let mut display = Display::new(8, 2);
+        display.draw_sprite(2, 1, &[0xA0]);
+
+        assert_eq!(display.to_ascii(), "........\n..#.#...\n");
+    }
+
+    #[test]
+    fn test_pbm_round_trip() {
+        let mut display = Display::new(16, 4);
+        display.draw_sprite(3, 1, &[0xA5, 0x81]);
+
+        let pbm = display.to_pbm();
+        assert!(pbm.starts_with("P1\n16 4\n0000000000000000\n0001010010100000\n"));
+        assert!(display.equals_grid(&parse_pbm(&pbm).unwrap()));
+    }
+
+    #[test]
+    fn test_parse_pbm_spaced_with_comments() {
+        let grid = parse_pbm("P1\n# a comment\n3 2\n1 0 1 # trailing\n0 1 0\n").unwrap();
+
+        assert_eq!(grid.size(), (2, 3));
+        assert_eq!(grid[(0, 0)], Pixel::On);
+        assert_eq!(grid[(0, 1)], Pixel::Off);
+        assert_eq!(grid[(1, 1)], Pixel::On);
+    }
+
+    #[test]
+    fn test_parse_pbm_errors() {
+        assert_eq!(parse_pbm("P4\n1 1\n1"), Err(PbmError::NotPlainPbm));
+        assert_eq!(parse_pbm("P1\n0 1\n"), Err(PbmError::BadDimensions));
+        assert_eq!(
+            parse_pbm("P1\n2 1\n12"),
+            Err(PbmError::BadPixel { value: '2' })
+        );
+        assert_eq!(
+            parse_pbm("P1\n2 2\n101"),
+            Err(PbmError::WrongPixelCount {
+                expected: 4,
+                found: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_packed_bytes_round_trip() {
+        let mut display = Display::new(64, 32);
+        display.draw_sprite(4, 1, &[0xA5]);
+
+        let bytes = display.to_packed_bytes();
+        assert_eq!(bytes.len(), 256);
+        assert_eq!(bytes[8], 0x0A);
+        assert_eq!(bytes[9], 0x50);
+        assert_eq!(bytes.iter().filter(|byte| **byte != 0).count(), 2);
+
+        let mut copy = Display::new(64, 32);
+        copy.load_packed_bytes(&bytes);
+        assert_eq!(copy, display);
+
+        // rows that share bytes, and rows that end partway through a word
+        for (width, height) in [(12, 3), (72, 3)] {
+            let mut display = Display::new(width, height);
+            display.draw_sprite(width - 8, 1, &[0xA5, 0x81]);
+            let bytes = display.to_packed_bytes();
+            assert_eq!(bytes.len(), (width * height).div_ceil(8));
+
+            let mut copy = Display::new(width, height);
+            copy.load_packed_bytes(&bytes);
+            assert_eq!(copy, display);
+
+            // short input leaves the rest unlit
+            copy.load_packed_bytes(&bytes[..width / 8]);
+            assert_eq!(lit_pixels(&copy), vec![]);
+        }
+    }
+
+    #[test]
+    fn test_dimensions() {
+        let mut display = Display::new(64, 32);
+        assert_eq!((display.width(), display.height()), (64, 32));
+
+        display.set_hires(true);
+        assert_eq!((display.width(), display.height()), (128, 64));
+
+        let display = Display::new(40, 20);
+        assert_eq!((display.width(), display.height()), (40, 20));
+    }
+
+    #[test]
+    fn test_set_hires_resizes_and_clears() {
+        let mut display = Display::new(64, 32);
+        display.draw_sprite(0, 0, &[0xFF]);
+        display.set_hires(true);
+
+        assert!(display.is_hires());
+        assert_eq!(display.to_grid().size(), (64, 128));
+        assert!(display.to_grid().iter().all(|pixel| *pixel == Pixel::Off));
+        assert!(display.get_display_buffer().is_some());
+
+        display.set_hires(false);
+        assert!(!display.is_hires());
+        assert_eq!(display.to_grid().size(), (32, 64));
+    }
+
+    #[test]
+    fn test_draw_wide_sprite() {
+        let mut display = Display::new(128, 64);
+        let pixels_disabled =
+            display.draw_wide_sprite(120, 62, &[0xFF, 0x01, 0x80, 0xFF, 0xFF, 0xFF]);
+
+        assert_eq!(pixels_disabled, PixelsDisabled::NoPixels);
+        assert_eq!(display.pixel(62, 120), Pixel::On);
+        assert_eq!(display.pixel(62, 127), Pixel::On);
+        assert_eq!(display.pixel(63, 120), Pixel::On);
+        // the right half and the third row are clipped
+        assert_eq!(
+            display
+                .to_grid()
+                .iter()
+                .filter(|pixel| **pixel == Pixel::On)
+                .count(),
+            9
+        );
+
+        let mut display = Display::new(128, 64);
+        display.draw_wide_sprite_wrapping(120, 0, &[0x00, 0x81]);
+        assert_eq!(display.pixel(0, 0), Pixel::On);
+        assert_eq!(display.pixel(0, 7), Pixel::On);
+    }
+
+    // One lit pixel per row, moving one column right on each row.
+    fn diagonal(width: usize, height: usize) -> Display {
+        let mut display = Display::new(width, height);
+        for row in 0..height {
+            display.set_pixel(row, row, Pixel::On);
+        }
+        display
+    }
+
+    #[test]
+    fn test_sprite_straddling_words() {
+        let mut display = Display::new(128, 64);
+        assert_eq!(
+            display.draw_sprite(60, 1, &[0xFF]),
+            PixelsDisabled::NoPixels
+        );
+        assert_eq!(
+            lit_pixels(&display),
+            (60..68).map(|col| (1, col)).collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            display.draw_sprite(66, 1, &[0x80]),
+            PixelsDisabled::SomePixels
+        );
+        assert_eq!(display.pixel(1, 66), Pixel::Off);
+        assert_eq!(display.pixel(1, 67), Pixel::On);
+    }
+
+    fn lit_pixels(display: &Display) -> Vec<(usize, usize)> {
+        display
+            .to_grid()
+            .indexed_iter()
+            .filter(|(_, pixel)| **pixel != Pixel::Off)
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    #[test]
+    fn test_scroll_down() {
+        let mut display = diagonal(8, 4);
+        display.get_display_buffer();
+        display.scroll_down(2);
+
+        assert_eq!(lit_pixels(&display), vec![(2, 0), (3, 1)]);
+        assert!(display.get_display_buffer().is_some());
+    }
+
+    #[test]
+    fn test_redundant_changes_stay_clean() {
+        let mut display = Display::new(8, 4);
+        assert!(display.get_display_buffer().is_some());
+
+        display.clear();
+        assert!(display.get_display_buffer().is_none());
+
+        display.draw_sprite(0, 0, &[0x00, 0x00]);
+        display.draw_sprite(6, 0, &[0x00, 0x03]);
+        display.scroll_down(1);
+        display.scroll_left();
+        display.scroll_right();
+        assert!(display.get_display_buffer().is_none());
+
+        display.draw_sprite(0, 0, &[0x80]);
+        assert!(display.get_display_buffer().is_some());
+        display.clear();
+        assert!(display.get_display_buffer().is_some());
+    }
+
+    #[test]
+    fn test_scroll_down_past_height() {
+        let mut display = diagonal(8, 4);
+        display.scroll_down(15);
+        assert_eq!(lit_pixels(&display), vec![]);
+    }
+
+    #[test]
+    fn test_scroll_right() {
+        let mut display = diagonal(8, 4);
+        display.get_display_buffer();
+        display.scroll_right();
+
+        assert_eq!(lit_pixels(&display), vec![(0, 4), (1, 5), (2, 6), (3, 7)]);
+        assert!(display.get_display_buffer().is_some());
+
+        display.scroll_right();
+        assert_eq!(lit_pixels(&display), vec![]);
+    }
+
+    #[test]
+    fn test_scroll_left() {
+        let mut display = diagonal(8, 8);
+        display.get_display_buffer();
+        display.scroll_left();
+
+        assert_eq!(lit_pixels(&display), vec![(4, 0), (5, 1), (6, 2), (7, 3)]);
+        assert!(display.get_display_buffer().is_some());
+    }
+
+    #[test]
+    fn test_scroll_across_words() {
+        let mut display = Display::new(128, 2);
+        display.draw_sprite(60, 0, &[0x88]);
+        display.draw_sprite(120, 1, &[0x01]);
+
+        display.scroll_right();
+        assert_eq!(lit_pixels(&display), vec![(0, 64), (0, 68)]);
+
+        display.scroll_left();
+        display.scroll_left();
+        assert_eq!(lit_pixels(&display), vec![(0, 56), (0, 60)]);
+
+        display.scroll_down(1);
+        assert_eq!(lit_pixels(&display), vec![(1, 56), (1, 60)]);
+    }
+
+    #[test]
+    fn test_draw_wide_sprite_full_block_collides_across_wrap() {
+        let block = [0xFF; 32];
+        let mut display = Display::new(128, 64);
+
+        assert_eq!(
+            display.draw_wide_sprite(0, 0, &block),
+            PixelsDisabled::NoPixels
+        );
+        assert_eq!(lit_pixels(&display).len(), 16 * 16);
+
+        // wraps into the top left corner, overlapping an 8x8 corner of the block
+        assert_eq!(
+            display.draw_wide_sprite_wrapping(120, 56, &block),
+            PixelsDisabled::SomePixels
+        );
+        assert_eq!(lit_pixels(&display).len(), 2 * 16 * 16 - 2 * 8 * 8);
+        assert_eq!(display.pixel(0, 0), Pixel::Off);
+        assert_eq!(display.pixel(63, 127), Pixel::On);
+
+        // clipped at the same position, nothing reaches the corner
+        let mut display = Display::new(128, 64);
+        display.draw_wide_sprite(0, 0, &block);
+        assert_eq!(
+            display.draw_wide_sprite(120, 56, &block),
+            PixelsDisabled::NoPixels
+        );
+        assert_eq!(lit_pixels(&display).len(), 16 * 16 + 8 * 8);
+    }
+
+    #[test]
+    fn test_draw_to_selected_planes() {
+        let mut display = Display::new(8, 2);
+        assert_eq!(display.selected_planes(), FIRST_PLANE);
+        display.draw_sprite(0, 0, &[0xC0]);
+
+        display.select_planes(SECOND_PLANE);
+        assert_eq!(display.draw_sprite(0, 0, &[0x60]), PixelsDisabled::NoPixels);
+        assert_eq!(display.to_ascii(), concat!("#%+.....\n", "........\n"));
+
+        // a sprite for each plane, first plane first
+        display.select_planes(ALL_PLANES);
+        assert_eq!(display.selected_plane_count(), 2);
+        assert_eq!(
+            display.draw_sprite(0, 1, &[0x80, 0x01]),
+            PixelsDisabled::NoPixels
+        );
+        assert_eq!(
+            display.draw_sprite(0, 0, &[0x10, 0x20]),
+            PixelsDisabled::SomePixels
+        );
+        assert_eq!(display.to_ascii(), concat!("#%.#....\n", "#......+\n"));
+    }
+
+    #[test]
+    fn test_collision_in_any_selected_plane() {
+        let mut display = Display::new(8, 2);
+        display.select_planes(SECOND_PLANE);
+        display.draw_sprite(0, 0, &[0x80]);
+
+        display.select_planes(FIRST_PLANE);
+        assert_eq!(display.draw_sprite(0, 0, &[0x80]), PixelsDisabled::NoPixels);
+        display.select_planes(ALL_PLANES);
+        assert_eq!(
+            display.draw_sprite(0, 1, &[0x80, 0x80]),
+            PixelsDisabled::NoPixels
+        );
+        assert_eq!(
+            display.draw_sprite(0, 1, &[0x00, 0x80]),
+            PixelsDisabled::SomePixels
+        );
+        assert_eq!(display.pixel(1, 0), Pixel::On);
+    }
+
+    #[test]
+    fn test_no_planes_selected() {
+        let mut display = Display::new(8, 2);
+        display.draw_sprite(0, 0, &[0xFF]);
+        display.get_display_buffer();
+
+        display.select_planes(0);
+        assert_eq!(display.draw_sprite(0, 0, &[0xFF]), PixelsDisabled::NoPixels);
+        display.clear();
+        display.scroll_left();
+        assert!(display.get_display_buffer().is_none());
+        assert_eq!(lit_pixels(&display).len(), 8);
+    }
+
+    #[test]
+    fn test_clear_and_scroll_selected_planes() {
+        let mut display = Display::new(8, 2);
+        display.select_planes(ALL_PLANES);
+        display.draw_sprite(0, 0, &[0xF0, 0x0F]);
+
+        display.select_planes(SECOND_PLANE);
+        display.scroll_right();
+        assert_eq!(display.to_ascii(), "####....\n........\n");
+        display.scroll_left();
+        display.scroll_left();
+        assert_eq!(display.to_ascii(), "####....\n........\n");
+
+        display.select_planes(ALL_PLANES);
+        display.draw_sprite(0, 0, &[0x00, 0xF0]);
+        display.select_planes(FIRST_PLANE);
+        display.scroll_down(1);
+        assert_eq!(display.to_ascii(), "++++....\n####....\n");
+        display.clear();
+        assert_eq!(display.to_ascii(), "++++....\n........\n");
+    }
+
+    #[test]
+    fn test_reset_clears_every_plane() {
+        let mut display = Display::new(8, 2);
+        display.select_planes(ALL_PLANES);
+        display.draw_sprite(0, 0, &[0x80, 0x40]);
+        display.set_hires(true);
+        assert_eq!(display.selected_planes(), ALL_PLANES);
+        display.draw_sprite(0, 0, &[0x80, 0x40]);
+
+        display.reset();
+        assert!(!display.is_hires());
+        assert_eq!(display.selected_planes(), FIRST_PLANE);
+        assert_eq!(lit_pixels(&display), vec![]);
+    }
+
+    #[test]
+    fn test_pixel_planes() {
+        for planes in 0..4 {
+            assert_eq!(Pixel::from_planes(planes).planes(), planes);
+        }
+        assert_eq!(Pixel::from_planes(FIRST_PLANE), Pixel::On);
+    }
 }
@@ -0,0 +1,227 @@
+use alloc::vec::Vec;
+use grid::Grid;
+
+use crate::display::Pixel;
+use crate::keypad::KeyStatus;
+use crate::processor::{Config, Processor, ProcessorError, Quirks, StepOutcome};
+
+// Drives a processor one call at a time, for hosts that can't spawn threads or
+// block, like a browser calling in from requestAnimationFrame. The host decides
+// how many instructions make a frame and ticks the timers at 60Hz itself.
+pub struct Emulator {
+    processor: Processor,
+    frame: Grid<Pixel>,
+}
+
+impl Emulator {
+    pub fn new(program_bytes: Vec<u8>) -> Result<Self, ProcessorError> {
+        Self::new_with_config(program_bytes, Config::default(), Quirks::default())
+    }
+
+    pub fn new_with_config(
+        program_bytes: Vec<u8>,
+        config: Config,
+        quirks: Quirks,
+    ) -> Result<Self, ProcessorError> {
+        Ok(Self::from_processor(Processor::new_with_config(
+            program_bytes,
+            config,
+            quirks,
+        )?))
+    }
+
+    // The display is left marked as changed, so the first refresh still
+    // reports it.
+    pub fn from_processor(processor: Processor) -> Self {
+        let frame = processor.display().to_grid();
+        Emulator { processor, frame }
+    }
+
+    pub fn processor(&self) -> &Processor {
+        &self.processor
+    }
+
+    pub fn processor_mut(&mut self) -> &mut Processor {
+        &mut self.processor
+    }
+
+    // Replaces the running program and restarts from its power-on state. The
+    // cleared display is left marked as changed, for the next refresh to pick
+    // up.
+    pub fn load(&mut self, program_bytes: &[u8]) -> Result<(), ProcessorError> {
+        self.processor.load_program(program_bytes.to_vec())
+    }
+
+    pub fn step(&mut self) -> Result<StepOutcome, ProcessorError> {
+        self.processor.step()
+    }
+
+    // One 60Hz tick, which also ends the frame for rewinding.
+    pub fn tick_timers(&mut self) {
+        self.processor.tick();
+        self.processor.record_frame();
+    }
+
+    pub fn set_key(&mut self, key: usize, status: KeyStatus) {
+        self.processor.add_key_event(key, status);
+    }
+
+    // Steps up to `cycles` times, stopping early if the program exits or idles,
+    // then ticks the timers and refreshes the frame buffer.
+    pub fn run_frame(&mut self, cycles: usize) -> Result<StepOutcome, ProcessorError> {
+        let mut outcome = StepOutcome::Running;
+        for _ in 0..cycles {
+            outcome = self.step()?;
+            if outcome != StepOutcome::Running {
+                break;
+            }
+        }
+        self.tick_timers();
+        self.refresh_frame();
+        Ok(outcome)
+    }
+
+    // Copies the display into the frame buffer if it changed since the last
+    // refresh, returning whether it did.
+    pub fn refresh_frame(&mut self) -> bool {
+        match self.processor.get_display_buffer() {
+            Some(frame) => {
+                self.frame = frame;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // The display if it changed since the last refresh, for hosts that keep
+    // frames themselves. Saves copying the frame a second time, but leaves
+    // `frame` and `frame_buffer` as they were.
+    pub fn take_frame(&mut self) -> Option<Grid<Pixel>> {
+        self.processor.get_display_buffer()
+    }
+
+    pub fn frame(&self) -> &Grid<Pixel> {
+        &self.frame
+    }
+
+    // The pixels row by row from the top left, `frame_width` to a row.
+    pub fn frame_buffer(&self) -> &[Pixel] {
+        self.frame.flatten()
+    }
+
+    pub fn frame_width(&self) -> usize {
+        self.frame.cols()
+    }
+
+    pub fn frame_height(&self) -> usize {
+        self.frame.rows()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+    use alloc::vec;
+
+    const FRAME_CYCLES: usize = 10;
+
+    // Waits for key 5, then draws the digit 0 at the top left and idles.
+    const PROGRAM: [u8; 12] = [
+        0x61, 0x05, // LD V1, 0x05
+        0xE1, 0xA1, // SKNP V1
+        0x12, 0x08, // JP 0x208
+        0x12, 0x02, // JP 0x202
+        0xD0, 0x05, // DRW V0, V0, 5
+        0x12, 0x0A, // JP 0x20A
+    ];
+
+    fn lit_rows(emulator: &Emulator) -> Vec<String> {
+        emulator
+            .frame_buffer()
+            .chunks(emulator.frame_width())
+            .take(5)
+            .map(|row| {
+                row.iter()
+                    .take(4)
+                    .map(|pixel| if *pixel == Pixel::Off { '.' } else { '#' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_run_frame() {
+        let mut emulator = Emulator::new(PROGRAM.to_vec()).unwrap();
+        assert_eq!(
+            emulator.frame_buffer().len(),
+            emulator.frame_width() * emulator.frame_height()
+        );
+        assert_eq!(emulator.frame_width(), Config::default().display_width);
+
+        assert_eq!(emulator.run_frame(FRAME_CYCLES), Ok(StepOutcome::Running));
+        assert!(emulator
+            .frame_buffer()
+            .iter()
+            .all(|pixel| *pixel == Pixel::Off));
+
+        emulator.set_key(5, KeyStatus::Pressed);
+        assert_eq!(emulator.run_frame(FRAME_CYCLES), Ok(StepOutcome::Idle));
+        assert_eq!(
+            lit_rows(&emulator),
+            ["####", "#..#", "#..#", "#..#", "####"]
+        );
+    }
+
+    #[test]
+    fn test_tick_timers() {
+        let mut emulator = Emulator::new(vec![
+            0x60, 0x02, // LD V0, 0x02
+            0xF0, 0x15, // LD DT, V0
+            0x12, 0x04, // JP 0x204
+        ])
+        .unwrap();
+        emulator.run_frame(FRAME_CYCLES).unwrap();
+        assert_eq!(emulator.processor().delay(), 1);
+        emulator.tick_timers();
+        assert_eq!(emulator.processor().delay(), 0);
+        assert!(emulator.processor_mut().rewind_frame(1));
+    }
+
+    #[test]
+    fn test_take_frame() {
+        let mut emulator = Emulator::new(PROGRAM.to_vec()).unwrap();
+        assert!(emulator.take_frame().is_some());
+        assert_eq!(emulator.take_frame(), None);
+
+        emulator.set_key(5, KeyStatus::Pressed);
+        for _ in 0..FRAME_CYCLES {
+            emulator.step().unwrap();
+        }
+        let frame = emulator.take_frame().unwrap();
+        assert_eq!(
+            frame.iter().filter(|pixel| **pixel == Pixel::On).count(),
+            14
+        );
+        // the cached frame is left alone
+        assert!(!emulator.frame_buffer().contains(&Pixel::On));
+    }
+
+    #[test]
+    fn test_load() {
+        let mut emulator = Emulator::new(PROGRAM.to_vec()).unwrap();
+        emulator.set_key(5, KeyStatus::Pressed);
+        emulator.run_frame(FRAME_CYCLES).unwrap();
+        assert!(emulator.frame_buffer().contains(&Pixel::On));
+
+        emulator.load(&[0x12, 0x00]).unwrap();
+        assert!(emulator.refresh_frame());
+        assert!(emulator
+            .frame_buffer()
+            .iter()
+            .all(|pixel| *pixel == Pixel::Off));
+        assert_eq!(emulator.run_frame(FRAME_CYCLES), Ok(StepOutcome::Idle));
+
+        assert!(emulator.load(&[0; 0x1000]).is_err());
+    }
+}
@@ -1,13 +1,49 @@
 use crate::types::{Address, GeneralRegister, Nibble};
-use std::fmt::Display;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Display;
+use strum_macros::IntoStaticStr;
 
-#[derive(Debug, PartialEq, Eq)]
+const HIRES_SWITCH: u16 = 0x00FF;
+const HIRES_SCAN_WORDS: usize = 32;
+
+const LONG_LOAD: u16 = 0xF000;
+
+// Each extension is a superset of the one before, so they're ordered by what
+// they support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InstructionSet {
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl Display for InstructionSet {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            InstructionSet::Chip8 => "CHIP-8",
+            InstructionSet::SuperChip => "SUPER-CHIP",
+            InstructionSet::XoChip => "XO-CHIP",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, IntoStaticStr)]
 pub enum Instruction {
     Sys {
         addr: Address,
     },
     Clear,
     Return,
+    LowResolution,
+    HighResolution,
+    ScrollDown {
+        rows: Nibble,
+    },
+    ScrollRight,
+    ScrollLeft,
+    Exit,
     Jump {
         addr: Address,
     },
@@ -77,6 +113,11 @@ pub enum Instruction {
     LoadI {
         addr: Address,
     },
+    // XO-CHIP's F000 NNNN. It's four bytes long, so the processor decodes it
+    // rather than decode.
+    LoadLongI {
+        addr: Address,
+    },
     JumpPlusV0 {
         addr: Address,
     },
@@ -113,6 +154,9 @@ pub enum Instruction {
     LoadSpriteLocation {
         digit: GeneralRegister,
     },
+    LoadHighSpriteLocation {
+        digit: GeneralRegister,
+    },
     LoadBcd {
         source: GeneralRegister,
     },
@@ -122,14 +166,215 @@ pub enum Instruction {
     LoadRegisterRangeFromI {
         last: GeneralRegister,
     },
+    StoreFlags {
+        last: GeneralRegister,
+    },
+    LoadFlags {
+        last: GeneralRegister,
+    },
+    SelectPlanes {
+        mask: Nibble,
+    },
+    LoadAudioPattern,
+    SetPitch {
+        source: GeneralRegister,
+    },
+    // XO-CHIP's 5XY2 and 5XY3, which go from VX to VY in whichever direction
+    // that is, and leave I alone.
+    StoreRegisterRange {
+        first: GeneralRegister,
+        last: GeneralRegister,
+    },
+    LoadRegisterRange {
+        first: GeneralRegister,
+        last: GeneralRegister,
+    },
+}
+
+impl Instruction {
+    pub fn reads_register(&self, reg: GeneralRegister) -> bool {
+        match *self {
+            Instruction::SkipIfEqByte { reg: operand, .. }
+            | Instruction::SkipIfNeqByte { reg: operand, .. }
+            | Instruction::AddValue { dest: operand, .. }
+            | Instruction::LoadRegister {
+                source: operand, ..
+            }
+            | Instruction::SkipIfKeyDown { key_val: operand }
+            | Instruction::SkipIfKeyUp { key_val: operand }
+            | Instruction::SetDelayTimer { source: operand }
+            | Instruction::SetSoundTimer { source: operand }
+            | Instruction::SetPitch { source: operand }
+            | Instruction::AddI { source: operand }
+            | Instruction::LoadSpriteLocation { digit: operand }
+            | Instruction::LoadHighSpriteLocation { digit: operand }
+            | Instruction::LoadBcd { source: operand } => operand == reg,
+
+            Instruction::SkipIfEqReg { lhs, rhs } | Instruction::SkipIfNeqReg { lhs, rhs } => {
+                lhs == reg || rhs == reg
+            }
+
+            Instruction::Or { dest, source }
+            | Instruction::And { dest, source }
+            | Instruction::Xor { dest, source }
+            | Instruction::AddRegister { dest, source }
+            | Instruction::Subtract { dest, source }
+            | Instruction::SubtractNegate { dest, source } => dest == reg || source == reg,
+
+            // Which of these is read depends on the shift quirk, so count both.
+            Instruction::ShiftRight { dest, source } | Instruction::ShiftLeft { dest, source } => {
+                dest == reg || source == reg
+            }
+
+            Instruction::Draw { x, y, .. } => x == reg || y == reg,
+            Instruction::JumpPlusV0 { .. } => reg == GeneralRegister::V0,
+            Instruction::StoreRegisterRangeAtI { last } | Instruction::StoreFlags { last } => {
+                reg as u8 <= last as u8
+            }
+            Instruction::StoreRegisterRange { first, last } => {
+                let (first, last) = (first as u8, last as u8);
+                (first.min(last)..=first.max(last)).contains(&(reg as u8))
+            }
+
+            Instruction::Sys { .. }
+            | Instruction::Clear
+            | Instruction::Return
+            | Instruction::LowResolution
+            | Instruction::HighResolution
+            | Instruction::ScrollDown { .. }
+            | Instruction::ScrollRight
+            | Instruction::ScrollLeft
+            | Instruction::Exit
+            | Instruction::Jump { .. }
+            | Instruction::Call { .. }
+            | Instruction::LoadValue { .. }
+            | Instruction::LoadI { .. }
+            | Instruction::LoadLongI { .. }
+            | Instruction::Random { .. }
+            | Instruction::LoadFromDelayTimer { .. }
+            | Instruction::LoadFromKey { .. }
+            | Instruction::LoadRegisterRangeFromI { .. }
+            | Instruction::LoadFlags { .. }
+            | Instruction::SelectPlanes { .. }
+            | Instruction::LoadAudioPattern
+            | Instruction::LoadRegisterRange { .. } => false,
+        }
+    }
+
+    // The first instruction set to have this instruction. SCHIP's DXY0 draws a
+    // 16x16 sprite where classic CHIP-8 draws nothing.
+    pub fn instruction_set(&self) -> InstructionSet {
+        match self {
+            Instruction::LowResolution
+            | Instruction::HighResolution
+            | Instruction::ScrollDown { .. }
+            | Instruction::ScrollRight
+            | Instruction::ScrollLeft
+            | Instruction::Exit
+            | Instruction::LoadHighSpriteLocation { .. }
+            | Instruction::StoreFlags { .. }
+            | Instruction::LoadFlags { .. }
+            | Instruction::Draw {
+                num_bytes: Nibble::Zero,
+                ..
+            } => InstructionSet::SuperChip,
+            Instruction::LoadLongI { .. }
+            | Instruction::SelectPlanes { .. }
+            | Instruction::LoadAudioPattern
+            | Instruction::SetPitch { .. }
+            | Instruction::StoreRegisterRange { .. }
+            | Instruction::LoadRegisterRange { .. } => InstructionSet::XoChip,
+            _ => InstructionSet::Chip8,
+        }
+    }
+
+    pub fn sets_vf_flag(&self) -> bool {
+        matches!(
+            self,
+            Instruction::AddRegister { .. }
+                | Instruction::Subtract { .. }
+                | Instruction::ShiftRight { .. }
+                | Instruction::SubtractNegate { .. }
+                | Instruction::ShiftLeft { .. }
+                | Instruction::Draw { .. }
+        )
+    }
+}
+
+// The usual CHIP-8 assembly mnemonics, as in Cowgod's reference, with the SCHIP
+// additions and Octo's names for the XO-CHIP ones. Bytes and addresses are in
+// hex, sprite heights and scroll distances in decimal.
+impl Display for Instruction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Instruction::Sys { addr } => write!(f, "SYS {}", addr),
+            Instruction::Clear => write!(f, "CLS"),
+            Instruction::Return => write!(f, "RET"),
+            Instruction::LowResolution => write!(f, "LOW"),
+            Instruction::HighResolution => write!(f, "HIGH"),
+            Instruction::ScrollDown { rows } => write!(f, "SCD {}", *rows as u8),
+            Instruction::ScrollRight => write!(f, "SCR"),
+            Instruction::ScrollLeft => write!(f, "SCL"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::Jump { addr } => write!(f, "JP {}", addr),
+            Instruction::Call { addr } => write!(f, "CALL {}", addr),
+            Instruction::SkipIfEqByte { reg, value } => write!(f, "SE {}, {:#04x}", reg, value),
+            Instruction::SkipIfNeqByte { reg, value } => write!(f, "SNE {}, {:#04x}", reg, value),
+            Instruction::SkipIfEqReg { lhs, rhs } => write!(f, "SE {}, {}", lhs, rhs),
+            Instruction::LoadValue { dest, value } => write!(f, "LD {}, {:#04x}", dest, value),
+            Instruction::AddValue { dest, value } => write!(f, "ADD {}, {:#04x}", dest, value),
+            Instruction::LoadRegister { dest, source } => write!(f, "LD {}, {}", dest, source),
+            Instruction::Or { dest, source } => write!(f, "OR {}, {}", dest, source),
+            Instruction::And { dest, source } => write!(f, "AND {}, {}", dest, source),
+            Instruction::Xor { dest, source } => write!(f, "XOR {}, {}", dest, source),
+            Instruction::AddRegister { dest, source } => write!(f, "ADD {}, {}", dest, source),
+            Instruction::Subtract { dest, source } => write!(f, "SUB {}, {}", dest, source),
+            Instruction::ShiftRight { dest, source } => write!(f, "SHR {}, {}", dest, source),
+            Instruction::SubtractNegate { dest, source } => {
+                write!(f, "SUBN {}, {}", dest, source)
+            }
+            Instruction::ShiftLeft { dest, source } => write!(f, "SHL {}, {}", dest, source),
+            Instruction::SkipIfNeqReg { lhs, rhs } => write!(f, "SNE {}, {}", lhs, rhs),
+            Instruction::LoadI { addr } => write!(f, "LD I, {}", addr),
+            Instruction::LoadLongI { addr } => write!(f, "LD I, LONG {}", addr),
+            Instruction::JumpPlusV0 { addr } => write!(f, "JP V0, {}", addr),
+            Instruction::Random { dest, mask } => write!(f, "RND {}, {:#04x}", dest, mask),
+            Instruction::Draw { x, y, num_bytes } => {
+                write!(f, "DRW {}, {}, {}", x, y, *num_bytes as u8)
+            }
+            Instruction::SkipIfKeyDown { key_val } => write!(f, "SKP {}", key_val),
+            Instruction::SkipIfKeyUp { key_val } => write!(f, "SKNP {}", key_val),
+            Instruction::LoadFromDelayTimer { dest } => write!(f, "LD {}, DT", dest),
+            Instruction::LoadFromKey { dest } => write!(f, "LD {}, K", dest),
+            Instruction::SetDelayTimer { source } => write!(f, "LD DT, {}", source),
+            Instruction::SetSoundTimer { source } => write!(f, "LD ST, {}", source),
+            Instruction::AddI { source } => write!(f, "ADD I, {}", source),
+            Instruction::LoadSpriteLocation { digit } => write!(f, "LD F, {}", digit),
+            Instruction::LoadHighSpriteLocation { digit } => write!(f, "LD HF, {}", digit),
+            Instruction::LoadBcd { source } => write!(f, "LD B, {}", source),
+            Instruction::StoreRegisterRangeAtI { last } => write!(f, "LD [I], {}", last),
+            Instruction::LoadRegisterRangeFromI { last } => write!(f, "LD {}, [I]", last),
+            Instruction::StoreFlags { last } => write!(f, "LD R, {}", last),
+            Instruction::LoadFlags { last } => write!(f, "LD {}, R", last),
+            Instruction::SelectPlanes { mask } => write!(f, "PLANE {}", *mask as u8),
+            Instruction::LoadAudioPattern => write!(f, "AUDIO"),
+            Instruction::SetPitch { source } => write!(f, "PITCH {}", source),
+            Instruction::StoreRegisterRange { first, last } => {
+                write!(f, "SAVE {} - {}", first, last)
+            }
+            Instruction::LoadRegisterRange { first, last } => {
+                write!(f, "LOAD {} - {}", first, last)
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct InstructionBytePair(pub u16);
 
 impl Display for InstructionBytePair {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:#06x}", u16::to_be(self.0))
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:#06x}", self.0)
     }
 }
 
@@ -143,148 +388,217 @@ impl InstructionBytePair {
     }
 }
 
-fn handle_zero(bytes: InstructionBytePair) -> Option<Instruction> {
+fn handle_zero(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
     match bytes.0 {
-        0x00E0 => Some(Instruction::Clear),
-        0x00EE => Some(Instruction::Return),
-        value => Some(Instruction::Sys {
+        0x00C0..=0x00CF => Ok(Instruction::ScrollDown {
+            rows: Nibble::from_lower(bytes.get_lower_byte()),
+        }),
+        0x00E0 => Ok(Instruction::Clear),
+        0x00EE => Ok(Instruction::Return),
+        0x00FB => Ok(Instruction::ScrollRight),
+        0x00FC => Ok(Instruction::ScrollLeft),
+        0x00FD => Ok(Instruction::Exit),
+        0x00FE => Ok(Instruction::LowResolution),
+        HIRES_SWITCH => Ok(Instruction::HighResolution),
+        value => Ok(Instruction::Sys {
             addr: Address::from(value),
         }),
     }
 }
 
-fn handle_one(bytes: InstructionBytePair) -> Option<Instruction> {
-    Some(Instruction::Jump {
+fn handle_one(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
+    Ok(Instruction::Jump {
         addr: Address::from(bytes.0 & 0x0FFF),
     })
 }
 
-fn handle_two(bytes: InstructionBytePair) -> Option<Instruction> {
-    Some(Instruction::Call {
+fn handle_two(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
+    Ok(Instruction::Call {
         addr: Address::from(bytes.0 & 0x0FFF),
     })
 }
 
-fn handle_three(bytes: InstructionBytePair) -> Option<Instruction> {
-    Some(Instruction::SkipIfEqByte {
+fn handle_three(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
+    Ok(Instruction::SkipIfEqByte {
         reg: Nibble::from_lower(bytes.get_upper_byte()).into(),
         value: bytes.get_lower_byte(),
     })
 }
 
-fn handle_four(bytes: InstructionBytePair) -> Option<Instruction> {
-    Some(Instruction::SkipIfNeqByte {
+fn handle_four(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
+    Ok(Instruction::SkipIfNeqByte {
         reg: Nibble::from_lower(bytes.get_upper_byte()).into(),
         value: bytes.get_lower_byte(),
     })
 }
 
-fn handle_five(bytes: InstructionBytePair) -> Option<Instruction> {
-    if Nibble::from_lower(bytes.get_lower_byte()) != Nibble::Zero {
-        return None;
+fn handle_five(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
+    let x: GeneralRegister = Nibble::from_lower(bytes.get_upper_byte()).into();
+    let y: GeneralRegister = Nibble::from_upper(bytes.get_lower_byte()).into();
+    match Nibble::from_lower(bytes.get_lower_byte()) {
+        Nibble::Zero => Ok(Instruction::SkipIfEqReg { lhs: x, rhs: y }),
+        Nibble::Two => Ok(Instruction::StoreRegisterRange { first: x, last: y }),
+        Nibble::Three => Ok(Instruction::LoadRegisterRange { first: x, last: y }),
+        _ => Err(DecodeError::RegisterPair {
+            byte: bytes.get_lower_byte(),
+        }),
     }
-
-    Some(Instruction::SkipIfEqReg {
-        lhs: Nibble::from_lower(bytes.get_upper_byte()).into(),
-        rhs: Nibble::from_upper(bytes.get_lower_byte()).into(),
-    })
 }
 
-fn handle_six(bytes: InstructionBytePair) -> Option<Instruction> {
-    Some(Instruction::LoadValue {
+fn handle_six(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
+    Ok(Instruction::LoadValue {
         dest: Nibble::from_lower(bytes.get_upper_byte()).into(),
         value: bytes.get_lower_byte(),
     })
 }
 
-fn handle_seven(bytes: InstructionBytePair) -> Option<Instruction> {
-    Some(Instruction::AddValue {
+fn handle_seven(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
+    Ok(Instruction::AddValue {
         dest: Nibble::from_lower(bytes.get_upper_byte()).into(),
         value: bytes.get_lower_byte(),
     })
 }
 
-fn handle_eight(bytes: InstructionBytePair) -> Option<Instruction> {
+fn handle_eight(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
     let x: GeneralRegister = Nibble::from_lower(bytes.get_upper_byte()).into();
     let y: GeneralRegister = Nibble::from_upper(bytes.get_lower_byte()).into();
     match Nibble::from_lower(bytes.get_lower_byte()) {
-        Nibble::Zero => Some(Instruction::LoadRegister { dest: x, source: y }),
-        Nibble::One => Some(Instruction::Or { dest: x, source: y }),
-        Nibble::Two => Some(Instruction::And { dest: x, source: y }),
-        Nibble::Three => Some(Instruction::Xor { dest: x, source: y }),
-        Nibble::Four => Some(Instruction::AddRegister { dest: x, source: y }),
-        Nibble::Five => Some(Instruction::Subtract { dest: x, source: y }),
-        Nibble::Six => Some(Instruction::ShiftRight { dest: x, source: y }),
-        Nibble::Seven => Some(Instruction::SubtractNegate { dest: x, source: y }),
-        Nibble::Fourteen => Some(Instruction::ShiftLeft { dest: x, source: y }),
-        _ => None,
+        Nibble::Zero => Ok(Instruction::LoadRegister { dest: x, source: y }),
+        Nibble::One => Ok(Instruction::Or { dest: x, source: y }),
+        Nibble::Two => Ok(Instruction::And { dest: x, source: y }),
+        Nibble::Three => Ok(Instruction::Xor { dest: x, source: y }),
+        Nibble::Four => Ok(Instruction::AddRegister { dest: x, source: y }),
+        Nibble::Five => Ok(Instruction::Subtract { dest: x, source: y }),
+        Nibble::Six => Ok(Instruction::ShiftRight { dest: x, source: y }),
+        Nibble::Seven => Ok(Instruction::SubtractNegate { dest: x, source: y }),
+        Nibble::Fourteen => Ok(Instruction::ShiftLeft { dest: x, source: y }),
+        _ => Err(DecodeError::Arithmetic {
+            byte: bytes.get_lower_byte(),
+        }),
     }
 }
 
-fn handle_nine(bytes: InstructionBytePair) -> Option<Instruction> {
+fn handle_nine(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
     if Nibble::from_lower(bytes.get_lower_byte()) != Nibble::Zero {
-        return None;
+        return Err(DecodeError::SkipIfNotEqual {
+            byte: bytes.get_lower_byte(),
+        });
     }
 
-    Some(Instruction::SkipIfNeqReg {
+    Ok(Instruction::SkipIfNeqReg {
         lhs: Nibble::from_lower(bytes.get_upper_byte()).into(),
         rhs: Nibble::from_upper(bytes.get_lower_byte()).into(),
     })
 }
 
-fn handle_ten(bytes: InstructionBytePair) -> Option<Instruction> {
-    Some(Instruction::LoadI {
+fn handle_ten(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
+    Ok(Instruction::LoadI {
         addr: Address::from(bytes.0 & 0x0FFF),
     })
 }
 
-fn handle_eleven(bytes: InstructionBytePair) -> Option<Instruction> {
-    Some(Instruction::JumpPlusV0 {
+fn handle_eleven(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
+    Ok(Instruction::JumpPlusV0 {
         addr: Address::from(bytes.0 & 0x0FFF),
     })
 }
 
-fn handle_twelve(bytes: InstructionBytePair) -> Option<Instruction> {
-    Some(Instruction::Random {
+fn handle_twelve(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
+    Ok(Instruction::Random {
         dest: Nibble::from_lower(bytes.get_upper_byte()).into(),
         mask: bytes.get_lower_byte(),
     })
 }
 
-fn handle_thirteen(bytes: InstructionBytePair) -> Option<Instruction> {
-    Some(Instruction::Draw {
+fn handle_thirteen(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
+    Ok(Instruction::Draw {
         x: Nibble::from_lower(bytes.get_upper_byte()).into(),
         y: Nibble::from_upper(bytes.get_lower_byte()).into(),
         num_bytes: Nibble::from_lower(bytes.get_lower_byte()),
     })
 }
 
-fn handle_fourteen(bytes: InstructionBytePair) -> Option<Instruction> {
+fn handle_fourteen(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
     let key_val: GeneralRegister = Nibble::from_lower(bytes.get_upper_byte()).into();
     match bytes.get_lower_byte() {
-        0x9E => Some(Instruction::SkipIfKeyDown { key_val }),
-        0xA1 => Some(Instruction::SkipIfKeyUp { key_val }),
-        _ => None,
+        0x9E => Ok(Instruction::SkipIfKeyDown { key_val }),
+        0xA1 => Ok(Instruction::SkipIfKeyUp { key_val }),
+        byte => Err(DecodeError::KeySkip { byte }),
     }
 }
 
-fn handle_fifteen(bytes: InstructionBytePair) -> Option<Instruction> {
+fn handle_fifteen(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
     let x: GeneralRegister = Nibble::from_lower(bytes.get_upper_byte()).into();
     match bytes.get_lower_byte() {
-        0x07 => Some(Instruction::LoadFromDelayTimer { dest: x }),
-        0x0A => Some(Instruction::LoadFromKey { dest: x }),
-        0x15 => Some(Instruction::SetDelayTimer { source: x }),
-        0x18 => Some(Instruction::SetSoundTimer { source: x }),
-        0x1E => Some(Instruction::AddI { source: x }),
-        0x29 => Some(Instruction::LoadSpriteLocation { digit: x }),
-        0x33 => Some(Instruction::LoadBcd { source: x }),
-        0x55 => Some(Instruction::StoreRegisterRangeAtI { last: x }),
-        0x65 => Some(Instruction::LoadRegisterRangeFromI { last: x }),
-        _ => None,
+        0x01 => Ok(Instruction::SelectPlanes {
+            mask: Nibble::from_lower(bytes.get_upper_byte()),
+        }),
+        // X isn't used, as in Octo
+        0x02 => Ok(Instruction::LoadAudioPattern),
+        0x07 => Ok(Instruction::LoadFromDelayTimer { dest: x }),
+        0x0A => Ok(Instruction::LoadFromKey { dest: x }),
+        0x15 => Ok(Instruction::SetDelayTimer { source: x }),
+        0x18 => Ok(Instruction::SetSoundTimer { source: x }),
+        0x1E => Ok(Instruction::AddI { source: x }),
+        0x29 => Ok(Instruction::LoadSpriteLocation { digit: x }),
+        0x30 => Ok(Instruction::LoadHighSpriteLocation { digit: x }),
+        0x33 => Ok(Instruction::LoadBcd { source: x }),
+        0x3A => Ok(Instruction::SetPitch { source: x }),
+        0x55 => Ok(Instruction::StoreRegisterRangeAtI { last: x }),
+        0x65 => Ok(Instruction::LoadRegisterRangeFromI { last: x }),
+        0x75 => Ok(Instruction::StoreFlags { last: x }),
+        0x85 => Ok(Instruction::LoadFlags { last: x }),
+        byte => Err(DecodeError::Misc { byte }),
+    }
+}
+
+// Why a word isn't an instruction. Mostly that's the group its first nibble
+// picks, and the low byte that doesn't name anything in that group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    // 5XYN, with N other than 0, 2 or 3
+    RegisterPair { byte: u8 },
+    // 8XYN, with N not one of the arithmetic and logic operations
+    Arithmetic { byte: u8 },
+    // 9XYN, with N other than 0
+    SkipIfNotEqual { byte: u8 },
+    // EXNN, with NN other than 9E or A1
+    KeySkip { byte: u8 },
+    // FXNN, with NN not one of the timer, memory and font operations
+    Misc { byte: u8 },
+    // 0NNN, under the sys_is_invalid quirk
+    SysDisabled,
+    // An XO-CHIP instruction, without the xo_chip option
+    XoChipDisabled,
+    // A lone byte at the end of the program, or F000 without its address
+    Truncated,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let (group, byte) = match self {
+            DecodeError::RegisterPair { byte } => ("5XY_", byte),
+            DecodeError::Arithmetic { byte } => ("8XY_", byte),
+            DecodeError::SkipIfNotEqual { byte } => ("9XY_", byte),
+            DecodeError::KeySkip { byte } => ("EX__", byte),
+            DecodeError::Misc { byte } => ("FX__", byte),
+            DecodeError::SysDisabled => {
+                return write!(f, "0NNN machine code calls are treated as invalid")
+            }
+            DecodeError::XoChipDisabled => {
+                return write!(f, "XO-CHIP instructions need the xo_chip option")
+            }
+            DecodeError::Truncated => {
+                return write!(f, "the program ends partway through the instruction")
+            }
+        };
+        write!(f, "{:#04x} doesn't end a valid {} instruction", byte, group)
     }
 }
 
-pub fn decode(bytes: InstructionBytePair) -> Option<Instruction> {
+impl core::error::Error for DecodeError {}
+
+pub fn decode(bytes: InstructionBytePair) -> Result<Instruction, DecodeError> {
     match Nibble::from_upper(bytes.get_upper_byte()) {
         Nibble::Zero => handle_zero(bytes),
         Nibble::One => handle_one(bytes),
@@ -305,9 +619,171 @@ pub fn decode(bytes: InstructionBytePair) -> Option<Instruction> {
     }
 }
 
+pub fn decode_opt(bytes: InstructionBytePair) -> Option<Instruction> {
+    decode(bytes).ok()
+}
+
+fn encode_nnn(opcode: u16, addr: Address) -> u16 {
+    opcode | (u16::from(addr) & 0x0FFF)
+}
+
+fn encode_xnn(opcode: u16, x: GeneralRegister, nn: u8) -> u16 {
+    opcode | ((x as u16) << 8) | nn as u16
+}
+
+fn encode_xyn(opcode: u16, x: GeneralRegister, y: GeneralRegister, n: u8) -> u16 {
+    opcode | ((x as u16) << 8) | ((y as u16) << 4) | n as u16
+}
+
+// The inverse of decode, plus the four-byte F000 NNNN. Encoding what decode
+// returns gives back the original word, apart from the X that FX02 ignores.
+pub fn encode(instruction: &Instruction) -> Vec<u8> {
+    use GeneralRegister::V0;
+
+    let word = match *instruction {
+        Instruction::Sys { addr } => encode_nnn(0x0000, addr),
+        Instruction::Clear => 0x00E0,
+        Instruction::Return => 0x00EE,
+        Instruction::LowResolution => 0x00FE,
+        Instruction::HighResolution => HIRES_SWITCH,
+        Instruction::ScrollDown { rows } => 0x00C0 | rows as u16,
+        Instruction::ScrollRight => 0x00FB,
+        Instruction::ScrollLeft => 0x00FC,
+        Instruction::Exit => 0x00FD,
+        Instruction::Jump { addr } => encode_nnn(0x1000, addr),
+        Instruction::Call { addr } => encode_nnn(0x2000, addr),
+        Instruction::SkipIfEqByte { reg, value } => encode_xnn(0x3000, reg, value),
+        Instruction::SkipIfNeqByte { reg, value } => encode_xnn(0x4000, reg, value),
+        Instruction::SkipIfEqReg { lhs, rhs } => encode_xyn(0x5000, lhs, rhs, 0x0),
+        Instruction::StoreRegisterRange { first, last } => encode_xyn(0x5000, first, last, 0x2),
+        Instruction::LoadRegisterRange { first, last } => encode_xyn(0x5000, first, last, 0x3),
+        Instruction::LoadValue { dest, value } => encode_xnn(0x6000, dest, value),
+        Instruction::AddValue { dest, value } => encode_xnn(0x7000, dest, value),
+        Instruction::LoadRegister { dest, source } => encode_xyn(0x8000, dest, source, 0x0),
+        Instruction::Or { dest, source } => encode_xyn(0x8000, dest, source, 0x1),
+        Instruction::And { dest, source } => encode_xyn(0x8000, dest, source, 0x2),
+        Instruction::Xor { dest, source } => encode_xyn(0x8000, dest, source, 0x3),
+        Instruction::AddRegister { dest, source } => encode_xyn(0x8000, dest, source, 0x4),
+        Instruction::Subtract { dest, source } => encode_xyn(0x8000, dest, source, 0x5),
+        Instruction::ShiftRight { dest, source } => encode_xyn(0x8000, dest, source, 0x6),
+        Instruction::SubtractNegate { dest, source } => encode_xyn(0x8000, dest, source, 0x7),
+        Instruction::ShiftLeft { dest, source } => encode_xyn(0x8000, dest, source, 0xE),
+        Instruction::SkipIfNeqReg { lhs, rhs } => encode_xyn(0x9000, lhs, rhs, 0x0),
+        Instruction::LoadI { addr } => encode_nnn(0xA000, addr),
+        Instruction::LoadLongI { addr } => {
+            let mut bytes = vec![0xF0, 0x00];
+            bytes.extend(u16::from(addr).to_be_bytes());
+            return bytes;
+        }
+        Instruction::JumpPlusV0 { addr } => encode_nnn(0xB000, addr),
+        Instruction::Random { dest, mask } => encode_xnn(0xC000, dest, mask),
+        Instruction::Draw { x, y, num_bytes } => encode_xyn(0xD000, x, y, num_bytes as u8),
+        Instruction::SkipIfKeyDown { key_val } => encode_xnn(0xE000, key_val, 0x9E),
+        Instruction::SkipIfKeyUp { key_val } => encode_xnn(0xE000, key_val, 0xA1),
+        Instruction::SelectPlanes { mask } => encode_xnn(0xF000, mask.into(), 0x01),
+        Instruction::LoadAudioPattern => encode_xnn(0xF000, V0, 0x02),
+        Instruction::LoadFromDelayTimer { dest } => encode_xnn(0xF000, dest, 0x07),
+        Instruction::LoadFromKey { dest } => encode_xnn(0xF000, dest, 0x0A),
+        Instruction::SetDelayTimer { source } => encode_xnn(0xF000, source, 0x15),
+        Instruction::SetSoundTimer { source } => encode_xnn(0xF000, source, 0x18),
+        Instruction::AddI { source } => encode_xnn(0xF000, source, 0x1E),
+        Instruction::LoadSpriteLocation { digit } => encode_xnn(0xF000, digit, 0x29),
+        Instruction::LoadHighSpriteLocation { digit } => encode_xnn(0xF000, digit, 0x30),
+        Instruction::LoadBcd { source } => encode_xnn(0xF000, source, 0x33),
+        Instruction::SetPitch { source } => encode_xnn(0xF000, source, 0x3A),
+        Instruction::StoreRegisterRangeAtI { last } => encode_xnn(0xF000, last, 0x55),
+        Instruction::LoadRegisterRangeFromI { last } => encode_xnn(0xF000, last, 0x65),
+        Instruction::StoreFlags { last } => encode_xnn(0xF000, last, 0x75),
+        Instruction::LoadFlags { last } => encode_xnn(0xF000, last, 0x85),
+    };
+    word.to_be_bytes().to_vec()
+}
+
+// SCHIP programs tend to switch to hires within their first few instructions, so
+// scanning the head of the ROM is enough to pick a window size before running it.
+pub fn detect_hires(program: &[u8]) -> bool {
+    program
+        .chunks_exact(2)
+        .take(HIRES_SCAN_WORDS)
+        .any(|word| u16::from_be_bytes([word[0], word[1]]) == HIRES_SWITCH)
+}
+
+// Pairs every word of the program with its address when loaded at `start`.
+// Words that don't decode, usually sprite data, come back as the reason why,
+// as does a trailing odd byte. F000 NNNN comes back as one four-byte
+// instruction.
+pub fn disassemble(bytes: &[u8], start: u16) -> Vec<(u16, Result<Instruction, DecodeError>)> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let address = start.wrapping_add(offset as u16);
+        let word = |offset: usize| match bytes.get(offset..offset + 2) {
+            Some(&[high, low]) => Some(u16::from_be_bytes([high, low])),
+            _ => None,
+        };
+        let instruction = match (word(offset), word(offset + 2)) {
+            (Some(LONG_LOAD), Some(long_address)) => {
+                offset += 2;
+                Ok(Instruction::LoadLongI {
+                    addr: Address::extended(long_address),
+                })
+            }
+            (Some(LONG_LOAD), None) | (None, _) => Err(DecodeError::Truncated),
+            (Some(word), _) => decode(InstructionBytePair(word)),
+        };
+        instructions.push((address, instruction));
+        offset += 2;
+    }
+    instructions
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnsupportedOpcode {
+    pub address: Address,
+    pub instruction: Instruction,
+    pub target: InstructionSet,
+}
+
+impl Display for UnsupportedOpcode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{}: {} is a {} instruction, which {} doesn't support",
+            self.address,
+            self.instruction,
+            self.instruction.instruction_set(),
+            self.target
+        )
+    }
+}
+
+// Disassembles the program looking for instructions beyond `target`. Sprite
+// data is scanned too, so a match is a hint that the ROM targets a later
+// instruction set rather than proof.
+pub fn find_unsupported_opcodes(
+    program: &[u8],
+    start: u16,
+    target: InstructionSet,
+) -> Vec<UnsupportedOpcode> {
+    disassemble(program, start)
+        .into_iter()
+        .filter_map(|(address, instruction)| {
+            instruction
+                .ok()
+                .filter(|instruction| instruction.instruction_set() > target)
+                .map(|instruction| UnsupportedOpcode {
+                    address: Address::from(address),
+                    instruction,
+                    target,
+                })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::string::ToString;
     use strum::IntoEnumIterator;
 
     fn all_addresses() -> impl Iterator<Item = u16> {
@@ -318,6 +794,52 @@ mod tests {
         0x00..=0xFF
     }
 
+    #[test]
+    fn test_instruction_mnemonics() {
+        for (opcode, mnemonic) in [
+            (0x0123, "SYS 0x123"),
+            (0x00E0, "CLS"),
+            (0x00EE, "RET"),
+            (0x00C4, "SCD 4"),
+            (0x00FF, "HIGH"),
+            (0x1208, "JP 0x208"),
+            (0x2ABC, "CALL 0xabc"),
+            (0x3A0F, "SE VA, 0x0f"),
+            (0x6120, "LD V1, 0x20"),
+            (0x7FFF, "ADD VF, 0xff"),
+            (0x8126, "SHR V1, V2"),
+            (0x8347, "SUBN V3, V4"),
+            (0xA300, "LD I, 0x300"),
+            (0xB300, "JP V0, 0x300"),
+            (0xC53F, "RND V5, 0x3f"),
+            (0xD015, "DRW V0, V1, 5"),
+            (0xD120, "DRW V1, V2, 0"),
+            (0xE19E, "SKP V1"),
+            (0xE2A1, "SKNP V2"),
+            (0xF307, "LD V3, DT"),
+            (0xF40A, "LD V4, K"),
+            (0xF515, "LD DT, V5"),
+            (0xF618, "LD ST, V6"),
+            (0xF71E, "ADD I, V7"),
+            (0xF829, "LD F, V8"),
+            (0xF930, "LD HF, V9"),
+            (0xFA33, "LD B, VA"),
+            (0xFB55, "LD [I], VB"),
+            (0xFC65, "LD VC, [I]"),
+            (0xF775, "LD R, V7"),
+            (0xF785, "LD V7, R"),
+        ] {
+            let decoded = decode(InstructionBytePair(opcode)).unwrap();
+            assert_eq!(decoded.to_string(), mnemonic);
+        }
+    }
+
+    #[test]
+    fn test_byte_pair_display() {
+        assert_eq!(InstructionBytePair(0xF001).to_string(), "0xf001");
+        assert_eq!(InstructionBytePair(0x00E0).to_string(), "0x00e0");
+    }
+
     #[test]
     fn test_cls() {
         let clear_bytes = InstructionBytePair(0x00E0);
@@ -332,9 +854,48 @@ mod tests {
         assert_eq!(decoded, Instruction::Return);
     }
 
+    #[test]
+    fn test_resolution_switches() {
+        assert_eq!(
+            decode_opt(InstructionBytePair(0x00FE)),
+            Some(Instruction::LowResolution)
+        );
+        assert_eq!(
+            decode_opt(InstructionBytePair(0x00FF)),
+            Some(Instruction::HighResolution)
+        );
+    }
+
+    #[test]
+    fn test_scrolls() {
+        for (rows, value) in Nibble::iter().zip(0x00C0..=0x00CF) {
+            assert_eq!(
+                decode_opt(InstructionBytePair(value)),
+                Some(Instruction::ScrollDown { rows })
+            );
+        }
+        assert_eq!(
+            decode_opt(InstructionBytePair(0x00FB)),
+            Some(Instruction::ScrollRight)
+        );
+        assert_eq!(
+            decode_opt(InstructionBytePair(0x00FC)),
+            Some(Instruction::ScrollLeft)
+        );
+    }
+
+    #[test]
+    fn test_exit() {
+        assert_eq!(
+            decode_opt(InstructionBytePair(0x00FD)),
+            Some(Instruction::Exit)
+        );
+    }
+
     #[test]
     fn test_sys() {
-        let non_sys_addresses = [0x00E0, 0x00EE];
+        let mut non_sys_addresses = vec![0x00E0, 0x00EE, 0x00FB, 0x00FC, 0x00FD, 0x00FE, 0x00FF];
+        non_sys_addresses.extend(0x00C0..=0x00CF);
         for value in all_addresses().filter(|x| !non_sys_addresses.contains(x)) {
             let sys_bytes = InstructionBytePair(value);
             let decoded = decode(sys_bytes).unwrap();
@@ -402,11 +963,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_save_load_vx_vy() {
+        for first in GeneralRegister::iter() {
+            for last in GeneralRegister::iter() {
+                let operands = ((first as u16) << 8) | ((last as u16) << 4);
+                let decoded = decode(InstructionBytePair(0x5002 | operands)).unwrap();
+                assert_eq!(decoded, Instruction::StoreRegisterRange { first, last });
+                let decoded = decode(InstructionBytePair(0x5003 | operands)).unwrap();
+                assert_eq!(decoded, Instruction::LoadRegisterRange { first, last });
+            }
+        }
+        assert_eq!(
+            decode(InstructionBytePair(0x5312)).unwrap().to_string(),
+            "SAVE V3 - V1"
+        );
+    }
+
     #[test]
     fn test_invalid_fives() {
-        for bytes in (0x0000..0x1000).filter(|x| (x % 0x0010) != 0) {
+        for bytes in (0x0000..0x1000).filter(|x| ![0x0, 0x2, 0x3].contains(&(x % 0x0010))) {
             let invalid_bytes = InstructionBytePair(0x5000 | bytes);
-            let decoded = decode(invalid_bytes);
+            let decoded = decode_opt(invalid_bytes);
             assert!(decoded.is_none());
         }
     }
@@ -543,11 +1121,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_shift_decode_keeps_y_nibble() {
+        // the shift quirk reads VY, so Y must survive decoding even though the
+        // in-place shifts ignore it
+        assert_eq!(
+            decode_opt(InstructionBytePair(0x8AB6)),
+            Some(Instruction::ShiftRight {
+                dest: GeneralRegister::VA,
+                source: GeneralRegister::VB,
+            })
+        );
+        assert_eq!(
+            decode_opt(InstructionBytePair(0x80FE)),
+            Some(Instruction::ShiftLeft {
+                dest: GeneralRegister::V0,
+                source: GeneralRegister::VF,
+            })
+        );
+    }
+
     #[test]
     fn test_invalid_eights() {
         for bytes in (0x0000..0x1000).filter(|x| (x % 0x0010) > 0x7 && (x % 0x0010) != 0xE) {
             let invalid_bytes = InstructionBytePair(0x8000 | bytes);
-            let decoded = decode(invalid_bytes);
+            let decoded = decode_opt(invalid_bytes);
             assert!(decoded.is_none());
         }
     }
@@ -568,7 +1166,7 @@ mod tests {
     fn test_invalid_nines() {
         for bytes in (0x0000..0x1000).filter(|x| (x % 0x0010) != 0) {
             let invalid_bytes = InstructionBytePair(0x9000 | bytes);
-            let decoded = decode(invalid_bytes);
+            let decoded = decode_opt(invalid_bytes);
             assert!(decoded.is_none());
         }
     }
@@ -690,6 +1288,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ld_hf_vx() {
+        for digit in GeneralRegister::iter() {
+            let high_font_bytes = InstructionBytePair(0xF030 | ((digit as u16) << 8));
+            let decoded = decode(high_font_bytes).unwrap();
+            assert_eq!(decoded, Instruction::LoadHighSpriteLocation { digit });
+        }
+    }
+
     #[test]
     fn test_ld_b_vx() {
         for source in GeneralRegister::iter() {
@@ -717,15 +1324,305 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ld_r_vx() {
+        for last in GeneralRegister::iter() {
+            let store_flags_bytes = InstructionBytePair(0xF075 | ((last as u16) << 8));
+            let decoded = decode(store_flags_bytes).unwrap();
+            assert_eq!(decoded, Instruction::StoreFlags { last });
+        }
+    }
+
+    #[test]
+    fn test_ld_vx_r() {
+        for last in GeneralRegister::iter() {
+            let load_flags_bytes = InstructionBytePair(0xF085 | ((last as u16) << 8));
+            let decoded = decode(load_flags_bytes).unwrap();
+            assert_eq!(decoded, Instruction::LoadFlags { last });
+        }
+    }
+
+    #[test]
+    fn test_plane_n() {
+        for mask in Nibble::iter() {
+            let select_planes_bytes = InstructionBytePair(0xF001 | ((mask as u16) << 8));
+            let decoded = decode(select_planes_bytes).unwrap();
+            assert_eq!(decoded, Instruction::SelectPlanes { mask });
+        }
+        assert_eq!(
+            decode(InstructionBytePair(0xF301)).unwrap().to_string(),
+            "PLANE 3"
+        );
+    }
+
+    #[test]
+    fn test_audio_and_pitch() {
+        for x in GeneralRegister::iter() {
+            let audio_bytes = InstructionBytePair(0xF002 | ((x as u16) << 8));
+            assert_eq!(decode(audio_bytes).unwrap(), Instruction::LoadAudioPattern);
+            let pitch_bytes = InstructionBytePair(0xF03A | ((x as u16) << 8));
+            assert_eq!(
+                decode(pitch_bytes).unwrap(),
+                Instruction::SetPitch { source: x }
+            );
+        }
+        assert_eq!(
+            decode(InstructionBytePair(0xF53A)).unwrap().to_string(),
+            "PITCH V5"
+        );
+    }
+
     #[test]
     fn test_invalid_fifteens() {
-        let valid_tails = [0x07, 0x0A, 0x15, 0x18, 0x1E, 0x29, 0x33, 0x55, 0x65];
+        let valid_tails = [
+            0x01, 0x02, 0x07, 0x0A, 0x15, 0x18, 0x1E, 0x29, 0x30, 0x33, 0x3A, 0x55, 0x65, 0x75,
+            0x85,
+        ];
         for x in GeneralRegister::iter() {
             for invalid_tail in (0x00..=0xFF).filter(|x| !valid_tails.contains(x)) {
                 let invalid_bytes = InstructionBytePair(0xF000 | ((x as u16) << 8) | invalid_tail);
-                let decoded = decode(invalid_bytes);
+                let decoded = decode_opt(invalid_bytes);
                 assert!(decoded.is_none());
             }
         }
     }
+
+    #[test]
+    fn test_encode_round_trip() {
+        for word in (0x0000..=0xFFFF).filter(|word| word & 0xF0FF != 0xF002) {
+            if let Ok(instruction) = decode(InstructionBytePair(word)) {
+                assert_eq!(encode(&instruction), word.to_be_bytes(), "{}", instruction);
+            }
+        }
+        assert_eq!(encode(&Instruction::LoadAudioPattern), [0xF0, 0x02]);
+        assert_eq!(
+            encode(&Instruction::LoadLongI {
+                addr: Address::extended(0xABCD)
+            }),
+            [0xF0, 0x00, 0xAB, 0xCD]
+        );
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(
+            decode(InstructionBytePair(0x5AB1)),
+            Err(DecodeError::RegisterPair { byte: 0xB1 })
+        );
+        assert_eq!(
+            decode(InstructionBytePair(0x812F)),
+            Err(DecodeError::Arithmetic { byte: 0x2F })
+        );
+        assert_eq!(
+            decode(InstructionBytePair(0x9128)),
+            Err(DecodeError::SkipIfNotEqual { byte: 0x28 })
+        );
+        assert_eq!(
+            decode(InstructionBytePair(0xE39F)),
+            Err(DecodeError::KeySkip { byte: 0x9F })
+        );
+        assert_eq!(
+            decode(InstructionBytePair(0xF0FF)),
+            Err(DecodeError::Misc { byte: 0xFF })
+        );
+        assert_eq!(
+            DecodeError::Arithmetic { byte: 0x2F }.to_string(),
+            "0x2f doesn't end a valid 8XY_ instruction"
+        );
+        assert_eq!(decode_opt(InstructionBytePair(0x812F)), None);
+    }
+
+    #[test]
+    fn test_detect_hires_leading() {
+        let program = [0x00, 0xFF, 0x60, 0x01, 0x12, 0x04];
+        assert!(detect_hires(&program));
+    }
+
+    #[test]
+    fn test_detect_hires_early() {
+        let program = [0x00, 0xE0, 0x60, 0x01, 0x00, 0xFF];
+        assert!(detect_hires(&program));
+    }
+
+    #[test]
+    fn test_detect_hires_absent() {
+        let program = [0x00, 0xE0, 0x60, 0xFF, 0x12, 0x04];
+        assert!(!detect_hires(&program));
+    }
+
+    #[test]
+    fn test_detect_hires_misaligned() {
+        // 0x00FF straddling two instructions isn't a hires switch
+        let program = [0x60, 0x00, 0xFF, 0x00];
+        assert!(!detect_hires(&program));
+    }
+
+    #[test]
+    fn test_detect_hires_too_late() {
+        let mut program = vec![0x00; HIRES_SCAN_WORDS * 2];
+        program.extend_from_slice(&[0x00, 0xFF]);
+        assert!(!detect_hires(&program));
+    }
+
+    #[test]
+    fn test_reads_register() {
+        let add = decode(InstructionBytePair(0x81F4)).unwrap(); // ADD V1, VF
+        assert!(add.reads_register(GeneralRegister::V1));
+        assert!(add.reads_register(GeneralRegister::VF));
+        assert!(!add.reads_register(GeneralRegister::V2));
+
+        let load = decode(InstructionBytePair(0x6F12)).unwrap(); // LD VF, 0x12
+        assert!(!load.reads_register(GeneralRegister::VF));
+
+        let shift = decode(InstructionBytePair(0x8126)).unwrap(); // SHR V1, V2
+        assert!(shift.reads_register(GeneralRegister::V1));
+        assert!(shift.reads_register(GeneralRegister::V2));
+
+        let store = decode(InstructionBytePair(0xF355)).unwrap(); // LD [I], V3
+        assert!(store.reads_register(GeneralRegister::V3));
+        assert!(!store.reads_register(GeneralRegister::V4));
+    }
+
+    #[test]
+    fn test_sets_vf_flag() {
+        assert!(decode(InstructionBytePair(0x8124)).unwrap().sets_vf_flag());
+        assert!(decode(InstructionBytePair(0xD125)).unwrap().sets_vf_flag());
+        assert!(!decode(InstructionBytePair(0x8121)).unwrap().sets_vf_flag());
+        assert!(!decode(InstructionBytePair(0x6F01)).unwrap().sets_vf_flag());
+    }
+
+    #[test]
+    fn test_find_unsupported_opcodes() {
+        let program = [
+            0x00, 0xE0, // CLS
+            0x00, 0xC4, // SCHIP: scroll down 4
+            0x60, 0x01, // LD V0, 0x01
+            0xF1, 0x75, // SCHIP: save V0-V1 to flags
+            0xF0, 0x00, 0x12, 0x34, // XO-CHIP: LD I, LONG 0x1234
+            0x12, 0x0C, // JP 0x20C
+        ];
+
+        let found = find_unsupported_opcodes(&program, 0x200, InstructionSet::Chip8);
+        let addresses: Vec<_> = found
+            .iter()
+            .map(|opcode| u16::from(opcode.address))
+            .collect();
+        assert_eq!(addresses, [0x202, 0x206, 0x208]);
+        assert_eq!(
+            found[0],
+            UnsupportedOpcode {
+                address: Address::from(0x202),
+                instruction: Instruction::ScrollDown { rows: Nibble::Four },
+                target: InstructionSet::Chip8,
+            }
+        );
+        assert_eq!(
+            found[0].to_string(),
+            "0x202: SCD 4 is a SUPER-CHIP instruction, which CHIP-8 doesn't support"
+        );
+
+        let found = find_unsupported_opcodes(&program, 0x600, InstructionSet::SuperChip);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].address, Address::from(0x608));
+        assert_eq!(
+            found[0].instruction,
+            Instruction::LoadLongI {
+                addr: Address::extended(0x1234)
+            }
+        );
+        assert!(find_unsupported_opcodes(&program, 0x200, InstructionSet::XoChip).is_empty());
+        assert!(
+            find_unsupported_opcodes(&[0x00, 0xE0, 0x12, 0x00], 0x200, InstructionSet::Chip8)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_instruction_set() {
+        assert_eq!(Instruction::Clear.instruction_set(), InstructionSet::Chip8);
+        assert_eq!(
+            Instruction::Exit.instruction_set(),
+            InstructionSet::SuperChip
+        );
+        assert_eq!(
+            Instruction::LoadAudioPattern.instruction_set(),
+            InstructionSet::XoChip
+        );
+        let draw = |num_bytes| Instruction::Draw {
+            x: GeneralRegister::V0,
+            y: GeneralRegister::V1,
+            num_bytes,
+        };
+        assert_eq!(draw(Nibble::Five).instruction_set(), InstructionSet::Chip8);
+        assert_eq!(
+            draw(Nibble::Zero).instruction_set(),
+            InstructionSet::SuperChip
+        );
+        assert!(InstructionSet::Chip8 < InstructionSet::SuperChip);
+        assert!(InstructionSet::SuperChip < InstructionSet::XoChip);
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let program = [
+            0x00, 0xE0, // CLS
+            0x61, 0x20, // LD V1, 0x20
+            0x5A, 0xB1, // not an instruction
+            0xD0, 0x15, // DRW V0, V1, 5
+            0x12, 0x00, // JP 0x200
+            0xF0, // trailing byte
+        ];
+
+        assert_eq!(
+            disassemble(&program, 0x200),
+            vec![
+                (0x200, Ok(Instruction::Clear)),
+                (
+                    0x202,
+                    Ok(Instruction::LoadValue {
+                        dest: GeneralRegister::V1,
+                        value: 0x20
+                    })
+                ),
+                (0x204, Err(DecodeError::RegisterPair { byte: 0xB1 })),
+                (
+                    0x206,
+                    Ok(Instruction::Draw {
+                        x: GeneralRegister::V0,
+                        y: GeneralRegister::V1,
+                        num_bytes: Nibble::Five
+                    })
+                ),
+                (
+                    0x208,
+                    Ok(Instruction::Jump {
+                        addr: Address::from(0x200)
+                    })
+                ),
+                (0x20A, Err(DecodeError::Truncated)),
+            ]
+        );
+        assert!(disassemble(&[], 0x200).is_empty());
+        assert_eq!(
+            disassemble(&[0xF0, 0x00, 0x12, 0x34, 0xF0, 0x00], 0x200),
+            vec![
+                (
+                    0x200,
+                    Ok(Instruction::LoadLongI {
+                        addr: Address::extended(0x1234)
+                    })
+                ),
+                (0x204, Err(DecodeError::Truncated)),
+            ]
+        );
+        assert_eq!(
+            disassemble(&program, 0x600)[4],
+            (
+                0x608,
+                Ok(Instruction::Jump {
+                    addr: Address::from(0x200)
+                })
+            )
+        );
+    }
 }
@@ -1,4 +1,4 @@
-pub(crate) const NUM_KEYS: usize = 16;
+pub const NUM_KEYS: usize = 16;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyStatus {
@@ -6,6 +6,7 @@ pub enum KeyStatus {
     Released,
 }
 
+#[derive(Clone)]
 pub(crate) struct Keys {
     keys_status: [KeyStatus; NUM_KEYS],
 }
@@ -17,18 +18,39 @@ impl Keys {
         }
     }
 
+    // Keys outside the keypad are ignored.
     pub(crate) fn input(&mut self, key: usize, status: KeyStatus) {
-        if key > NUM_KEYS {
-            return;
+        if let Some(key_status) = self.keys_status.get_mut(key) {
+            *key_status = status;
         }
-        self.keys_status[key] = status;
     }
 
     pub(crate) fn get_status(&self, key: usize) -> Option<KeyStatus> {
-        if key > NUM_KEYS {
-            None
-        } else {
-            Some(self.keys_status[key])
+        self.keys_status.get(key).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_key_valid() {
+        let mut keys = Keys::new();
+        keys.input(15, KeyStatus::Pressed);
+        assert_eq!(keys.get_status(15), Some(KeyStatus::Pressed));
+    }
+
+    #[test]
+    fn test_keys_out_of_range() {
+        let mut keys = Keys::new();
+        for key in [NUM_KEYS, usize::MAX] {
+            keys.input(key, KeyStatus::Pressed);
+            assert_eq!(keys.get_status(key), None);
         }
+        assert!(keys
+            .keys_status
+            .iter()
+            .all(|status| *status == KeyStatus::Released));
     }
 }
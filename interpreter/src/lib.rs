@@ -1,7 +1,18 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod assembler;
+pub mod builtin_roms;
+pub mod cheats;
 mod common_test_data;
 pub mod display;
+pub mod emulator;
 pub mod instructions;
 pub mod keypad;
 pub mod processor;
+#[cfg(feature = "profiling")]
+pub mod profile;
 mod registers;
+pub mod regression;
 pub mod types;
@@ -1,19 +1,39 @@
+#[cfg(feature = "profiling")]
+use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeSet, VecDeque};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+#[cfg(feature = "serde")]
+use bincode::Options;
 use core::fmt;
+use core::ops::Range;
 use grid::Grid;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use strum::IntoEnumIterator;
 
-use crate::display::{Display, Pixel};
-use crate::instructions::{self, Instruction};
+use crate::display::{Display, Pixel, PixelsDisabled};
+use crate::instructions::{self, DecodeError, Instruction, InstructionSet};
 use crate::keypad::{KeyStatus, Keys, NUM_KEYS};
+#[cfg(feature = "profiling")]
+use crate::profile::Profile;
 use crate::registers::{Flag, Registers};
-use crate::types::{Address, GeneralRegister};
+use crate::types::{Address, GeneralRegister, Nibble};
 
-const MEMORY_SIZE_BYTES: usize = 0xFFF;
+pub(crate) const MEMORY_SIZE_BYTES: usize = 0x1000;
+const XO_CHIP_MEMORY_SIZE_BYTES: usize = 0x10000;
 const STACK_SIZE: usize = 16;
 const PROGRAM_START: usize = 0x200;
-const MAX_PROGRAM_BYTES: usize = MEMORY_SIZE_BYTES - PROGRAM_START;
-const HEX_SPRITE_STRIDE: usize = 5;
-const HEX_SPRITE_DATA: [u8; HEX_SPRITE_STRIDE * 16] = [
+const DEFAULT_FRAME_HISTORY_LENGTH: usize = 600;
+const WIDE_SPRITE_BYTES: usize = 32;
+const RPL_FLAG_COUNT: usize = 16;
+pub const AUDIO_PATTERN_BYTES: usize = 16;
+// Plays the pattern at 4000 bits a second.
+const DEFAULT_PITCH: u8 = 64;
+// SCHIP only saves and restores V0 to V7, whatever X is given.
+const LAST_RPL_REGISTER: GeneralRegister = GeneralRegister::V7;
+pub const HEX_SPRITE_STRIDE: usize = 5;
+pub const HEX_SPRITE_DATA: [u8; HEX_SPRITE_STRIDE * 16] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
     0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -31,11 +51,34 @@ const HEX_SPRITE_DATA: [u8; HEX_SPRITE_STRIDE * 16] = [
     0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
+// SCHIP's 8x10 digits for FX30, stored straight after the small font.
+pub const HIGH_HEX_SPRITE_STRIDE: usize = 10;
+pub const HIGH_HEX_SPRITE_DATA: [u8; HIGH_HEX_SPRITE_STRIDE * 16] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x3C, 0x7E, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFE, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFE, 0xFC, // B
+    0x3C, 0x7E, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0x7E, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+const HIGH_HEX_SPRITE_START: usize = HEX_SPRITE_DATA.len();
+const FONT_END: usize = HIGH_HEX_SPRITE_START + HIGH_HEX_SPRITE_DATA.len();
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProcessorError {
     ProgramTooLong {
         size: usize,
+        max: usize,
     },
     StackOverflow {
         address: Address,
@@ -48,18 +91,31 @@ pub enum ProcessorError {
     },
     DecodeFailure {
         instruction: instructions::InstructionBytePair,
+        reason: DecodeError,
     },
     KeyOutOfRange {
         key_index: u8,
     },
+    InvalidSaveState {
+        size: usize,
+    },
+    AddressOutOfRange {
+        address: u16,
+    },
+    InvalidProgramStart {
+        start: usize,
+    },
+    HiresWithMappedDisplay {
+        address: Address,
+    },
 }
 
 impl fmt::Display for ProcessorError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let err_msg = match self {
-            ProcessorError::ProgramTooLong { size } => format!(
+            ProcessorError::ProgramTooLong { size, max } => format!(
                 "Can't load program of size {}, max capacity is {}",
-                size, MAX_PROGRAM_BYTES
+                size, max
             ),
             ProcessorError::StackOverflow { address } => format!(
                 "Stack overflow occurred while executing instruction at address: {}",
@@ -73,8 +129,11 @@ impl fmt::Display for ProcessorError {
                 "Memory overrun occurred while executing instruction at address: {}",
                 address
             ),
-            ProcessorError::DecodeFailure { instruction } => {
-                format!("Failed to decode instruction: {}", instruction)
+            ProcessorError::DecodeFailure {
+                instruction,
+                reason,
+            } => {
+                format!("Failed to decode instruction {}: {}", instruction, reason)
             }
             ProcessorError::KeyOutOfRange { key_index } => {
                 format!(
@@ -82,31 +141,369 @@ impl fmt::Display for ProcessorError {
                     key_index, NUM_KEYS
                 )
             }
+            ProcessorError::InvalidSaveState { size } => {
+                format!("Couldn't load a save state of {} bytes", size)
+            }
+            ProcessorError::AddressOutOfRange { address } => {
+                format!("Address {:#06x} is outside of memory", address)
+            }
+            ProcessorError::InvalidProgramStart { start } => {
+                format!("Can't load a program at {:#05x}", start)
+            }
+            ProcessorError::HiresWithMappedDisplay { address } => format!(
+                "Can't switch to hires with the display mapped into memory, at address: {}",
+                address
+            ),
         };
         write!(f, "{}", err_msg)
     }
 }
 
-impl std::error::Error for ProcessorError {}
+impl core::error::Error for ProcessorError {}
+
+// Larger than any valid save state, so a corrupt length can't cause a huge
+// allocation.
+#[cfg(feature = "serde")]
+const SAVE_STATE_LIMIT: u64 = 0x20000;
+
+#[cfg(feature = "serde")]
+fn save_state_options() -> impl Options {
+    bincode::DefaultOptions::new().with_limit(SAVE_STATE_LIMIT)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutedInstruction {
+    pub address: Address,
+    pub instruction: Instruction,
+}
+
+impl ExecutedInstruction {
+    pub fn outcome(&self) -> StepOutcome {
+        match self.instruction {
+            Instruction::Exit => StepOutcome::Exited,
+            _ => StepOutcome::Running,
+        }
+    }
+}
+
+impl fmt::Display for ExecutedInstruction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}: {}", self.address, self.instruction)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    Running,
+    // The program ran SCHIP's 00FD. Further steps stay on the exit instruction.
+    Exited,
+    // The program jumped to the jump itself, which is how most programs end.
+    // Nothing but the timers changes until something outside the program
+    // does, so there's no point running it flat out.
+    Idle,
+    // A 0x0000 was fetched under the halt_on_zero_opcode quirk. Nothing runs
+    // until the processor is reset or given a new program.
+    Halted,
+    // The instruction at a breakpoint is next, and hasn't run yet. Stepping
+    // again runs it.
+    Breakpoint { address: Address },
+    // The instruction just run wrote to a watched byte of memory.
+    Watchpoint { address: u16 },
+}
+
+impl ProcessorError {
+    pub fn category(&self) -> &'static str {
+        match self {
+            ProcessorError::ProgramTooLong { .. } => "Program too long",
+            ProcessorError::StackOverflow { .. } => "Stack overflow",
+            ProcessorError::StackUnderflow { .. } => "Stack underflow",
+            ProcessorError::MemoryOverrun { .. } => "Memory overrun",
+            ProcessorError::DecodeFailure { .. } => "Invalid instruction",
+            ProcessorError::KeyOutOfRange { .. } => "Key out of range",
+            ProcessorError::InvalidSaveState { .. } => "Invalid save state",
+            ProcessorError::AddressOutOfRange { .. } => "Address out of range",
+            ProcessorError::InvalidProgramStart { .. } => "Invalid program start",
+            ProcessorError::HiresWithMappedDisplay { .. } => "Hires unavailable",
+        }
+    }
+
+    // A longer, user-facing explanation of what usually causes the error.
+    pub fn explanation(&self) -> String {
+        match self {
+            ProcessorError::ProgramTooLong { max, .. } => format!(
+                "The program doesn't fit in memory. CHIP-8 programs usually start at {:#05x}, and from where this one is loaded it can be at most {} bytes long.",
+                PROGRAM_START, max
+            ),
+            ProcessorError::StackOverflow { .. } => format!(
+                "The program called subroutines more than {} levels deep without returning.",
+                STACK_SIZE
+            ),
+            ProcessorError::StackUnderflow { .. } => {
+                "The program returned from a subroutine when no subroutine was running.".to_string()
+            }
+            ProcessorError::MemoryOverrun { .. } => {
+                "The program tried to read or write past the end of memory, usually because I was set too high.".to_string()
+            }
+            ProcessorError::DecodeFailure { .. } => {
+                "The program counter reached bytes that aren't a valid instruction, which usually means the program jumped into data or the ROM isn't a CHIP-8 program.".to_string()
+            }
+            ProcessorError::KeyOutOfRange { .. } => format!(
+                "The program checked a key outside of the {} keys on the CHIP-8 keypad.",
+                NUM_KEYS
+            ),
+            ProcessorError::InvalidSaveState { .. } => {
+                "The save state is corrupt, or was saved by an interpreter with a different display size.".to_string()
+            }
+            ProcessorError::AddressOutOfRange { .. } => format!(
+                "Addresses go up to {:#05x}, or {:#06x} in XO-CHIP mode.",
+                MEMORY_SIZE_BYTES - 1,
+                XO_CHIP_MEMORY_SIZE_BYTES - 1
+            ),
+            ProcessorError::InvalidProgramStart { .. } => format!(
+                "Programs have to start after the font, at {:#05x} or above, and below {:#05x} where the program counter can reach them.",
+                FONT_END, MEMORY_SIZE_BYTES
+            ),
+            ProcessorError::HiresWithMappedDisplay { .. } => {
+                "The memory_mapped_display quirk only has room for the lores display at the end of memory, as on the COSMAC VIP. The hires display would overwrite the program, so SUPER-CHIP programs need the quirk turned off.".to_string()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessorWarning {
+    VfClobbered {
+        written_at: Address,
+        clobbered_at: Address,
+    },
+    InvalidInstructionSkipped {
+        address: Address,
+        instruction: instructions::InstructionBytePair,
+        reason: DecodeError,
+    },
+}
+
+impl fmt::Display for ProcessorWarning {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ProcessorWarning::VfClobbered {
+                written_at,
+                clobbered_at,
+            } => write!(
+                f,
+                "VF was loaded at address {} but overwritten by a flag at address {} before being read",
+                written_at, clobbered_at
+            ),
+            ProcessorWarning::InvalidInstructionSkipped {
+                address,
+                instruction,
+                reason,
+            } => write!(
+                f,
+                "Skipped invalid instruction {} at address {}: {}",
+                instruction, address, reason
+            ),
+        }
+    }
+}
+
+// Where things live in memory, for labelling memory dumps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryMap {
+    pub font: Range<usize>,
+    pub large_font: Range<usize>,
+    // Unused by programs, this held the interpreter itself on the COSMAC VIP.
+    pub reserved: Range<usize>,
+    pub program: Range<usize>,
+    // Only mapped with the memory_mapped_display quirk, overlapping the end of
+    // the program region.
+    pub display: Option<Range<usize>>,
+    pub total_size: usize,
+}
+
+pub const REGISTER_CSV_HEADER: &str =
+    "frame,pc,i,v0,v1,v2,v3,v4,v5,v6,v7,v8,v9,va,vb,vc,vd,ve,vf,delay,sound";
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Config {
-    display_width: usize,
-    display_height: usize,
+    pub display_width: usize,
+    pub display_height: usize,
+    // XO-CHIP's 64KB of memory, with I able to address all of it through
+    // F000 NNNN. The program counter stays within the first 4KB.
+    pub xo_chip: bool,
+    // Step over words that don't decode, with a warning, rather than failing.
+    // Helps with corrupt ROMs and self-modifying code that misfires.
+    pub skip_invalid_instructions: bool,
+    // Where the program is loaded and starts running. Some ETI-660 programs
+    // expect 0x600.
+    pub program_start: usize,
+    // Replaces the built-in 0-F font, in the same layout as HEX_SPRITE_DATA.
+    pub font: Option<[u8; HEX_SPRITE_DATA.len()]>,
+}
+
+impl Config {
+    pub fn new(display_width: usize, display_height: usize) -> Self {
+        Config {
+            display_width,
+            display_height,
+            xo_chip: false,
+            skip_invalid_instructions: false,
+            program_start: PROGRAM_START,
+            font: None,
+        }
+    }
+
+    fn memory_size(&self) -> usize {
+        if self.xo_chip {
+            XO_CHIP_MEMORY_SIZE_BYTES
+        } else {
+            MEMORY_SIZE_BYTES
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::new(64, 32)
+    }
+}
+
+// Behaviours that differ between CHIP-8 interpreters. The defaults match the
+// original COSMAC VIP interpreter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    // 8XY6/8XYE shift VY into VX, as on the COSMAC VIP, rather than shifting VX
+    // in place.
+    pub shift_uses_vy: bool,
+    // FX55/FX65 leave I pointing just past the last register stored or loaded,
+    // as on the COSMAC VIP, rather than leaving it unchanged.
+    pub increment_i_on_store: bool,
+    // BNNN jumps to XNN + VX, where X is the top nibble of the address, as on
+    // SCHIP, rather than to NNN + V0.
+    pub jump_uses_vx: bool,
+    // 8XY1/8XY2/8XY3 clear VF afterwards, a side effect of how the COSMAC VIP
+    // ran them.
+    pub reset_vf_on_logic: bool,
+    // Sprites are cut off at the right and bottom edges rather than wrapping
+    // round to the opposite edge. The starting position wraps either way.
+    pub clip_sprites: bool,
+    // Mirror the display into the last bytes of memory, like the COSMAC VIP's
+    // display RAM at 0xF00-0xFFF. Writes there show up on screen and draws show
+    // up in memory. See Display::to_packed_bytes for the layout. Only the
+    // lores display fits, so switching to hires fails while it's on.
+    pub memory_mapped_display: bool,
+    // Sequential reads and writes from I (FX33, FX55, FX65 and DXYN) wrap from
+    // the top of memory back to 0x000 instead of failing with MemoryOverrun.
+    pub wrap_memory: bool,
+    // A fetched 0x0000 halts the program instead of being ignored as SYS 0x000,
+    // for toolchains that mark the end of a program with zeroed memory.
+    pub halt_on_zero_opcode: bool,
+    // 0NNN machine code calls are invalid instructions instead of being
+    // ignored. Modern programs never mean to use them, so one usually means the
+    // program has jumped somewhere it shouldn't.
+    pub sys_is_invalid: bool,
+    // DXYN waits for the next 60Hz timer tick before drawing, like the COSMAC
+    // VIP waiting for the vertical blank, so there's at most one draw a frame.
+    pub display_wait: bool,
+    // FX1E sets VF when I goes past 0xFFF, and clears it otherwise, as on the
+    // Amiga interpreter that Spacefight 2091! relies on. I wraps either way.
+    pub add_i_sets_vf_on_overflow: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Quirks {
+            shift_uses_vy: true,
+            increment_i_on_store: true,
+            jump_uses_vx: false,
+            reset_vf_on_logic: true,
+            clip_sprites: true,
+            memory_mapped_display: false,
+            wrap_memory: false,
+            halt_on_zero_opcode: false,
+            sys_is_invalid: false,
+            display_wait: false,
+            add_i_sets_vf_on_overflow: false,
+        }
+    }
+}
+
+impl Quirks {
+    // SUPER-CHIP 1.1 on the HP 48, which most SCHIP games are written for.
+    pub fn super_chip() -> Self {
+        Quirks {
+            shift_uses_vy: false,
+            increment_i_on_store: false,
+            jump_uses_vx: true,
+            reset_vf_on_logic: false,
+            ..Quirks::default()
+        }
+    }
+
+    // Octo, which XO-CHIP games are written for. Sprites wrap round the edges.
+    pub fn xo_chip() -> Self {
+        Quirks {
+            reset_vf_on_logic: false,
+            clip_sprites: false,
+            ..Quirks::default()
+        }
+    }
+}
+
+enum DisplaySync {
+    None,
+    DisplayToMemory,
+    MemoryToDisplay,
 }
 
-const DEFAULT_CONFIG: Config = Config {
-    display_width: 64,
-    display_height: 32,
-};
+impl DisplaySync {
+    fn for_instruction(instruction: &Instruction) -> DisplaySync {
+        match instruction {
+            Instruction::Clear
+            | Instruction::LowResolution
+            | Instruction::HighResolution
+            | Instruction::ScrollDown { .. }
+            | Instruction::ScrollRight
+            | Instruction::ScrollLeft
+            | Instruction::Draw { .. } => DisplaySync::DisplayToMemory,
+            Instruction::StoreRegisterRangeAtI { .. }
+            | Instruction::StoreRegisterRange { .. }
+            | Instruction::LoadBcd { .. } => DisplaySync::MemoryToDisplay,
+            _ => DisplaySync::None,
+        }
+    }
+}
 
+// FX0A completes when a key is pressed and then released. The first key
+// pressed while waiting is latched, and only its release counts.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct AwaitingKey {
-    register: GeneralRegister,
-    pressed: bool,
+    pressed: Option<usize>,
+    released: bool,
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct FrameSnapshot {
+    memory: Vec<u8>,
+    registers: Registers,
+    stack: [Address; STACK_SIZE],
+    program_counter: Address,
+    stack_pointer: usize,
+    display: Display,
+    awaiting_key: Option<AwaitingKey>,
+    halted: bool,
+    vblank: bool,
+    rpl_flags: [u8; RPL_FLAG_COUNT],
+    audio_pattern: [u8; AUDIO_PATTERN_BYTES],
+    pitch: u8,
 }
 
 pub struct Processor {
-    memory: [u8; MEMORY_SIZE_BYTES],
+    memory: Vec<u8>,
+    // Memory as it was when the program was loaded, for reset to go back to.
+    initial_memory: Vec<u8>,
     registers: Registers,
     stack: [Address; STACK_SIZE],
     program_counter: Address,
@@ -114,6 +511,86 @@ pub struct Processor {
     display: Display,
     keys: Keys,
     awaiting_key: Option<AwaitingKey>,
+    halted: bool,
+    idle: bool,
+    // Set by each timer tick, and used up by a draw under the display_wait
+    // quirk.
+    vblank: bool,
+    // The HP48's RPL user flags, which SCHIP programs use to keep high scores.
+    // Like on the calculator, they survive loading another program.
+    rpl_flags: [u8; RPL_FLAG_COUNT],
+    // XO-CHIP's one-bit audio, played a bit at a time while the sound timer
+    // runs.
+    audio_pattern: [u8; AUDIO_PATTERN_BYTES],
+    pitch: u8,
+    frame_history: VecDeque<FrameSnapshot>,
+    frame_history_length: usize,
+    warn_vf_clobber: bool,
+    unread_vf_load: Option<Address>,
+    warnings: Vec<ProcessorWarning>,
+    quirks: Quirks,
+    xo_chip: bool,
+    skip_invalid_instructions: bool,
+    program_start: usize,
+    font: [u8; HEX_SPRITE_DATA.len()],
+    rng: StdRng,
+    breakpoints: BTreeSet<Address>,
+    watchpoints: BTreeSet<u16>,
+    // The breakpoint step last stopped at, so later steps run the instruction
+    // there rather than stopping again. It's kept until the program counter
+    // moves on, as instructions like FX0A run again in place while they wait.
+    stopped_at: Option<Address>,
+    watchpoint_hit: Option<u16>,
+    #[cfg(feature = "profiling")]
+    profile: Profile,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Processor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.snapshot().serialize(serializer)
+    }
+}
+
+// A deserialized processor has the default quirks and display size, and no
+// program to reset to. It's in XO-CHIP mode if the saved memory is XO-CHIP's.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Processor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let snapshot = FrameSnapshot::deserialize(deserializer)?;
+        let config = Config {
+            xo_chip: snapshot.memory.len() == XO_CHIP_MEMORY_SIZE_BYTES,
+            ..Config::default()
+        };
+        let mut processor = Processor::new_with_config(Vec::new(), config, Quirks::default())
+            .map_err(D::Error::custom)?;
+        if !processor.accepts_snapshot(&snapshot) {
+            return Err(D::Error::custom(
+                "the saved state doesn't fit a CHIP-8 processor",
+            ));
+        }
+        processor.restore(snapshot);
+        Ok(processor)
+    }
+}
+
+// 64-bit FNV-1a. Unlike std's DefaultHasher, it gives the same hash on every
+// platform and toolchain, so hashes can be compared between machines.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xCBF2_9CE4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01B3);
+        }
+    }
 }
 
 fn to_bcd(byte: u8) -> [u8; 3] {
@@ -135,157 +612,987 @@ fn to_bcd(byte: u8) -> [u8; 3] {
     ]
 }
 
+// VX to VY for 5XY2 and 5XY3, counting down when VY comes first.
+fn register_range(first: GeneralRegister, last: GeneralRegister) -> Vec<GeneralRegister> {
+    let (first, last) = (first as u8, last as u8);
+    let mut registers: Vec<_> = GeneralRegister::iter()
+        .filter(|&reg| (first.min(last)..=first.max(last)).contains(&(reg as u8)))
+        .collect();
+    if first > last {
+        registers.reverse();
+    }
+    registers
+}
+
+#[cfg(feature = "std")]
+fn initial_rng() -> StdRng {
+    StdRng::from_entropy()
+}
+
+// There's nothing to gather entropy from without std, so CXKK gives the same
+// results every run unless the processor is seeded.
+#[cfg(not(feature = "std"))]
+fn initial_rng() -> StdRng {
+    StdRng::seed_from_u64(0)
+}
+
+// The program has to start past the font and where the program counter can
+// reach, though in XO-CHIP mode it can run on past 4KB.
+fn initial_memory(
+    program_bytes: &[u8],
+    memory_size: usize,
+    program_start: usize,
+    font: &[u8; HEX_SPRITE_DATA.len()],
+) -> Result<Vec<u8>, ProcessorError> {
+    if !(FONT_END..MEMORY_SIZE_BYTES).contains(&program_start) {
+        return Err(ProcessorError::InvalidProgramStart {
+            start: program_start,
+        });
+    }
+    let max = memory_size - program_start;
+    if program_bytes.len() > max {
+        return Err(ProcessorError::ProgramTooLong {
+            size: program_bytes.len(),
+            max,
+        });
+    }
+
+    let mut memory = vec![0_u8; memory_size];
+    memory[..HEX_SPRITE_DATA.len()].copy_from_slice(font);
+    memory[HIGH_HEX_SPRITE_START..FONT_END].copy_from_slice(&HIGH_HEX_SPRITE_DATA);
+    memory[program_start..program_start + program_bytes.len()].copy_from_slice(program_bytes);
+    Ok(memory)
+}
+
+// A clone starts with no rewind history of its own. Copying up to
+// frame_history_length snapshots would make cloning anything but cheap.
+impl Clone for Processor {
+    fn clone(&self) -> Self {
+        Processor {
+            memory: self.memory.clone(),
+            initial_memory: self.initial_memory.clone(),
+            registers: self.registers.clone(),
+            stack: self.stack,
+            program_counter: self.program_counter,
+            stack_pointer: self.stack_pointer,
+            display: self.display.clone(),
+            keys: self.keys.clone(),
+            awaiting_key: self.awaiting_key,
+            halted: self.halted,
+            idle: self.idle,
+            vblank: self.vblank,
+            rpl_flags: self.rpl_flags,
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            frame_history: VecDeque::new(),
+            frame_history_length: self.frame_history_length,
+            warn_vf_clobber: self.warn_vf_clobber,
+            unread_vf_load: self.unread_vf_load,
+            warnings: self.warnings.clone(),
+            quirks: self.quirks,
+            xo_chip: self.xo_chip,
+            skip_invalid_instructions: self.skip_invalid_instructions,
+            program_start: self.program_start,
+            font: self.font,
+            rng: self.rng.clone(),
+            breakpoints: self.breakpoints.clone(),
+            watchpoints: self.watchpoints.clone(),
+            stopped_at: self.stopped_at,
+            watchpoint_hit: self.watchpoint_hit,
+            #[cfg(feature = "profiling")]
+            profile: self.profile.clone(),
+        }
+    }
+}
+
 impl Processor {
     pub fn new(program_bytes: Vec<u8>) -> Result<Self, ProcessorError> {
-        Self::new_with_config(program_bytes, DEFAULT_CONFIG)
+        Self::new_with_config(program_bytes, Config::default(), Quirks::default())
     }
-    pub fn new_with_config(program_bytes: Vec<u8>, config: Config) -> Result<Self, ProcessorError> {
-        if program_bytes.len() > MAX_PROGRAM_BYTES {
-            return Err(ProcessorError::ProgramTooLong {
-                size: program_bytes.len(),
-            });
-        }
 
-        let mut memory = [0_u8; MEMORY_SIZE_BYTES];
-        memory[..HEX_SPRITE_DATA.len()].copy_from_slice(&HEX_SPRITE_DATA);
-        memory[PROGRAM_START..PROGRAM_START + program_bytes.len()].copy_from_slice(&program_bytes);
+    // Random numbers come from `seed`, so CXKK gives the same results every run.
+    pub fn new_with_seed(program_bytes: Vec<u8>, seed: u64) -> Result<Self, ProcessorError> {
+        let mut processor = Self::new(program_bytes)?;
+        processor.seed_rng(seed);
+        Ok(processor)
+    }
 
-        Ok(Processor {
+    pub fn new_with_config(
+        program_bytes: Vec<u8>,
+        config: Config,
+        quirks: Quirks,
+    ) -> Result<Self, ProcessorError> {
+        let font = config.font.unwrap_or(HEX_SPRITE_DATA);
+        let memory = initial_memory(
+            &program_bytes,
+            config.memory_size(),
+            config.program_start,
+            &font,
+        )?;
+        let mut processor = Processor {
+            initial_memory: memory.clone(),
             memory,
             registers: Registers::new(),
             stack: [Address::from(0); STACK_SIZE],
-            program_counter: Address::from(PROGRAM_START as u16),
+            program_counter: Address::from(config.program_start as u16),
             stack_pointer: 0,
             display: Display::new(config.display_width, config.display_height),
             keys: Keys::new(),
             awaiting_key: None,
-        })
+            halted: false,
+            idle: false,
+            vblank: false,
+            rpl_flags: [0; RPL_FLAG_COUNT],
+            audio_pattern: [0; AUDIO_PATTERN_BYTES],
+            pitch: DEFAULT_PITCH,
+            frame_history: VecDeque::new(),
+            frame_history_length: DEFAULT_FRAME_HISTORY_LENGTH,
+            warn_vf_clobber: false,
+            unread_vf_load: None,
+            warnings: Vec::new(),
+            quirks,
+            xo_chip: config.xo_chip,
+            skip_invalid_instructions: config.skip_invalid_instructions,
+            program_start: config.program_start,
+            font,
+            rng: initial_rng(),
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+            stopped_at: None,
+            watchpoint_hit: None,
+            #[cfg(feature = "profiling")]
+            profile: Profile::default(),
+        };
+        if quirks.memory_mapped_display {
+            processor.store_display_in_memory();
+        }
+        Ok(processor)
     }
 
-    pub fn step(&mut self) -> Result<(), ProcessorError> {
-        if self.awaiting_key.is_some() {
-            std::thread::sleep(std::time::Duration::from_micros(100));
-            return Ok(());
-        }
+    // Replaces the running program, then resets.
+    pub fn load_program(&mut self, program_bytes: Vec<u8>) -> Result<(), ProcessorError> {
+        self.initial_memory = initial_memory(
+            &program_bytes,
+            self.memory.len(),
+            self.program_start,
+            &self.font,
+        )?;
+        self.reset();
+        Ok(())
+    }
 
-        let instruction_bytes = self.fetch();
+    // Restarts the loaded program from its power-on state, with memory as it was
+    // when the program was loaded and the display back in lores. The display
+    // size, quirks, XO-CHIP mode, warning settings, RPL flags, breakpoints and
+    // watchpoints are kept.
+    pub fn reset(&mut self) {
+        self.memory.clone_from(&self.initial_memory);
+        self.registers = Registers::new();
+        self.stack = [Address::from(0); STACK_SIZE];
+        self.program_counter = Address::from(self.program_start as u16);
+        self.stack_pointer = 0;
+        self.display.reset();
+        self.keys = Keys::new();
+        self.awaiting_key = None;
+        self.halted = false;
+        self.idle = false;
+        self.vblank = false;
+        self.audio_pattern = [0; AUDIO_PATTERN_BYTES];
+        self.pitch = DEFAULT_PITCH;
+        self.frame_history.clear();
+        self.unread_vf_load = None;
+        self.warnings.clear();
+        self.stopped_at = None;
+        self.watchpoint_hit = None;
+        #[cfg(feature = "profiling")]
+        self.profile.clear();
+        if self.quirks.memory_mapped_display {
+            self.store_display_in_memory();
+        }
+    }
 
-        let instruction =
-            instructions::decode(instruction_bytes).ok_or(ProcessorError::DecodeFailure {
-                instruction: instruction_bytes,
-            })?;
+    pub fn step(&mut self) -> Result<StepOutcome, ProcessorError> {
+        let address = self.program_counter;
+        if !self.halted && self.breakpoints.contains(&address) && self.stopped_at != Some(address) {
+            self.stopped_at = Some(address);
+            return Ok(StepOutcome::Breakpoint { address });
+        }
 
-        self.execute(instruction)?;
+        let outcome = self
+            .step_traced()?
+            .map_or(StepOutcome::Running, |executed| executed.outcome());
+        if let Some(address) = self.watchpoint_hit.take() {
+            Ok(StepOutcome::Watchpoint { address })
+        } else if self.halted {
+            Ok(StepOutcome::Halted)
+        } else if outcome == StepOutcome::Running && self.idle {
+            Ok(StepOutcome::Idle)
+        } else {
+            Ok(outcome)
+        }
+    }
 
+    // Steps up to `cycles` times with no timers or input, stopping early once
+    // the program exits, halts or idles.
+    pub fn run_cycles(&mut self, cycles: usize) -> Result<(), ProcessorError> {
+        for _ in 0..cycles {
+            if self.step()? != StepOutcome::Running {
+                break;
+            }
+        }
         Ok(())
     }
 
-    pub fn get_display_buffer(&mut self) -> Option<&Grid<Pixel>> {
-        self.display.get_display_buffer()
+    // Like step, but returns the instruction that ran and where it was fetched
+    // from. Nothing runs while halted, on the 0x0000 that halts, on a draw
+    // waiting for the next tick, or on an invalid word that's skipped.
+    // Breakpoints and watchpoints don't stop it.
+    pub fn step_traced(&mut self) -> Result<Option<ExecutedInstruction>, ProcessorError> {
+        if self.halted {
+            return Ok(None);
+        }
+        self.watchpoint_hit = None;
+
+        #[cfg(feature = "profiling")]
+        let (address, started) = (self.program_counter, std::time::Instant::now());
+
+        let result = self.step_instruction();
+
+        #[cfg(feature = "profiling")]
+        {
+            self.profile.record(address, started.elapsed());
+            if let Ok(Some(executed)) = &result {
+                self.profile.record_instruction(&executed.instruction);
+            }
+        }
+
+        if self.stopped_at != Some(self.program_counter) {
+            self.stopped_at = None;
+        }
+        result
     }
 
-    pub fn add_key_event(&mut self, key: usize, status: KeyStatus) {
-        if let Some(wait_key) = &self.awaiting_key.clone() {
-            if wait_key.pressed && status == KeyStatus::Released {
-                self.awaiting_key = None;
-                self.registers.set_general(wait_key.register, key as u8);
+    fn step_instruction(&mut self) -> Result<Option<ExecutedInstruction>, ProcessorError> {
+        let address = self.program_counter;
+        let instruction_bytes = self.fetch();
+        self.idle = false;
+
+        if self.quirks.halt_on_zero_opcode && instruction_bytes.0 == 0x0000 {
+            self.halted = true;
+            return Ok(None);
+        }
+
+        let instruction = match self.decode(instruction_bytes) {
+            Ok(instruction) => instruction,
+            Err(reason) if !self.skip_invalid_instructions => {
+                return Err(ProcessorError::DecodeFailure {
+                    instruction: instruction_bytes,
+                    reason,
+                });
             }
-            if !wait_key.pressed && status == KeyStatus::Pressed {
-                self.awaiting_key.as_mut().unwrap().pressed = true;
+            Err(reason) => {
+                self.warnings
+                    .push(ProcessorWarning::InvalidInstructionSkipped {
+                        address,
+                        instruction: instruction_bytes,
+                        reason,
+                    });
+                self.pc_advance();
+                return Ok(None);
             }
+        };
+        let executed = ExecutedInstruction {
+            address,
+            instruction: instruction.clone(),
+        };
+
+        if instruction == Instruction::Exit {
+            return Ok(Some(executed));
         }
 
-        self.keys.input(key, status);
+        // stay on the draw until the next tick
+        if matches!(instruction, Instruction::Draw { .. })
+            && self.quirks.display_wait
+            && !core::mem::take(&mut self.vblank)
+        {
+            return Ok(None);
+        }
+
+        if self.warn_vf_clobber {
+            self.track_vf_load(&instruction);
+        }
+
+        let display_sync = if self.quirks.memory_mapped_display {
+            DisplaySync::for_instruction(&instruction)
+        } else {
+            DisplaySync::None
+        };
+
+        let is_jump = matches!(
+            instruction,
+            Instruction::Jump { .. } | Instruction::JumpPlusV0 { .. }
+        );
+        self.execute(instruction)?;
+        self.idle = is_jump && self.program_counter == address;
+
+        match display_sync {
+            DisplaySync::DisplayToMemory => self.store_display_in_memory(),
+            DisplaySync::MemoryToDisplay => self.load_display_from_memory(),
+            DisplaySync::None => {}
+        }
+
+        Ok(Some(executed))
     }
 
-    pub fn decrement_timers(&mut self) {
-        self.registers.decrement_delay();
-        self.registers.decrement_sound();
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+        if self.quirks.memory_mapped_display {
+            self.map_display();
+        }
     }
 
-    fn fetch(&self) -> instructions::InstructionBytePair {
-        let instruction_index = u16::from(self.program_counter) as usize;
-        let instruction_bytes: [u8; 2] =
-            core::array::from_fn(|idx| self.memory[instruction_index + idx]);
-        instructions::InstructionBytePair(u16::from_be_bytes(instruction_bytes))
+    #[cfg(feature = "profiling")]
+    pub fn profile(&self) -> &Profile {
+        &self.profile
     }
 
-    fn pc_skip(&mut self) {
-        self.program_counter.increment(4);
+    #[cfg(feature = "profiling")]
+    pub fn instruction_counts(&self) -> &BTreeMap<&'static str, u64> {
+        self.profile.instruction_counts()
     }
 
-    fn pc_advance(&mut self) {
-        self.program_counter.increment(2);
+    // step stops before running the instruction at `address`.
+    pub fn add_breakpoint(&mut self, address: u16) -> Result<(), ProcessorError> {
+        self.check_address(address)?;
+        self.breakpoints.insert(Address::extended(address));
+        Ok(())
     }
 
-    fn execute(&mut self, instruction: Instruction) -> Result<(), ProcessorError> {
-        match instruction {
-            Instruction::Sys { .. } => {
-                self.pc_advance();
-            }
+    // step stops after an instruction writes to the byte at `address`, whether
+    // or not the value changes.
+    pub fn add_watchpoint(&mut self, address: u16) -> Result<(), ProcessorError> {
+        self.check_address(address)?;
+        self.watchpoints.insert(address);
+        Ok(())
+    }
 
-            Instruction::Clear => {
-                self.display.clear();
-                self.pc_advance();
-            }
+    // Only XO-CHIP has memory past 0xFFF, so anything higher would otherwise be
+    // masked to some other address.
+    fn check_address(&self, address: u16) -> Result<(), ProcessorError> {
+        if (address as usize) < self.memory.len() {
+            Ok(())
+        } else {
+            Err(ProcessorError::AddressOutOfRange { address })
+        }
+    }
 
-            Instruction::Return => {
-                if self.stack_pointer == 0 {
-                    return Err(ProcessorError::StackUnderflow {
-                        address: self.program_counter,
-                    });
-                }
-                self.program_counter = self.stack[self.stack_pointer];
-                self.stack_pointer -= 1;
-                self.pc_advance();
-            }
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
 
-            Instruction::Jump { addr } => self.program_counter = addr,
+    pub fn set_warn_vf_clobber(&mut self, enabled: bool) {
+        self.warn_vf_clobber = enabled;
+        self.unread_vf_load = None;
+    }
 
-            Instruction::Call { addr } => {
-                self.stack_pointer += 1;
-                if self.stack_pointer >= STACK_SIZE {
-                    return Err(ProcessorError::StackOverflow {
-                        address: self.program_counter,
-                    });
-                }
+    pub fn take_warnings(&mut self) -> Vec<ProcessorWarning> {
+        core::mem::take(&mut self.warnings)
+    }
 
-                self.stack[self.stack_pointer] = self.program_counter;
-                self.program_counter = addr;
-            }
+    pub fn program_counter(&self) -> Address {
+        self.program_counter
+    }
 
-            Instruction::SkipIfEqByte { reg, value } => {
-                if self.registers.get_general(reg) == value {
-                    self.pc_skip();
-                } else {
-                    self.pc_advance();
-                }
-            }
+    pub fn stack_pointer(&self) -> usize {
+        self.stack_pointer
+    }
 
-            Instruction::SkipIfNeqByte { reg, value } => {
-                if self.registers.get_general(reg) != value {
-                    self.pc_skip();
-                } else {
-                    self.pc_advance();
-                }
-            }
+    pub fn i_register(&self) -> Address {
+        self.registers.i
+    }
 
-            Instruction::SkipIfEqReg { lhs, rhs } => {
-                if self.registers.get_general(lhs) == self.registers.get_general(rhs) {
-                    self.pc_skip();
-                } else {
-                    self.pc_advance();
-                }
-            }
+    pub fn general_register(&self, register: GeneralRegister) -> u8 {
+        self.registers.get_general(register)
+    }
 
-            Instruction::LoadValue { dest, value } => {
-                self.registers.set_general(dest, value);
-                self.pc_advance();
-            }
+    // The instruction at the program counter, which the next step executes
+    // unless the processor is halted.
+    pub fn next_instruction(&self) -> Option<Instruction> {
+        self.decode(self.fetch()).ok()
+    }
 
-            Instruction::AddValue { dest, value } => {
-                let current = self.registers.get_general(dest);
-                let (result, _) = current.overflowing_add(value);
+    pub fn display(&self) -> &Display {
+        &self.display
+    }
+
+    // (width, height) in pixels, which changes as SCHIP programs switch between
+    // lores and hires.
+    pub fn display_dimensions(&self) -> (usize, usize) {
+        (self.display.width(), self.display.height())
+    }
+
+    pub fn selected_planes(&self) -> u8 {
+        self.display.selected_planes()
+    }
+
+    // The bits to play, most significant first, while the sound timer runs.
+    pub fn audio_pattern(&self) -> &[u8; AUDIO_PATTERN_BYTES] {
+        &self.audio_pattern
+    }
+
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    // Bits of the audio pattern played per second at the current pitch.
+    #[cfg(feature = "std")]
+    pub fn audio_bit_rate(&self) -> f64 {
+        4000.0 * 2_f64.powf((self.pitch as f64 - 64.0) / 48.0)
+    }
+
+    pub fn get_display_buffer(&mut self) -> Option<Grid<Pixel>> {
+        self.display.get_display_buffer()
+    }
+
+    pub fn add_key_event(&mut self, key: usize, status: KeyStatus) {
+        if let Some(wait_key) = self.awaiting_key.as_mut() {
+            match (wait_key.pressed, status) {
+                (None, KeyStatus::Pressed) => wait_key.pressed = Some(key),
+                (Some(pressed), KeyStatus::Released) if pressed == key => wait_key.released = true,
+                _ => {}
+            }
+        }
+
+        self.keys.input(key, status);
+    }
+
+    // All of memory as it is now, including anything the program has written.
+    pub fn memory_snapshot(&self) -> &[u8] {
+        &self.memory
+    }
+
+    // Reads outside of memory return 0.
+    pub fn read_memory(&self, address: u16) -> u8 {
+        self.memory.get(address as usize).copied().unwrap_or(0)
+    }
+
+    // The range is clamped to the end of memory, so it may come back shorter
+    // than `len`, or empty if `start` is already past the end.
+    pub fn read_memory_range(&self, start: u16, len: usize) -> &[u8] {
+        let start = (start as usize).min(self.memory.len());
+        let end = start.saturating_add(len).min(self.memory.len());
+        &self.memory[start..end]
+    }
+
+    // Writes outside of memory are ignored.
+    pub fn write_memory(&mut self, address: u16, value: u8) {
+        if let Some(byte) = self.memory.get_mut(address as usize) {
+            *byte = value;
+            if self.quirks.memory_mapped_display {
+                self.load_display_from_memory();
+            }
+        }
+    }
+
+    // One 60Hz tick, counting both timers down by one and stopping at zero.
+    pub fn tick(&mut self) {
+        self.vblank = true;
+        self.registers.tick();
+    }
+
+    pub fn tick_timers(&mut self, ticks: usize) {
+        for _ in 0..ticks {
+            self.tick();
+        }
+    }
+
+    pub fn memory_map(&self) -> MemoryMap {
+        MemoryMap {
+            font: 0..HEX_SPRITE_DATA.len(),
+            large_font: HIGH_HEX_SPRITE_START..FONT_END,
+            reserved: FONT_END..self.program_start,
+            program: self.program_start..self.memory.len(),
+            display: self
+                .quirks
+                .memory_mapped_display
+                .then(|| self.display_region_start()..self.memory.len()),
+            total_size: self.memory.len(),
+        }
+    }
+
+    // Summarises memory, registers, the stack and the display, so two runs can be
+    // checked for identical results.
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+        hasher.write(&self.memory);
+        for reg in GeneralRegister::iter() {
+            hasher.write(&[self.registers.get_general(reg)]);
+        }
+        hasher.write(&u16::from(self.registers.i).to_le_bytes());
+        hasher.write(&[self.registers.delay, self.registers.sound]);
+        for address in &self.stack {
+            hasher.write(&u16::from(*address).to_le_bytes());
+        }
+        hasher.write(&u16::from(self.program_counter).to_le_bytes());
+        hasher.write(&[self.stack_pointer as u8]);
+        hasher.write(&self.display.to_packed_bytes());
+        hasher.0
+    }
+
+    // The registers in hex, eight general registers to a line, then I, PC, SP
+    // and the timers.
+    pub fn register_dump(&self) -> String {
+        let general: Vec<String> = GeneralRegister::iter()
+            .map(|reg| format!("{}={:02x}", reg, self.registers.get_general(reg)))
+            .collect();
+        format!(
+            "{}\n{}\nI={} PC={} SP={} DT={:02x} ST={:02x}",
+            general[..8].join(" "),
+            general[8..].join(" "),
+            self.registers.i,
+            self.program_counter,
+            self.stack_pointer,
+            self.registers.delay,
+            self.registers.sound
+        )
+    }
+
+    // One row matching REGISTER_CSV_HEADER, with every value in decimal.
+    pub fn register_csv_row(&self, frame: usize) -> String {
+        let mut row = format!(
+            "{},{},{}",
+            frame,
+            u16::from(self.program_counter),
+            u16::from(self.registers.i)
+        );
+        for reg in GeneralRegister::iter() {
+            row.push_str(&format!(",{}", self.registers.get_general(reg)));
+        }
+        row.push_str(&format!(
+            ",{},{}",
+            self.registers.delay, self.registers.sound
+        ));
+        row
+    }
+
+    pub fn set_timers(&mut self, delay: u8, sound: u8) {
+        self.registers.delay = delay;
+        self.registers.sound = sound;
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    // Whether the last step was a jump to itself, see `StepOutcome::Idle`.
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    pub fn delay(&self) -> u8 {
+        self.registers.delay
+    }
+
+    pub fn sound(&self) -> u8 {
+        self.registers.sound
+    }
+
+    // How many frames `record_frame` keeps, dropping the oldest first. Zero turns
+    // recording off.
+    pub fn set_frame_history_length(&mut self, frames: usize) {
+        self.frame_history_length = frames;
+        let excess = self.frame_history.len().saturating_sub(frames);
+        self.frame_history.drain(..excess);
+    }
+
+    pub fn record_frame(&mut self) {
+        if self.frame_history_length == 0 {
+            return;
+        }
+        if self.frame_history.len() == self.frame_history_length {
+            self.frame_history.pop_front();
+        }
+
+        let snapshot = self.snapshot();
+        self.frame_history.push_back(snapshot);
+    }
+
+    // Restores the state recorded `frames` frame boundaries ago, so rewinding one
+    // frame returns to the most recent call to `record_frame`. Leaves the processor
+    // untouched and returns false if that much history isn't available.
+    pub fn rewind_frame(&mut self, frames: usize) -> bool {
+        if frames == 0 || frames > self.frame_history.len() {
+            return false;
+        }
+
+        let keep = self.frame_history.len() - frames;
+        let Some(snapshot) = self.frame_history.drain(keep..).next() else {
+            return false;
+        };
+
+        self.restore(snapshot);
+        true
+    }
+
+    // Everything needed to carry on running from this point. Settings such as
+    // quirks aren't included.
+    #[cfg(feature = "serde")]
+    pub fn save_state(&self) -> Vec<u8> {
+        save_state_options()
+            .serialize(&self.snapshot())
+            .expect("A snapshot always fits in the save state limit")
+    }
+
+    // Replaces the running state with one from `save_state`. The state must
+    // come from a processor with the same display size and XO-CHIP mode.
+    #[cfg(feature = "serde")]
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), ProcessorError> {
+        let invalid = ProcessorError::InvalidSaveState { size: bytes.len() };
+        let snapshot: FrameSnapshot = save_state_options()
+            .deserialize(bytes)
+            .map_err(|_| invalid)?;
+        if !self.accepts_snapshot(&snapshot) {
+            return Err(invalid);
+        }
+
+        self.restore(snapshot);
+        if self.quirks.memory_mapped_display {
+            self.map_display();
+        }
+        Ok(())
+    }
+
+    fn snapshot(&self) -> FrameSnapshot {
+        FrameSnapshot {
+            memory: self.memory.clone(),
+            registers: self.registers.clone(),
+            stack: self.stack,
+            program_counter: self.program_counter,
+            stack_pointer: self.stack_pointer,
+            display: self.display.clone(),
+            awaiting_key: self.awaiting_key,
+            halted: self.halted,
+            vblank: self.vblank,
+            rpl_flags: self.rpl_flags,
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+        }
+    }
+
+    fn restore(&mut self, snapshot: FrameSnapshot) {
+        self.memory = snapshot.memory;
+        self.registers = snapshot.registers;
+        self.stack = snapshot.stack;
+        self.program_counter = snapshot.program_counter;
+        self.stack_pointer = snapshot.stack_pointer;
+        self.display = snapshot.display;
+        self.display.mark_dirty();
+        self.awaiting_key = snapshot.awaiting_key;
+        self.halted = snapshot.halted;
+        self.vblank = snapshot.vblank;
+        self.idle = false;
+        self.stopped_at = None;
+        self.rpl_flags = snapshot.rpl_flags;
+        self.audio_pattern = snapshot.audio_pattern;
+        self.pitch = snapshot.pitch;
+    }
+
+    // Rejects saved values that would later index out of bounds.
+    #[cfg(feature = "serde")]
+    fn accepts_snapshot(&self, snapshot: &FrameSnapshot) -> bool {
+        let pc = u16::from(snapshot.program_counter) as usize;
+        snapshot.stack_pointer <= STACK_SIZE
+            && pc < MEMORY_SIZE_BYTES
+            && snapshot.memory.len() == self.memory.len()
+            && snapshot.display.lores_size() == self.display.lores_size()
+            && snapshot
+                .awaiting_key
+                .and_then(|wait_key| wait_key.pressed)
+                .is_none_or(|key| key < NUM_KEYS)
+    }
+
+    fn track_vf_load(&mut self, instruction: &Instruction) {
+        if instruction.reads_register(GeneralRegister::VF) {
+            self.unread_vf_load = None;
+        }
+
+        match instruction {
+            Instruction::LoadValue {
+                dest: GeneralRegister::VF,
+                ..
+            }
+            | Instruction::LoadRegister {
+                dest: GeneralRegister::VF,
+                ..
+            } => self.unread_vf_load = Some(self.program_counter),
+            _ if instruction.sets_vf_flag() => {
+                if let Some(written_at) = self.unread_vf_load.take() {
+                    self.warnings.push(ProcessorWarning::VfClobbered {
+                        written_at,
+                        clobbered_at: self.program_counter,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn display_region_start(&self) -> usize {
+        self.memory.len() - self.display.packed_len()
+    }
+
+    // There's only room for the lores display, so a hires one goes back to
+    // lores before it's mapped.
+    fn map_display(&mut self) {
+        self.display.set_hires(false);
+        self.store_display_in_memory();
+    }
+
+    fn store_display_in_memory(&mut self) {
+        let start = self.display_region_start();
+        self.memory[start..].copy_from_slice(&self.display.to_packed_bytes());
+    }
+
+    fn load_display_from_memory(&mut self) {
+        let start = self.display_region_start();
+        if self.memory[start..] != self.display.to_packed_bytes() {
+            self.display.load_packed_bytes(&self.memory[start..]);
+        }
+    }
+
+    // Reads `len` bytes of sprite data from I. Every draw mode should go through
+    // this so the bounds check lives in one place.
+    fn read_sprite(&self, len: usize) -> Result<Vec<u8>, ProcessorError> {
+        let start = u16::from(self.registers.i) as usize;
+        if self.quirks.wrap_memory {
+            return Ok((start..start + len)
+                .map(|address| self.memory[self.wrap_address(address)])
+                .collect());
+        }
+
+        match start.checked_add(len) {
+            Some(end) if end <= self.memory.len() => Ok(self.memory[start..end].to_vec()),
+            _ => Err(ProcessorError::MemoryOverrun {
+                address: self.program_counter,
+            }),
+        }
+    }
+
+    // Checks that `len` bytes from I fit in memory before any of them are
+    // touched, so an overrun never leaves a partial write behind.
+    fn check_i_range(&self, len: usize) -> Result<(), ProcessorError> {
+        let start = u16::from(self.registers.i) as usize;
+        if self.quirks.wrap_memory || start + len <= self.memory.len() {
+            Ok(())
+        } else {
+            Err(ProcessorError::MemoryOverrun {
+                address: self.program_counter,
+            })
+        }
+    }
+
+    // Every write the program makes goes through here, to trip watchpoints.
+    fn store(&mut self, address: usize, value: u8) {
+        self.memory[address] = value;
+        if self.watchpoints.contains(&(address as u16)) {
+            self.watchpoint_hit.get_or_insert(address as u16);
+        }
+    }
+
+    fn wrap_address(&self, address: usize) -> usize {
+        if self.quirks.wrap_memory {
+            address % self.memory.len()
+        } else {
+            address
+        }
+    }
+
+    fn reset_vf_after_logic(&mut self) {
+        if self.quirks.reset_vf_on_logic {
+            self.registers.set_vf_flag(Flag::Low);
+        }
+    }
+
+    fn increment_i_after_store(&mut self, last: GeneralRegister) {
+        if self.quirks.increment_i_on_store {
+            let next = u16::from(self.registers.i) as usize + last as usize + 1;
+            self.registers.i = self.i_address(next);
+        }
+    }
+
+    // I covers all of XO-CHIP's memory, and wraps within 12 bits otherwise.
+    fn i_address(&self, value: usize) -> Address {
+        if self.xo_chip {
+            Address::extended(value as u16)
+        } else {
+            Address::from(value as u16)
+        }
+    }
+
+    fn shift_operand(&self, dest: GeneralRegister, source: GeneralRegister) -> GeneralRegister {
+        if self.quirks.shift_uses_vy {
+            source
+        } else {
+            dest
+        }
+    }
+
+    fn fetch(&self) -> instructions::InstructionBytePair {
+        self.word_at(u16::from(self.program_counter) as usize)
+    }
+
+    // Words are read from the program counter's 12-bit address space, so a word
+    // at 0xFFF takes its second byte from 0x000.
+    fn word_at(&self, address: usize) -> instructions::InstructionBytePair {
+        let bytes: [u8; 2] =
+            core::array::from_fn(|idx| self.memory[(address + idx) % MEMORY_SIZE_BYTES]);
+        instructions::InstructionBytePair(u16::from_be_bytes(bytes))
+    }
+
+    fn is_long_load(&self, bytes: instructions::InstructionBytePair) -> bool {
+        self.xo_chip && bytes.0 == 0xF000
+    }
+
+    // Decodes the instruction at the program counter that starts with `bytes`,
+    // reading the address that follows F000 in XO-CHIP mode.
+    fn decode(&self, bytes: instructions::InstructionBytePair) -> Result<Instruction, DecodeError> {
+        if self.is_long_load(bytes) {
+            let address = self.word_at(u16::from(self.program_counter) as usize + 2);
+            return Ok(Instruction::LoadLongI {
+                addr: Address::extended(address.0),
+            });
+        }
+        match instructions::decode(bytes)? {
+            Instruction::Sys { .. } if self.quirks.sys_is_invalid => Err(DecodeError::SysDisabled),
+            instruction
+                if instruction.instruction_set() == InstructionSet::XoChip && !self.xo_chip =>
+            {
+                Err(DecodeError::XoChipDisabled)
+            }
+            instruction => Ok(instruction),
+        }
+    }
+
+    // Skips the next instruction, all four bytes of it if it's an F000 NNNN.
+    fn pc_skip(&mut self) {
+        let next = self.word_at(u16::from(self.program_counter) as usize + 2);
+        let skipped = if self.is_long_load(next) { 6 } else { 4 };
+        self.program_counter.increment(skipped);
+    }
+
+    fn pc_advance(&mut self) {
+        self.program_counter.increment(2);
+    }
+
+    fn execute(&mut self, instruction: Instruction) -> Result<(), ProcessorError> {
+        match instruction {
+            Instruction::Sys { .. } => {
+                self.pc_advance();
+            }
+
+            Instruction::Clear => {
+                self.display.clear();
+                self.pc_advance();
+            }
+
+            Instruction::LowResolution => {
+                self.display.set_hires(false);
+                self.pc_advance();
+            }
+
+            Instruction::HighResolution => {
+                if self.quirks.memory_mapped_display {
+                    return Err(ProcessorError::HiresWithMappedDisplay {
+                        address: self.program_counter,
+                    });
+                }
+                self.display.set_hires(true);
+                self.pc_advance();
+            }
+
+            Instruction::SelectPlanes { mask } => {
+                self.display.select_planes(mask as u8);
+                self.pc_advance();
+            }
+
+            // Handled in step_instruction, as it ends the program rather than
+            // running an instruction.
+            Instruction::Exit => {}
+
+            Instruction::ScrollDown { rows } => {
+                self.display.scroll_down(rows as usize);
+                self.pc_advance();
+            }
+
+            Instruction::ScrollRight => {
+                self.display.scroll_right();
+                self.pc_advance();
+            }
+
+            Instruction::ScrollLeft => {
+                self.display.scroll_left();
+                self.pc_advance();
+            }
+
+            Instruction::Return => {
+                if self.stack_pointer == 0 {
+                    return Err(ProcessorError::StackUnderflow {
+                        address: self.program_counter,
+                    });
+                }
+                self.stack_pointer -= 1;
+                self.program_counter = self.stack[self.stack_pointer];
+                self.pc_advance();
+            }
+
+            Instruction::Jump { addr } => self.program_counter = addr,
+
+            // stack_pointer is the number of return addresses on the stack, so
+            // it's also the index of the next free slot.
+            Instruction::Call { addr } => {
+                if self.stack_pointer == STACK_SIZE {
+                    return Err(ProcessorError::StackOverflow {
+                        address: self.program_counter,
+                    });
+                }
+
+                self.stack[self.stack_pointer] = self.program_counter;
+                self.stack_pointer += 1;
+                self.program_counter = addr;
+            }
+
+            Instruction::SkipIfEqByte { reg, value } => {
+                if self.registers.get_general(reg) == value {
+                    self.pc_skip();
+                } else {
+                    self.pc_advance();
+                }
+            }
+
+            Instruction::SkipIfNeqByte { reg, value } => {
+                if self.registers.get_general(reg) != value {
+                    self.pc_skip();
+                } else {
+                    self.pc_advance();
+                }
+            }
+
+            Instruction::SkipIfEqReg { lhs, rhs } => {
+                if self.registers.get_general(lhs) == self.registers.get_general(rhs) {
+                    self.pc_skip();
+                } else {
+                    self.pc_advance();
+                }
+            }
+
+            Instruction::LoadValue { dest, value } => {
+                self.registers.set_general(dest, value);
+                self.pc_advance();
+            }
+
+            Instruction::AddValue { dest, value } => {
+                let current = self.registers.get_general(dest);
+                let (result, _) = current.overflowing_add(value);
                 self.registers.set_general(dest, result);
                 self.pc_advance();
             }
@@ -300,6 +1607,7 @@ impl Processor {
                 let lhs = self.registers.get_general(dest);
                 let rhs = self.registers.get_general(source);
                 self.registers.set_general(dest, lhs | rhs);
+                self.reset_vf_after_logic();
                 self.pc_advance();
             }
 
@@ -307,6 +1615,7 @@ impl Processor {
                 let lhs = self.registers.get_general(dest);
                 let rhs = self.registers.get_general(source);
                 self.registers.set_general(dest, lhs & rhs);
+                self.reset_vf_after_logic();
                 self.pc_advance();
             }
 
@@ -314,6 +1623,7 @@ impl Processor {
                 let lhs = self.registers.get_general(dest);
                 let rhs = self.registers.get_general(source);
                 self.registers.set_general(dest, lhs ^ rhs);
+                self.reset_vf_after_logic();
                 self.pc_advance();
             }
 
@@ -343,8 +1653,8 @@ impl Processor {
                 self.pc_advance();
             }
 
-            Instruction::ShiftRight { dest, .. } => {
-                let value = self.registers.get_general(dest);
+            Instruction::ShiftRight { dest, source } => {
+                let value = self.registers.get_general(self.shift_operand(dest, source));
                 let lsb = value & 0x01_u8;
                 self.registers.set_general(dest, value >> 1);
 
@@ -370,8 +1680,8 @@ impl Processor {
                 self.pc_advance();
             }
 
-            Instruction::ShiftLeft { dest, .. } => {
-                let value = self.registers.get_general(dest);
+            Instruction::ShiftLeft { dest, source } => {
+                let value = self.registers.get_general(self.shift_operand(dest, source));
                 let msb = (value & 0b10000000_u8) >> 7;
                 self.registers.set_general(dest, value << 1);
                 if msb == 0x01_u8 {
@@ -395,35 +1705,55 @@ impl Processor {
                 self.pc_advance();
             }
 
+            Instruction::LoadLongI { addr } => {
+                self.registers.i = addr;
+                self.program_counter.increment(4);
+            }
+
             Instruction::JumpPlusV0 { addr } => {
+                let offset_register = if self.quirks.jump_uses_vx {
+                    Nibble::from_lower((u16::from(addr) >> 8) as u8).into()
+                } else {
+                    GeneralRegister::V0
+                };
                 let new_address = Address::from(
-                    self.registers.get_general(GeneralRegister::V0) as u16 + u16::from(addr),
+                    self.registers.get_general(offset_register) as u16 + u16::from(addr),
                 );
                 self.program_counter = new_address;
             }
 
             Instruction::Random { dest, mask } => {
-                let random_value: u8 = rand::random();
+                let random_value: u8 = self.rng.gen();
                 self.registers.set_general(dest, random_value & mask);
                 self.pc_advance();
             }
 
+            // In hires, DXY0 draws a 16x16 sprite from 32 bytes rather than
+            // nothing.
             Instruction::Draw { x, y, num_bytes } => {
-                let draw_start = u16::from(self.registers.i) as usize;
-                let draw_end = draw_start + num_bytes as usize;
-
-                if draw_end > MEMORY_SIZE_BYTES {
-                    return Err(ProcessorError::MemoryOverrun {
-                        address: self.program_counter,
-                    });
-                }
-
-                let bytes_to_draw = &self.memory[draw_start..draw_end];
-                self.display.draw_sprite(
-                    self.registers.get_general(x) as usize,
+                let wide = num_bytes == Nibble::Zero && self.display.is_hires();
+                let sprite_len = if wide {
+                    WIDE_SPRITE_BYTES
+                } else {
+                    num_bytes as usize
+                };
+                let bytes_to_draw =
+                    self.read_sprite(sprite_len * self.display.selected_plane_count())?;
+                let (x, y) = (
+                    self.registers.get_general(x) as usize,
                     self.registers.get_general(y) as usize,
-                    bytes_to_draw,
                 );
+                let pixels_disabled = match (wide, self.quirks.clip_sprites) {
+                    (false, true) => self.display.draw_sprite(x, y, &bytes_to_draw),
+                    (false, false) => self.display.draw_sprite_wrapping(x, y, &bytes_to_draw),
+                    (true, true) => self.display.draw_wide_sprite(x, y, &bytes_to_draw),
+                    (true, false) => self.display.draw_wide_sprite_wrapping(x, y, &bytes_to_draw),
+                };
+                if pixels_disabled == PixelsDisabled::SomePixels {
+                    self.registers.set_vf_flag(Flag::High);
+                } else {
+                    self.registers.set_vf_flag(Flag::Low);
+                }
                 self.pc_advance();
             }
 
@@ -460,13 +1790,25 @@ impl Processor {
                 self.pc_advance();
             }
 
-            Instruction::LoadFromKey { dest } => {
-                self.awaiting_key = Some(AwaitingKey {
-                    register: dest,
-                    pressed: false,
-                });
-                self.pc_advance();
-            }
+            // The program counter only moves on once a key has been pressed and
+            // released, so until then this instruction runs again every step.
+            Instruction::LoadFromKey { dest } => match self.awaiting_key {
+                Some(AwaitingKey {
+                    pressed: Some(key),
+                    released: true,
+                }) => {
+                    self.awaiting_key = None;
+                    self.registers.set_general(dest, key as u8);
+                    self.pc_advance();
+                }
+                Some(_) => {}
+                None => {
+                    self.awaiting_key = Some(AwaitingKey {
+                        pressed: None,
+                        released: false,
+                    });
+                }
+            },
 
             Instruction::SetDelayTimer { source } => {
                 self.registers.delay = self.registers.get_general(source);
@@ -478,10 +1820,31 @@ impl Processor {
                 self.pc_advance();
             }
 
+            Instruction::LoadAudioPattern => {
+                let start = u16::from(self.registers.i) as usize;
+                self.check_i_range(AUDIO_PATTERN_BYTES)?;
+                self.audio_pattern =
+                    core::array::from_fn(|offset| self.memory[self.wrap_address(start + offset)]);
+                self.pc_advance();
+            }
+
+            Instruction::SetPitch { source } => {
+                self.pitch = self.registers.get_general(source);
+                self.pc_advance();
+            }
+
             Instruction::AddI { source } => {
                 let base: u16 = self.registers.i.into();
                 let offset: u16 = self.registers.get_general(source) as u16;
-                self.registers.i = Address::from(base + offset);
+                let sum = base as usize + offset as usize;
+                self.registers.i = self.i_address(sum);
+                if self.quirks.add_i_sets_vf_on_overflow {
+                    self.registers.set_vf_flag(if sum >= self.memory.len() {
+                        Flag::High
+                    } else {
+                        Flag::Low
+                    });
+                }
                 self.pc_advance();
             }
 
@@ -494,47 +1857,95 @@ impl Processor {
                 self.pc_advance();
             }
 
+            Instruction::LoadHighSpriteLocation { digit } => {
+                let hex_digit = self.registers.get_general(digit);
+                let hex_sprite_address =
+                    HIGH_HEX_SPRITE_START + (hex_digit & 0x0F) as usize * HIGH_HEX_SPRITE_STRIDE;
+
+                self.registers.i = Address::from(hex_sprite_address as u16);
+
+                self.pc_advance();
+            }
+
             Instruction::LoadBcd { source } => {
                 let target_address = u16::from(self.registers.i) as usize;
-                if target_address + 3 > MEMORY_SIZE_BYTES {
-                    return Err(ProcessorError::MemoryOverrun {
-                        address: self.program_counter,
-                    });
-                }
+                self.check_i_range(3)?;
 
                 let binary_value = self.registers.get_general(source);
                 let bcd_digits = to_bcd(binary_value);
 
-                self.memory[target_address..target_address + bcd_digits.len()]
-                    .copy_from_slice(&bcd_digits);
+                for (offset, digit) in bcd_digits.into_iter().enumerate() {
+                    let address = self.wrap_address(target_address + offset);
+                    self.store(address, digit);
+                }
 
                 self.pc_advance();
             }
 
             Instruction::StoreRegisterRangeAtI { last } => {
-                let mut dest_address = u16::from(self.registers.i) as usize;
-                for reg in GeneralRegister::iter().take(last as usize + 1) {
-                    if dest_address > MEMORY_SIZE_BYTES {
-                        return Err(ProcessorError::MemoryOverrun {
-                            address: self.program_counter,
-                        });
-                    }
-                    self.memory[dest_address] = self.registers.get_general(reg);
-                    dest_address += 1;
+                let start = u16::from(self.registers.i) as usize;
+                self.check_i_range(last as usize + 1)?;
+                for (offset, reg) in GeneralRegister::iter().take(last as usize + 1).enumerate() {
+                    let dest_address = self.wrap_address(start + offset);
+                    self.store(dest_address, self.registers.get_general(reg));
                 }
+                self.increment_i_after_store(last);
                 self.pc_advance();
             }
 
             Instruction::LoadRegisterRangeFromI { last } => {
-                let mut src_address = u16::from(self.registers.i) as usize;
-                for reg in GeneralRegister::iter().take(last as usize + 1) {
-                    if src_address > MEMORY_SIZE_BYTES {
-                        return Err(ProcessorError::MemoryOverrun {
-                            address: self.program_counter,
-                        });
-                    }
+                let start = u16::from(self.registers.i) as usize;
+                self.check_i_range(last as usize + 1)?;
+                for (offset, reg) in GeneralRegister::iter().take(last as usize + 1).enumerate() {
+                    let src_address = self.wrap_address(start + offset);
+                    self.registers.set_general(reg, self.memory[src_address]);
+                }
+                self.increment_i_after_store(last);
+                self.pc_advance();
+            }
+
+            Instruction::StoreRegisterRange { first, last } => {
+                let start = u16::from(self.registers.i) as usize;
+                let registers = register_range(first, last);
+                self.check_i_range(registers.len())?;
+                for (offset, reg) in registers.into_iter().enumerate() {
+                    let dest_address = self.wrap_address(start + offset);
+                    self.store(dest_address, self.registers.get_general(reg));
+                }
+                self.pc_advance();
+            }
+
+            Instruction::LoadRegisterRange { first, last } => {
+                let start = u16::from(self.registers.i) as usize;
+                let registers = register_range(first, last);
+                self.check_i_range(registers.len())?;
+                for (offset, reg) in registers.into_iter().enumerate() {
+                    let src_address = self.wrap_address(start + offset);
                     self.registers.set_general(reg, self.memory[src_address]);
-                    src_address += 1;
+                }
+                self.pc_advance();
+            }
+
+            Instruction::StoreFlags { last } => {
+                let count = (last as usize).min(LAST_RPL_REGISTER as usize) + 1;
+                for (flag, reg) in self
+                    .rpl_flags
+                    .iter_mut()
+                    .zip(GeneralRegister::iter().take(count))
+                {
+                    *flag = self.registers.get_general(reg);
+                }
+                self.pc_advance();
+            }
+
+            Instruction::LoadFlags { last } => {
+                let count = (last as usize).min(LAST_RPL_REGISTER as usize) + 1;
+                for (flag, reg) in self
+                    .rpl_flags
+                    .iter()
+                    .zip(GeneralRegister::iter().take(count))
+                {
+                    self.registers.set_general(reg, *flag);
                 }
                 self.pc_advance();
             }
@@ -546,8 +1957,19 @@ impl Processor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cheats::{Cheat, CheatList, CheatMode};
     use crate::common_test_data::{BCD_INPUT_BYTES, BCD_OUTPUT_DIGITS};
-    use std::u8;
+    use crate::display::{ALL_PLANES, FIRST_PLANE, SECOND_PLANE};
+
+    const MAX_PROGRAM_BYTES: usize = MEMORY_SIZE_BYTES - PROGRAM_START;
+
+    fn xo_chip_processor(program: Vec<u8>) -> Processor {
+        let config = Config {
+            xo_chip: true,
+            ..Config::default()
+        };
+        Processor::new_with_config(program, config, Quirks::default()).unwrap()
+    }
 
     #[test]
     fn test_to_bcd() {
@@ -559,6 +1981,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_error_descriptions() {
+        let address = Address::from(0x200);
+        let errors = [
+            ProcessorError::ProgramTooLong {
+                size: 0x1000,
+                max: MAX_PROGRAM_BYTES,
+            },
+            ProcessorError::StackOverflow { address },
+            ProcessorError::StackUnderflow { address },
+            ProcessorError::MemoryOverrun { address },
+            ProcessorError::DecodeFailure {
+                instruction: instructions::InstructionBytePair(0xF001),
+                reason: DecodeError::Misc { byte: 0x01 },
+            },
+            ProcessorError::KeyOutOfRange { key_index: 0x10 },
+            ProcessorError::InvalidSaveState { size: 0 },
+            ProcessorError::AddressOutOfRange { address: 0x1200 },
+            ProcessorError::InvalidProgramStart { start: 0 },
+            ProcessorError::HiresWithMappedDisplay { address },
+        ];
+
+        for err in errors {
+            assert!(!err.category().is_empty());
+            assert!(!err.explanation().is_empty());
+        }
+        assert!(errors[1].explanation().contains("16 levels deep"));
+        assert!(errors[4]
+            .to_string()
+            .ends_with(": 0x01 doesn't end a valid FX__ instruction"));
+    }
+
     #[test]
     fn test_pc_advances() {
         let mut proc = Processor::new(vec![]).unwrap();
@@ -574,9 +2028,394 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_load_program() {
+        let mut proc = Processor::new(vec![0x60, 0x12, 0x22, 0x00]).unwrap();
+        proc.step().unwrap();
+        proc.step().unwrap();
+        proc.display.draw_sprite(0, 0, &[0xFF]);
+
+        proc.load_program(vec![0x61, 0x34]).unwrap();
+        assert_eq!(proc.program_counter, Address::from(PROGRAM_START as u16));
+        assert_eq!(proc.stack_pointer, 0);
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 0);
+        assert_eq!(proc.memory[PROGRAM_START + 2], 0);
+        assert_eq!(proc.display, Display::new(64, 32));
+
+        proc.step().unwrap();
+        assert_eq!(proc.registers.get_general(GeneralRegister::V1), 0x34);
+    }
+
+    #[test]
+    fn test_program_start() {
+        let config = Config {
+            program_start: 0x600,
+            ..Config::default()
+        };
+        let mut proc =
+            Processor::new_with_config(vec![0x60, 0x12], config, Quirks::default()).unwrap();
+        assert_eq!(proc.program_counter(), Address::from(0x600));
+        assert_eq!(proc.memory[PROGRAM_START], 0);
+        assert_eq!(
+            proc.next_instruction(),
+            Some(Instruction::LoadValue {
+                dest: GeneralRegister::V0,
+                value: 0x12
+            })
+        );
+        assert_eq!(proc.memory_map().program, 0x600..MEMORY_SIZE_BYTES);
+
+        proc.step().unwrap();
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 0x12);
+        proc.reset();
+        assert_eq!(proc.program_counter(), Address::from(0x600));
+
+        assert_eq!(
+            proc.load_program(vec![0; 0xA01]),
+            Err(ProcessorError::ProgramTooLong {
+                size: 0xA01,
+                max: 0xA00
+            })
+        );
+        for start in [0, 0x50, FONT_END - 1, MEMORY_SIZE_BYTES] {
+            let config = Config {
+                program_start: start,
+                ..Config::default()
+            };
+            assert_eq!(
+                Processor::new_with_config(vec![], config, Quirks::default()).err(),
+                Some(ProcessorError::InvalidProgramStart { start })
+            );
+        }
+        let config = Config {
+            program_start: FONT_END,
+            ..Config::default()
+        };
+        let proc = Processor::new_with_config(vec![0x12, 0x00], config, Quirks::default()).unwrap();
+        assert_eq!(proc.memory_map().reserved, FONT_END..FONT_END);
+        assert_eq!(proc.memory[..HEX_SPRITE_DATA.len()], HEX_SPRITE_DATA);
+
+        // XO-CHIP programs can run on past the first 4KB
+        let config = Config {
+            program_start: 0xF00,
+            xo_chip: true,
+            ..Config::default()
+        };
+        assert!(Processor::new_with_config(vec![0; 0x1000], config, Quirks::default()).is_ok());
+    }
+
+    #[test]
+    fn test_custom_font() {
+        let mut font = [0_u8; HEX_SPRITE_DATA.len()];
+        font[HEX_SPRITE_STRIDE * 0xA..HEX_SPRITE_STRIDE * 0xB]
+            .copy_from_slice(&[0x18, 0x24, 0x3C, 0x24, 0x24]);
+        let config = Config {
+            font: Some(font),
+            ..Config::default()
+        };
+        let mut proc = Processor::new_with_config(
+            vec![
+                0x60, 0x0A, // LD V0, 0x0A
+                0xF0, 0x29, // LD F, V0
+            ],
+            config,
+            Quirks::default(),
+        )
+        .unwrap();
+        proc.run_cycles(2).unwrap();
+
+        let i = u16::from(proc.i_register());
+        assert_eq!(i as usize, HEX_SPRITE_STRIDE * 0xA);
+        assert_eq!(
+            proc.read_memory_range(i, HEX_SPRITE_STRIDE),
+            [0x18, 0x24, 0x3C, 0x24, 0x24]
+        );
+        assert_eq!(proc.read_memory(0), 0);
+
+        proc.load_program(vec![]).unwrap();
+        assert_eq!(proc.read_memory_range(0, font.len()), font);
+    }
+
+    #[test]
+    fn test_load_program_too_long() {
+        let mut proc = Processor::new(vec![0x60, 0x12]).unwrap();
+        assert_eq!(
+            proc.load_program(vec![0; MAX_PROGRAM_BYTES + 1]),
+            Err(ProcessorError::ProgramTooLong {
+                size: MAX_PROGRAM_BYTES + 1,
+                max: MAX_PROGRAM_BYTES,
+            })
+        );
+        assert_eq!(proc.memory[PROGRAM_START], 0x60);
+    }
+
+    #[test]
+    fn test_write_memory() {
+        let mut proc = Processor::new(vec![]).unwrap();
+        proc.write_memory(0x300, 0xAB);
+        assert_eq!(proc.memory[0x300], 0xAB);
+
+        proc.write_memory(MEMORY_SIZE_BYTES as u16, 0xAB);
+        proc.write_memory(u16::MAX, 0xAB);
+    }
+
+    #[test]
+    fn test_read_memory() {
+        let mut proc = Processor::new(vec![
+            0x61, 0x20, // LD V1, 0x20
+            0x12, 0x00, // JP 0x200
+        ])
+        .unwrap();
+
+        assert_eq!(proc.read_memory(0x200), 0x61);
+        assert_eq!(proc.read_memory(0x203), 0x00);
+        assert_eq!(proc.read_memory(0x000), HEX_SPRITE_DATA[0]);
+        assert_eq!(proc.read_memory_range(0x200, 4), [0x61, 0x20, 0x12, 0x00]);
+        assert!(proc.read_memory_range(0x200, 0).is_empty());
+
+        proc.write_memory(0x201, 0x30);
+        assert_eq!(proc.read_memory(0x201), 0x30);
+    }
+
+    #[test]
+    fn test_read_memory_out_of_range() {
+        let proc = Processor::new(vec![]).unwrap();
+
+        assert_eq!(proc.read_memory(MEMORY_SIZE_BYTES as u16), 0);
+        assert_eq!(proc.read_memory(u16::MAX), 0);
+        assert_eq!(proc.read_memory_range(0xFFE, 4).len(), 2);
+        assert_eq!(proc.read_memory_range(0xFFE, usize::MAX).len(), 2);
+        assert!(proc
+            .read_memory_range(MEMORY_SIZE_BYTES as u16, 4)
+            .is_empty());
+        assert!(proc.read_memory_range(u16::MAX, 4).is_empty());
+    }
+
+    #[test]
+    fn test_per_frame_cheat_holds_memory() {
+        // decrements the byte at 0x300 on every pass of the loop
+        let mut proc = Processor::new(vec![
+            0xA3, 0x00, // LD I, 0x300
+            0xF0, 0x65, // LD V0, [I]
+            0x70, 0xFF, // ADD V0, 0xFF
+            0xF0, 0x55, // LD [I], V0
+            0x12, 0x02, // JP 0x202
+        ])
+        .unwrap();
+        proc.set_quirks(Quirks {
+            increment_i_on_store: false,
+            ..Quirks::default()
+        });
+        let mut cheats = CheatList::new(vec![Cheat {
+            address: Address::from(0x300),
+            value: 0x03,
+            mode: CheatMode::EveryFrame,
+        }]);
+
+        proc.step().unwrap();
+        cheats.apply(&mut proc);
+        for _ in 0..10 {
+            for _ in 0..4 {
+                proc.step().unwrap();
+            }
+            assert_eq!(proc.memory[0x300], 0x02);
+
+            cheats.apply(&mut proc);
+            assert_eq!(proc.memory[0x300], 0x03);
+        }
+    }
+
+    #[test]
+    fn test_one_shot_cheat_applies_once() {
+        let mut proc = Processor::new(vec![]).unwrap();
+        let mut cheats = CheatList::new(vec![Cheat {
+            address: Address::from(0x300),
+            value: 0x03,
+            mode: CheatMode::Once,
+        }]);
+
+        cheats.apply(&mut proc);
+        assert_eq!(proc.memory[0x300], 0x03);
+        assert!(cheats.is_empty());
+
+        proc.memory[0x300] = 0x00;
+        cheats.apply(&mut proc);
+        assert_eq!(proc.memory[0x300], 0x00);
+    }
+
+    #[test]
+    fn test_memory_is_4096_bytes() {
+        let proc = Processor::new(vec![0; MAX_PROGRAM_BYTES]).unwrap();
+        assert_eq!(proc.memory.len(), 4096);
+        assert_eq!(MAX_PROGRAM_BYTES, 0xE00);
+    }
+
+    #[test]
+    fn test_store_register_range_ending_at_last_byte() {
+        let mut proc = Processor::new(vec![
+            0xAF, 0xFC, // LD I, 0xFFC
+            0xF3, 0x55, // LD [I], V3
+            0xAF, 0xFC, // LD I, 0xFFC
+            0xF3, 0x65, // LD V3, [I]
+        ])
+        .unwrap();
+        for (idx, reg) in GeneralRegister::iter().take(4).enumerate() {
+            proc.registers.set_general(reg, 0xA0 + idx as u8);
+        }
+
+        proc.step().unwrap();
+        proc.step().unwrap();
+        assert_eq!(proc.memory[0xFFC..], [0xA0, 0xA1, 0xA2, 0xA3]);
+
+        proc.registers.set_general(GeneralRegister::V3, 0);
+        proc.step().unwrap();
+        proc.step().unwrap();
+        assert_eq!(proc.registers.get_general(GeneralRegister::V3), 0xA3);
+    }
+
+    #[test]
+    fn test_store_register_range_overrun() {
+        let mut proc = Processor::new(vec![
+            0xFF, 0x55, // LD [I], VF
+        ])
+        .unwrap();
+        for reg in GeneralRegister::iter() {
+            proc.registers.set_general(reg, 0xEE);
+        }
+        proc.registers.i = Address::from(0xFF8);
+
+        assert_eq!(
+            proc.step(),
+            Err(ProcessorError::MemoryOverrun {
+                address: Address::from(0x200)
+            })
+        );
+        // nothing is written when the range doesn't fit
+        assert!(proc.memory[0xFF8..].iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn test_load_register_range_overrun() {
+        let mut proc = Processor::new(vec![
+            0xF1, 0x65, // LD V1, [I]
+        ])
+        .unwrap();
+        proc.memory[0xFFF] = 0xAB;
+        proc.registers.i = Address::from(0xFFF);
+
+        assert_eq!(
+            proc.step(),
+            Err(ProcessorError::MemoryOverrun {
+                address: Address::from(0x200)
+            })
+        );
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 0);
+
+        // a single register at the last byte still fits
+        let mut proc = Processor::new(vec![
+            0xF0, 0x65, // LD V0, [I]
+        ])
+        .unwrap();
+        proc.memory[0xFFF] = 0xAB;
+        proc.registers.i = Address::from(0xFFF);
+        proc.step().unwrap();
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 0xAB);
+    }
+
+    #[test]
+    fn test_memory_mapped_display_refuses_hires() {
+        let mut proc = Processor::new(vec![
+            0x00, 0xFF, // HIGH
+        ])
+        .unwrap();
+        proc.set_quirks(Quirks {
+            memory_mapped_display: true,
+            ..Quirks::default()
+        });
+        assert_eq!(
+            proc.step(),
+            Err(ProcessorError::HiresWithMappedDisplay {
+                address: Address::from(0x200)
+            })
+        );
+        assert!(!proc.display.is_hires());
+
+        // turning the quirk on in hires goes back to lores rather than
+        // mapping over the program
+        let mut proc = Processor::new(vec![0x00, 0xFF]).unwrap();
+        proc.step().unwrap();
+        proc.memory[0xC00] = 0xAB;
+        proc.set_quirks(Quirks {
+            memory_mapped_display: true,
+            ..Quirks::default()
+        });
+        assert!(!proc.display.is_hires());
+        assert_eq!(proc.memory[0xC00], 0xAB);
+        assert_eq!(proc.memory_map().display, Some(0xF00..0x1000));
+    }
+
+    #[test]
+    fn test_memory_mapped_display_write() {
+        let mut proc = Processor::new(vec![
+            0x60, 0xC3, // LD V0, 0xC3
+            0xAF, 0x00, // LD I, 0xF00
+            0xF0, 0x55, // LD [I], V0
+        ])
+        .unwrap();
+        proc.set_quirks(Quirks {
+            memory_mapped_display: true,
+            ..Quirks::default()
+        });
+        for _ in 0..3 {
+            proc.step().unwrap();
+        }
+
+        let display = proc.get_display_buffer().unwrap();
+        let lit: Vec<(usize, usize)> = display
+            .indexed_iter()
+            .filter(|(_, pixel)| **pixel == Pixel::On)
+            .map(|(pos, _)| pos)
+            .collect();
+        assert_eq!(lit, vec![(0, 0), (0, 1), (0, 6), (0, 7)]);
+
+        proc.write_memory(0xF00 + 8 * 31 + 7, 0x01);
+        let display = proc.get_display_buffer().unwrap();
+        assert_eq!(display[(31, 63)], Pixel::On);
+    }
+
+    #[test]
+    fn test_memory_mapped_display_draw() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x08, // LD V0, 0x08
+            0xF0, 0x29, // LD F, V0
+            0xD0, 0x05, // DRW V0, V0, 5
+        ])
+        .unwrap();
+        proc.set_quirks(Quirks {
+            memory_mapped_display: true,
+            ..Quirks::default()
+        });
+        for _ in 0..3 {
+            proc.step().unwrap();
+        }
+
+        let start = MEMORY_SIZE_BYTES - 256;
+        let region = &proc.memory[start..];
+        assert_eq!(region, proc.display.to_packed_bytes());
+        assert_eq!(region[8 * 8 + 1], 0xF0);
+    }
+
+    #[test]
+    fn test_memory_mapped_display_disabled() {
+        let mut proc = Processor::new(vec![]).unwrap();
+        proc.write_memory(0xF00, 0xFF);
+        proc.get_display_buffer();
+        assert!(proc.get_display_buffer().is_none());
+    }
+
     #[test]
     fn test_invalid_instruction() {
-        let mut proc = Processor::new(vec![0xF0_u8, 0x01_u8]).unwrap();
+        let mut proc = Processor::new(vec![0xF0_u8, 0xFF_u8]).unwrap();
         assert!(matches!(
             proc.step(),
             Err(ProcessorError::DecodeFailure { .. })
@@ -608,6 +2447,7 @@ mod tests {
 
         assert_eq!(proc.program_counter, Address::from(0x206));
         assert_eq!(proc.stack_pointer, 1);
+        assert_eq!(proc.stack[0], Address::from(0x202));
 
         // execute the return
         proc.step().unwrap();
@@ -662,7 +2502,7 @@ mod tests {
 
         assert_eq!(proc.program_counter, Address::from(0xAAA));
         assert_eq!(proc.stack_pointer, 1);
-        assert_eq!(proc.stack[proc.stack_pointer], old_pc);
+        assert_eq!(proc.stack[0], old_pc);
     }
 
     #[test]
@@ -672,10 +2512,11 @@ mod tests {
         ])
         .unwrap();
 
-        for _ in 0..15 {
+        for _ in 0..STACK_SIZE {
             // fill up the call stack
             proc.step().unwrap();
         }
+        assert_eq!(proc.stack_pointer, STACK_SIZE);
 
         // call again to overflow
         let result = proc.step();
@@ -686,6 +2527,48 @@ mod tests {
                 address: Address::from(0x200)
             })
         );
+        assert_eq!(proc.stack_pointer, STACK_SIZE);
+    }
+
+    #[test]
+    fn test_sixteen_nested_calls_then_overflow() {
+        let mut program = Vec::new();
+        for idx in 1..=17_u16 {
+            // each call lands on the next one: call 0x200 + 2 * idx
+            program.extend((0x2000 + 0x200 + 2 * idx).to_be_bytes());
+        }
+        let mut proc = Processor::new(program).unwrap();
+
+        for depth in 0..16 {
+            proc.step().unwrap();
+            assert_eq!(proc.stack[depth], Address::from(0x200 + 2 * depth as u16));
+        }
+        assert_eq!(proc.stack_pointer, 16);
+
+        assert_eq!(
+            proc.step(),
+            Err(ProcessorError::StackOverflow {
+                address: Address::from(0x220)
+            })
+        );
+
+        // unwinding returns to each call site in turn
+        let mut proc = Processor::new(vec![
+            0x22, 0x04, // call 0x204 : addr 0x200
+            0x00, 0x00, // empty      : addr 0x202
+            0x22, 0x08, // call 0x208 : addr 0x204
+            0x00, 0x00, // empty      : addr 0x206
+            0x00, 0xEE, // return     : addr 0x208
+        ])
+        .unwrap();
+        proc.step().unwrap();
+        proc.step().unwrap();
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter, Address::from(0x206));
+        proc.step().unwrap();
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter, Address::from(0x202));
+        assert_eq!(proc.stack_pointer, 0);
     }
 
     #[test]
@@ -1039,6 +2922,10 @@ mod tests {
             0x81, 0x26, // SHR V1 {, V2}
         ])
         .unwrap();
+        proc.set_quirks(Quirks {
+            shift_uses_vy: false,
+            ..Quirks::default()
+        });
 
         let initial_value = 0b01010101_u8;
         proc.registers
@@ -1060,6 +2947,10 @@ mod tests {
             0x81, 0x26, // SHR V1 {, V2}
         ])
         .unwrap();
+        proc.set_quirks(Quirks {
+            shift_uses_vy: false,
+            ..Quirks::default()
+        });
 
         let initial_value = 0b10101010_u8;
         proc.registers
@@ -1076,33 +2967,167 @@ mod tests {
     }
 
     #[test]
-    fn test_subtract_negate() {
+    fn test_shift_right_uses_vy_quirk() {
         let mut proc = Processor::new(vec![
-            0x81, 0x27, // SUBN V1, V2
+            0x81, 0x26, // SHR V1, V2
         ])
         .unwrap();
-
-        // set vf to some value so we can check this instruction has affected the overflow flag
-        let initial_vf = 0x56_u8;
-        proc.registers.set_general(GeneralRegister::VF, initial_vf);
-        assert_eq!(proc.registers.get_vf_flag(), None);
-
-        let rhs = 0x43_u8;
-        let lhs = 0x21_u8;
-        proc.registers.set_general(GeneralRegister::V1, lhs);
-        proc.registers.set_general(GeneralRegister::V2, rhs);
+        proc.set_quirks(Quirks {
+            shift_uses_vy: true,
+            ..Quirks::default()
+        });
+        proc.registers.set_general(GeneralRegister::V1, 0b11110000);
+        proc.registers.set_general(GeneralRegister::V2, 0b00000011);
 
         proc.step().unwrap();
 
-        assert_eq!(proc.registers.get_general(GeneralRegister::V1), rhs - lhs);
-
-        // should not have overflowed
+        assert_eq!(proc.registers.get_general(GeneralRegister::V1), 0b00000001);
+        assert_eq!(proc.registers.get_general(GeneralRegister::V2), 0b00000011);
         assert_eq!(proc.registers.get_vf_flag(), Some(Flag::High));
     }
 
     #[test]
-    fn test_subtract_negate_overflow() {
-        let mut proc = Processor::new(vec![
+    fn test_quirk_presets() {
+        let program = vec![
+            0x60, 0x05, // LD V0, 0x05
+            0x62, 0x10, // LD V2, 0x10
+            0xB2, 0x20, // JP V0, 0x220
+        ];
+        let presets = [
+            (Quirks::default(), 0x225),
+            (Quirks::super_chip(), 0x230),
+            (Quirks::xo_chip(), 0x225),
+        ];
+        for (quirks, target) in presets {
+            let mut proc = Processor::new(program.clone()).unwrap();
+            proc.set_quirks(quirks);
+            proc.run_cycles(3).unwrap();
+            assert_eq!(u16::from(proc.program_counter), target);
+        }
+        assert!(!Quirks::xo_chip().clip_sprites);
+    }
+
+    #[test]
+    fn test_shift_vip_and_schip_behaviour() {
+        // (shift_uses_vy, SHR result, SHR VF, SHL result, SHL VF)
+        for (shift_uses_vy, shr, shr_vf, shl, shl_vf) in [
+            (true, 0x21, Flag::Low, 0x84, Flag::Low),
+            (false, 0x40, Flag::High, 0x02, Flag::High),
+        ] {
+            let mut proc = Processor::new(vec![
+                0x81, 0x26, // SHR V1, V2
+                0x81, 0x2E, // SHL V1, V2
+            ])
+            .unwrap();
+            proc.set_quirks(Quirks {
+                shift_uses_vy,
+                ..Quirks::default()
+            });
+
+            proc.registers.set_general(GeneralRegister::V1, 0x81);
+            proc.registers.set_general(GeneralRegister::V2, 0x42);
+            proc.step().unwrap();
+            assert_eq!(proc.registers.get_general(GeneralRegister::V1), shr);
+            assert_eq!(proc.registers.get_vf_flag(), Some(shr_vf));
+
+            proc.registers.set_general(GeneralRegister::V1, 0x81);
+            proc.step().unwrap();
+            assert_eq!(proc.registers.get_general(GeneralRegister::V1), shl);
+            assert_eq!(proc.registers.get_vf_flag(), Some(shl_vf));
+
+            // the source register is never modified
+            assert_eq!(proc.registers.get_general(GeneralRegister::V2), 0x42);
+        }
+    }
+
+    #[test]
+    fn test_shift_quirk_reads_decoded_source() {
+        for dest in GeneralRegister::iter().filter(|reg| *reg != GeneralRegister::VF) {
+            for source in GeneralRegister::iter().filter(|reg| *reg != dest) {
+                let x = dest as u8;
+                let y = source as u8;
+                let mut proc = Processor::new(vec![
+                    0x80 | x,
+                    (y << 4) | 0x06, // SHR Vx, Vy
+                    0x80 | x,
+                    (y << 4) | 0x0E, // SHL Vx, Vy
+                ])
+                .unwrap();
+                proc.set_quirks(Quirks {
+                    shift_uses_vy: true,
+                    ..Quirks::default()
+                });
+
+                proc.registers.set_general(dest, 0x00);
+                proc.registers.set_general(source, 0x0A);
+                proc.step().unwrap();
+                assert_eq!(proc.registers.get_general(dest), 0x05);
+
+                proc.registers.set_general(source, 0x11);
+                proc.step().unwrap();
+                assert_eq!(proc.registers.get_general(dest), 0x22);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shift_same_register_matches_without_quirk() {
+        for program in [
+            [0x83, 0x36], // SHR V3, V3
+            [0x83, 0x3E], // SHL V3, V3
+        ] {
+            for initial_value in [0x00_u8, 0x01, 0x80, 0x81, 0x55, 0xAA, 0xFF] {
+                let mut results = Vec::new();
+                for shift_uses_vy in [false, true] {
+                    let mut proc = Processor::new(program.to_vec()).unwrap();
+                    proc.set_quirks(Quirks {
+                        shift_uses_vy,
+                        ..Quirks::default()
+                    });
+                    proc.registers
+                        .set_general(GeneralRegister::V3, initial_value);
+
+                    proc.step().unwrap();
+
+                    results.push((
+                        proc.registers.get_general(GeneralRegister::V3),
+                        proc.registers.get_vf_flag(),
+                    ));
+                }
+
+                assert_eq!(results[0], results[1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_subtract_negate() {
+        let mut proc = Processor::new(vec![
+            0x81, 0x27, // SUBN V1, V2
+        ])
+        .unwrap();
+
+        // set vf to some value so we can check this instruction has affected the overflow flag
+        let initial_vf = 0x56_u8;
+        proc.registers.set_general(GeneralRegister::VF, initial_vf);
+        assert_eq!(proc.registers.get_vf_flag(), None);
+
+        let rhs = 0x43_u8;
+        let lhs = 0x21_u8;
+        proc.registers.set_general(GeneralRegister::V1, lhs);
+        proc.registers.set_general(GeneralRegister::V2, rhs);
+
+        proc.step().unwrap();
+
+        assert_eq!(proc.registers.get_general(GeneralRegister::V1), rhs - lhs);
+
+        // should not have overflowed
+        assert_eq!(proc.registers.get_vf_flag(), Some(Flag::High));
+    }
+
+    #[test]
+    fn test_subtract_negate_overflow() {
+        let mut proc = Processor::new(vec![
             0x81, 0x27, // SUBN V1, V2
         ])
         .unwrap();
@@ -1134,6 +3159,10 @@ mod tests {
             0x81, 0x2E, // SHL V1 {, V2}
         ])
         .unwrap();
+        proc.set_quirks(Quirks {
+            shift_uses_vy: false,
+            ..Quirks::default()
+        });
 
         let initial_value = 0b10101010_u8;
         proc.registers
@@ -1155,6 +3184,10 @@ mod tests {
             0x81, 0x2E, // SHL V1 {, V2}
         ])
         .unwrap();
+        proc.set_quirks(Quirks {
+            shift_uses_vy: false,
+            ..Quirks::default()
+        });
 
         let initial_value = 0b01010101_u8;
         proc.registers
@@ -1234,6 +3267,117 @@ mod tests {
         assert_eq!(proc.program_counter, Address::from(0x321));
     }
 
+    #[test]
+    fn test_jump_plus_vx_quirk() {
+        let mut proc = Processor::new(vec![
+            0xB3, 0x01, // JP V3, 0x301 : addr 0x200
+        ])
+        .unwrap();
+        proc.set_quirks(Quirks {
+            jump_uses_vx: true,
+            ..Quirks::default()
+        });
+
+        proc.registers.set_general(GeneralRegister::V0, 0x20_u8);
+        proc.registers.set_general(GeneralRegister::V3, 0x40_u8);
+
+        proc.step().unwrap();
+
+        assert_eq!(proc.program_counter, Address::from(0x341));
+    }
+
+    #[test]
+    fn test_logic_reset_vf_quirk() {
+        for (reset_vf_on_logic, expected) in [(true, 0x00), (false, 0x07)] {
+            let mut proc = Processor::new(vec![
+                0x81, 0x21, // OR V1, V2
+                0x81, 0x22, // AND V1, V2
+                0x81, 0x23, // XOR V1, V2
+            ])
+            .unwrap();
+            proc.set_quirks(Quirks {
+                reset_vf_on_logic,
+                ..Quirks::default()
+            });
+
+            for _ in 0..3 {
+                proc.registers.set_general(GeneralRegister::VF, 0x07);
+                proc.step().unwrap();
+                assert_eq!(proc.registers.get_general(GeneralRegister::VF), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_store_and_load_increment_i_quirk() {
+        for (increment_i_on_store, expected) in [(true, 0x404), (false, 0x400)] {
+            let mut proc = Processor::new(vec![
+                0xF3, 0x55, // LD [I], V3
+                0xF3, 0x65, // LD V3, [I]
+            ])
+            .unwrap();
+            proc.set_quirks(Quirks {
+                increment_i_on_store,
+                ..Quirks::default()
+            });
+
+            proc.registers.i = Address::from(0x400);
+            proc.step().unwrap();
+            assert_eq!(proc.registers.i, Address::from(expected));
+
+            proc.registers.i = Address::from(0x400);
+            proc.step().unwrap();
+            assert_eq!(proc.registers.i, Address::from(expected));
+        }
+    }
+
+    #[test]
+    fn test_draw_clip_sprites_quirk() {
+        for (clip_sprites, expected) in [
+            (
+                true,
+                "............................................................####",
+            ),
+            (
+                false,
+                "####........................................................####",
+            ),
+        ] {
+            let mut proc = Processor::new(vec![
+                0xA2, 0x04, // LD I, 0x204
+                0xD0, 0x11, // DRW V0, V1, 1
+                0xFF, 0x00, // sprite
+            ])
+            .unwrap();
+            proc.set_quirks(Quirks {
+                clip_sprites,
+                ..Quirks::default()
+            });
+
+            proc.registers.set_general(GeneralRegister::V0, 60);
+            proc.step().unwrap();
+            proc.step().unwrap();
+
+            assert_eq!(proc.display().to_ascii().lines().next(), Some(expected));
+        }
+    }
+
+    #[test]
+    fn test_skip_if_key_out_of_range() {
+        for program in [
+            [0xE1, 0x9E], // SKP V1
+            [0xE1, 0xA1], // SKNP V1
+        ] {
+            let mut proc = Processor::new(program.to_vec()).unwrap();
+            proc.registers.set_general(GeneralRegister::V1, 0x10);
+
+            assert_eq!(
+                proc.step(),
+                Err(ProcessorError::KeyOutOfRange { key_index: 0x10 })
+            );
+        }
+    }
+
     #[test]
     fn test_skip_if_key_down_false() {
         let mut proc = Processor::new(vec![
@@ -1314,6 +3458,74 @@ mod tests {
         assert_eq!(proc.program_counter, Address::from(0x204));
     }
 
+    #[test]
+    fn test_load_from_key_waits_for_release() {
+        let mut proc = Processor::new(vec![
+            0xF3, 0x0A, // LD V3, K
+            0x60, 0x01, // LD V0, 0x01
+        ])
+        .unwrap();
+
+        for _ in 0..3 {
+            proc.step().unwrap();
+        }
+        assert_eq!(proc.program_counter, Address::from(0x200));
+
+        proc.add_key_event(0xB, KeyStatus::Pressed);
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter, Address::from(0x200));
+        assert_eq!(proc.registers.get_general(GeneralRegister::V3), 0);
+
+        proc.add_key_event(0xB, KeyStatus::Released);
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter, Address::from(0x202));
+        assert_eq!(proc.registers.get_general(GeneralRegister::V3), 0xB);
+
+        proc.step().unwrap();
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 0x01);
+    }
+
+    #[test]
+    fn test_load_from_key_latches_first_key() {
+        let mut proc = Processor::new(vec![
+            0xF3, 0x0A, // LD V3, K
+        ])
+        .unwrap();
+
+        // a key released without being pressed during the wait doesn't count
+        proc.step().unwrap();
+        proc.add_key_event(0x1, KeyStatus::Released);
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter, Address::from(0x200));
+
+        proc.add_key_event(0x4, KeyStatus::Pressed);
+        proc.add_key_event(0x7, KeyStatus::Pressed);
+        proc.add_key_event(0x7, KeyStatus::Released);
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter, Address::from(0x200));
+
+        proc.add_key_event(0x4, KeyStatus::Released);
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter, Address::from(0x202));
+        assert_eq!(proc.registers.get_general(GeneralRegister::V3), 0x4);
+    }
+
+    #[test]
+    fn test_load_from_key_pressed_between_steps() {
+        let mut proc = Processor::new(vec![
+            0xF5, 0x0A, // LD V5, K
+        ])
+        .unwrap();
+
+        proc.step().unwrap();
+        proc.add_key_event(0xE, KeyStatus::Pressed);
+        proc.add_key_event(0xE, KeyStatus::Released);
+        proc.step().unwrap();
+
+        assert_eq!(proc.program_counter, Address::from(0x202));
+        assert_eq!(proc.registers.get_general(GeneralRegister::V5), 0xE);
+    }
+
     #[test]
     fn test_load_from_delay_timer() {
         let mut proc = Processor::new(vec![
@@ -1377,6 +3589,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_add_i_overflow_quirk() {
+        for (add_i_sets_vf_on_overflow, expected_vf) in [(false, 0x55), (true, 0x01)] {
+            let mut proc = Processor::new(vec![
+                0xF4, 0x1E, // ADD I, V4
+                0xF4, 0x1E, // ADD I, V4
+            ])
+            .unwrap();
+            proc.set_quirks(Quirks {
+                add_i_sets_vf_on_overflow,
+                ..Quirks::default()
+            });
+            proc.registers.i = Address::from(0xFF0);
+            proc.registers.set_general(GeneralRegister::V4, 0x20);
+            proc.registers.set_general(GeneralRegister::VF, 0x55);
+
+            proc.step().unwrap();
+            assert_eq!(proc.i_register(), Address::from(0x010));
+            assert_eq!(proc.general_register(GeneralRegister::VF), expected_vf);
+
+            proc.step().unwrap();
+            assert_eq!(proc.i_register(), Address::from(0x030));
+            let expected_vf = if add_i_sets_vf_on_overflow {
+                0x00
+            } else {
+                0x55
+            };
+            assert_eq!(proc.general_register(GeneralRegister::VF), expected_vf);
+        }
+    }
+
+    #[test]
+    fn test_program_counter_wraps() {
+        let mut proc = Processor::new(vec![
+            0x1F, 0xFE, // JP 0xFFE
+        ])
+        .unwrap();
+        proc.write_memory(0xFFE, 0x60); // LD V0, 0x01
+        proc.write_memory(0xFFF, 0x01);
+        proc.write_memory(0x000, 0x30); // SE V0, 0x01
+        proc.write_memory(0x001, 0x01);
+
+        proc.step().unwrap();
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter(), Address::from(0x000));
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter(), Address::from(0x004));
+
+        proc.program_counter = Address::from(0xFFF);
+        proc.write_memory(0xFFF, 0x60); // LD V0, 0x02, split across the wrap
+        proc.write_memory(0x000, 0x02);
+        assert_eq!(
+            proc.next_instruction(),
+            Some(Instruction::LoadValue {
+                dest: GeneralRegister::V0,
+                value: 0x02
+            })
+        );
+    }
+
     #[test]
     fn test_load_sprite_location() {
         for sprite_idx in 0..16_u8 {
@@ -1396,6 +3668,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_load_high_sprite_location() {
+        for sprite_idx in 0..16_u8 {
+            let mut proc = Processor::new(vec![
+                0xF0, 0x30, // LD HF, V0
+            ])
+            .unwrap();
+
+            proc.registers.set_general(GeneralRegister::V0, sprite_idx);
+
+            proc.step().unwrap();
+
+            let address = HEX_SPRITE_DATA.len() + sprite_idx as usize * HIGH_HEX_SPRITE_STRIDE;
+            assert_eq!(proc.registers.i, Address::from(address as u16));
+            assert_eq!(
+                proc.memory[address..address + HIGH_HEX_SPRITE_STRIDE],
+                HIGH_HEX_SPRITE_DATA[sprite_idx as usize * HIGH_HEX_SPRITE_STRIDE..]
+                    [..HIGH_HEX_SPRITE_STRIDE]
+            );
+        }
+    }
+
     #[test]
     fn test_load_bcd() {
         for (test_byte, expected_digits) in BCD_INPUT_BYTES
@@ -1483,4 +3777,1604 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_step_traced() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x01, // LD V0, 0x01
+            0x22, 0x06, // CALL 0x206
+            0x00, 0xFD, // EXIT
+            0xD0, 0x15, // DRW V0, V1, 5
+            0x00, 0xEE, // RET
+        ])
+        .unwrap();
+
+        let executed: Vec<_> = (0..6).map(|_| proc.step_traced().unwrap()).collect();
+        let expected = [
+            (
+                0x200,
+                Instruction::LoadValue {
+                    dest: GeneralRegister::V0,
+                    value: 0x01,
+                },
+            ),
+            (
+                0x202,
+                Instruction::Call {
+                    addr: Address::from(0x206),
+                },
+            ),
+            (
+                0x206,
+                Instruction::Draw {
+                    x: GeneralRegister::V0,
+                    y: GeneralRegister::V1,
+                    num_bytes: Nibble::Five,
+                },
+            ),
+            (0x208, Instruction::Return),
+            (0x204, Instruction::Exit),
+            (0x204, Instruction::Exit),
+        ]
+        .map(|(address, instruction)| {
+            Some(ExecutedInstruction {
+                address: Address::from(address),
+                instruction,
+            })
+        });
+        assert_eq!(executed, expected);
+
+        let mut proc = Processor::new(vec![0x00, 0x00]).unwrap();
+        proc.set_quirks(Quirks {
+            halt_on_zero_opcode: true,
+            ..Quirks::default()
+        });
+        assert_eq!(proc.step_traced(), Ok(None));
+        assert_eq!(proc.step_traced(), Ok(None));
+    }
+
+    #[test]
+    fn test_executed_instruction_display() {
+        let executed = ExecutedInstruction {
+            address: Address::from(0x2A4),
+            instruction: Instruction::Draw {
+                x: GeneralRegister::V0,
+                y: GeneralRegister::V1,
+                num_bytes: Nibble::Five,
+            },
+        };
+
+        assert_eq!(executed.to_string(), "0x2a4: DRW V0, V1, 5");
+        assert_eq!(executed.outcome(), StepOutcome::Running);
+    }
+
+    #[test]
+    fn test_self_jump_is_idle() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x01, // LD V0, 0x01
+            0x12, 0x02, // JP 0x202
+        ])
+        .unwrap();
+
+        assert_eq!(proc.step(), Ok(StepOutcome::Running));
+        assert!(!proc.is_idle());
+        assert_eq!(proc.step(), Ok(StepOutcome::Idle));
+        assert!(proc.is_idle());
+        assert_eq!(proc.step(), Ok(StepOutcome::Idle));
+        assert_eq!(proc.program_counter(), Address::from(0x202));
+
+        proc.reset();
+        assert!(!proc.is_idle());
+    }
+
+    #[test]
+    fn test_only_jumps_to_themselves_are_idle() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x06, // LD V0, 0x06
+            0x00, 0xE0, // CLS
+            0xB1, 0xFE, // JP V0, 0x1FE
+        ])
+        .unwrap();
+
+        proc.run_cycles(10).unwrap();
+        assert!(proc.is_idle());
+        assert_eq!(proc.program_counter(), Address::from(0x204));
+
+        let mut proc = Processor::new(vec![
+            0x12, 0x04, // JP 0x204
+            0x00, 0x00, //
+            0x12, 0x00, // JP 0x200
+        ])
+        .unwrap();
+        for _ in 0..4 {
+            assert_eq!(proc.step(), Ok(StepOutcome::Running));
+        }
+    }
+
+    #[test]
+    fn test_run_cycles() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x01, // LD V0, 0x01
+            0xF0, 0x29, // LD F, V0
+            0x61, 0x02, // LD V1, 0x02
+            0xD1, 0x15, // DRW V1, V1, 5
+            0x12, 0x08, // JP 0x208
+        ])
+        .unwrap();
+
+        proc.run_cycles(3).unwrap();
+        assert_eq!(proc.program_counter(), Address::from(0x206));
+        assert!(proc.display().to_ascii().chars().all(|pixel| pixel != '#'));
+
+        proc.run_cycles(10).unwrap();
+        assert_eq!(proc.program_counter(), Address::from(0x208));
+        assert_eq!(proc.general_register(GeneralRegister::V1), 0x02);
+        assert_eq!(proc.i_register(), Address::from(0x005));
+        let rows: Vec<String> = proc
+            .display()
+            .to_ascii()
+            .lines()
+            .take(8)
+            .map(|row| row[..8].to_string())
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "........", "........", "....#...", "...##...", "....#...", "....#...", "...###..",
+                "........",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_cycles_stops_at_exit_or_halt() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x01, // LD V0, 0x01
+            0x00, 0xFD, // EXIT
+            0x60, 0x02, // LD V0, 0x02
+        ])
+        .unwrap();
+        proc.run_cycles(10).unwrap();
+        assert_eq!(proc.general_register(GeneralRegister::V0), 0x01);
+
+        let mut proc = Processor::new(vec![
+            0x60, 0x01, // LD V0, 0x01
+            0x00, 0x00, // halts
+        ])
+        .unwrap();
+        proc.set_quirks(Quirks {
+            halt_on_zero_opcode: true,
+            ..Quirks::default()
+        });
+        proc.run_cycles(10).unwrap();
+        assert!(proc.is_halted());
+
+        let mut proc = Processor::new(vec![
+            0x60, 0x01, // LD V0, 0x01
+            0x61, 0x02, // LD V1, 0x02
+            0x00, 0xEE, // RET with an empty stack
+        ])
+        .unwrap();
+        assert!(proc.run_cycles(10).is_err());
+        assert_eq!(proc.program_counter(), Address::from(0x204));
+    }
+
+    #[test]
+    fn test_exit() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x01, // LD V0, 0x01
+            0x00, 0xFD, // EXIT
+            0x60, 0x02, // LD V0, 0x02
+        ])
+        .unwrap();
+
+        assert_eq!(proc.step(), Ok(StepOutcome::Running));
+        assert_eq!(proc.step(), Ok(StepOutcome::Exited));
+        assert_eq!(proc.step(), Ok(StepOutcome::Exited));
+        assert_eq!(proc.program_counter, Address::from(0x202));
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 0x01);
+    }
+
+    #[test]
+    fn test_rpl_flags_round_trip() {
+        let mut proc = Processor::new(vec![
+            0xF4, 0x75, // LD R, V4
+            0xF4, 0x85, // LD V4, R
+        ])
+        .unwrap();
+        for (idx, reg) in GeneralRegister::iter().enumerate() {
+            proc.registers.set_general(reg, 0x10 + idx as u8);
+        }
+
+        proc.step().unwrap();
+        for reg in GeneralRegister::iter() {
+            proc.registers.set_general(reg, 0xAA);
+        }
+        proc.step().unwrap();
+
+        // V0 to V4 come back from the flags, the rest are untouched
+        for (idx, reg) in GeneralRegister::iter().enumerate() {
+            let expected = if idx <= 4 { 0x10 + idx as u8 } else { 0xAA };
+            assert_eq!(proc.registers.get_general(reg), expected);
+        }
+        assert_eq!(proc.registers.i, Address::from(0));
+    }
+
+    #[test]
+    fn test_rpl_flags_capped_at_v7() {
+        let mut proc = Processor::new(vec![
+            0xFF, 0x75, // LD R, VF
+            0xFF, 0x85, // LD VF, R
+        ])
+        .unwrap();
+        for (idx, reg) in GeneralRegister::iter().enumerate() {
+            proc.registers.set_general(reg, 0x10 + idx as u8);
+        }
+
+        proc.step().unwrap();
+        assert_eq!(
+            proc.rpl_flags[..8],
+            [0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17]
+        );
+        assert_eq!(proc.rpl_flags[8..], [0; 8]);
+
+        for reg in GeneralRegister::iter() {
+            proc.registers.set_general(reg, 0xAA);
+        }
+        proc.step().unwrap();
+        for (idx, reg) in GeneralRegister::iter().enumerate() {
+            let expected = if idx <= 7 { 0x10 + idx as u8 } else { 0xAA };
+            assert_eq!(proc.registers.get_general(reg), expected);
+        }
+
+        // the flags outlive the program
+        proc.load_program(vec![]).unwrap();
+        assert_eq!(proc.rpl_flags[0], 0x10);
+    }
+
+    #[test]
+    fn test_draw_collision_sets_vf() {
+        let mut proc = Processor::new(vec![
+            0xA2, 0x0C, // LD I, 0x20C
+            0xD0, 0x11, // DRW V0, V1, 1
+            0xD0, 0x11, // DRW V0, V1, 1
+            0x70, 0x08, // ADD V0, 0x08
+            0xD0, 0x11, // DRW V0, V1, 1
+            0x12, 0x0A, // JP 0x20A
+            0xF0, 0x00, // sprite
+        ])
+        .unwrap();
+
+        proc.registers.set_general(GeneralRegister::VF, 0x01);
+        proc.step().unwrap();
+        proc.step().unwrap();
+        assert_eq!(proc.registers.get_general(GeneralRegister::VF), 0x00);
+
+        // the second draw overlaps the first and erases it
+        proc.step().unwrap();
+        assert_eq!(proc.registers.get_general(GeneralRegister::VF), 0x01);
+
+        proc.step().unwrap();
+        proc.step().unwrap();
+        assert_eq!(proc.registers.get_general(GeneralRegister::VF), 0x00);
+    }
+
+    #[test]
+    fn test_draw_at_memory_boundary() {
+        let mut proc = Processor::new(vec![
+            0xD0, 0x05, // DRW V0, V0, 5
+        ])
+        .unwrap();
+        proc.registers.i = Address::from((MEMORY_SIZE_BYTES - 5) as u16);
+        proc.step().unwrap();
+
+        let mut proc = Processor::new(vec![
+            0xD0, 0x05, // DRW V0, V0, 5
+        ])
+        .unwrap();
+        proc.registers.i = Address::from((MEMORY_SIZE_BYTES - 4) as u16);
+        assert_eq!(
+            proc.step(),
+            Err(ProcessorError::MemoryOverrun {
+                address: Address::from(0x200)
+            })
+        );
+    }
+
+    #[test]
+    fn test_draw_fifteen_rows() {
+        let mut proc = Processor::new(vec![
+            0xA3, 0x00, // LD I, 0x300
+            0xD0, 0x1F, // DRW V0, V1, 15
+        ])
+        .unwrap();
+        proc.registers.set_general(GeneralRegister::V0, 4);
+        proc.registers.set_general(GeneralRegister::V1, 10);
+        for row in 0..15 {
+            proc.memory[0x300 + row] = 0x80 >> (row % 8);
+        }
+
+        proc.step().unwrap();
+        proc.step().unwrap();
+
+        // a diagonal line, restarting from the left after eight rows
+        let display = proc.get_display_buffer().unwrap();
+        let lit: Vec<(usize, usize)> = display
+            .indexed_iter()
+            .filter(|(_, pixel)| **pixel == Pixel::On)
+            .map(|(pos, _)| pos)
+            .collect();
+        let expected: Vec<(usize, usize)> = (0..15).map(|row| (10 + row, 4 + row % 8)).collect();
+        assert_eq!(lit, expected);
+        assert_eq!(proc.registers.get_vf_flag(), Some(Flag::Low));
+    }
+
+    #[test]
+    fn test_draw_fifteen_rows_at_memory_boundary() {
+        let mut proc = Processor::new(vec![
+            0xD0, 0x0F, // DRW V0, V0, 15
+        ])
+        .unwrap();
+        proc.registers.i = Address::from((MEMORY_SIZE_BYTES - 15) as u16);
+        proc.step().unwrap();
+
+        let mut proc = Processor::new(vec![
+            0xD0, 0x0F, // DRW V0, V0, 15
+        ])
+        .unwrap();
+        proc.registers.i = Address::from((MEMORY_SIZE_BYTES - 14) as u16);
+        assert_eq!(
+            proc.step(),
+            Err(ProcessorError::MemoryOverrun {
+                address: Address::from(0x200)
+            })
+        );
+    }
+
+    #[test]
+    fn test_display_dimensions() {
+        let mut proc = Processor::new(vec![
+            0x00, 0xFF, // HIGH
+            0x00, 0xFE, // LOW
+        ])
+        .unwrap();
+        assert_eq!(proc.display_dimensions(), (64, 32));
+
+        proc.step().unwrap();
+        assert_eq!(proc.display_dimensions(), (128, 64));
+
+        proc.step().unwrap();
+        assert_eq!(proc.display_dimensions(), (64, 32));
+    }
+
+    #[test]
+    fn test_hires_draws_wide_sprites() {
+        let mut program = vec![
+            0xA2, 0x0A, // LD I, 0x20A
+            0xD0, 0x00, // DRW V0, V0, 0 : nothing in lores
+            0x00, 0xFF, // HIGH
+            0xD0, 0x00, // DRW V0, V0, 0 : 16x16 in hires
+            0x00, 0xFE, // LOW
+        ];
+        program.extend([0xFF; WIDE_SPRITE_BYTES]);
+        let mut proc = Processor::new(program).unwrap();
+
+        proc.step().unwrap();
+        proc.step().unwrap();
+        assert!(!proc.display().to_ascii().contains('#'));
+
+        proc.step().unwrap();
+        assert!(proc.display().is_hires());
+        assert_eq!(proc.get_display_buffer().unwrap().size(), (64, 128));
+
+        proc.step().unwrap();
+        let lit = proc.display().to_ascii().matches('#').count();
+        assert_eq!(lit, 16 * 16);
+
+        proc.step().unwrap();
+        assert!(!proc.display().is_hires());
+        assert_eq!(proc.get_display_buffer().unwrap().size(), (32, 64));
+
+        proc.display.set_hires(true);
+        proc.load_program(vec![]).unwrap();
+        assert!(!proc.display().is_hires());
+    }
+
+    #[test]
+    fn test_select_planes() {
+        let mut program = vec![
+            0xA2, 0x10, // LD I, 0x210
+            0xD0, 0x01, // DRW V0, V0, 1 : first plane
+            0xF2, 0x01, // PLANE 2
+            0xD0, 0x01, // DRW V0, V0, 1 : second plane
+            0xF3, 0x01, // PLANE 3
+            0x61, 0x01, // LD V1, 0x01
+            0xD0, 0x11, // DRW V0, V1, 1 : both planes, two bytes
+            0x00, 0xE0, // CLS
+        ];
+        program.extend([0xF0, 0x3C]);
+        let mut proc = xo_chip_processor(program);
+
+        proc.run_cycles(2).unwrap();
+        assert_eq!(proc.selected_planes(), FIRST_PLANE);
+        assert_eq!(&proc.display().to_ascii()[..8], "####....");
+
+        proc.run_cycles(2).unwrap();
+        assert_eq!(proc.selected_planes(), SECOND_PLANE);
+        assert_eq!(&proc.display().to_ascii()[..8], "%%%%....");
+        assert_eq!(proc.registers.get_general(GeneralRegister::VF), 0);
+
+        proc.run_cycles(3).unwrap();
+        assert_eq!(proc.selected_planes(), ALL_PLANES);
+        let second_row = &proc.display().to_ascii()[65..73];
+        assert_eq!(second_row, "##%%++..");
+
+        proc.step().unwrap();
+        assert!(!proc.display().to_ascii().contains(['#', '+', '%']));
+
+        proc.reset();
+        assert_eq!(proc.selected_planes(), FIRST_PLANE);
+    }
+
+    #[test]
+    fn test_store_and_load_register_range() {
+        let mut proc = xo_chip_processor(vec![
+            0xA4, 0x00, // LD I, 0x400
+            0x52, 0x42, // SAVE V2 - V4
+            0xA4, 0x10, // LD I, 0x410
+            0x54, 0x22, // SAVE V4 - V2
+            0x57, 0x93, // LOAD V7 - V9
+            0x5C, 0xA3, // LOAD VC - VA
+        ]);
+        for (idx, reg) in GeneralRegister::iter().enumerate() {
+            proc.registers.set_general(reg, idx as u8 + 1);
+        }
+
+        proc.run_cycles(2).unwrap();
+        assert_eq!(&proc.memory[0x3FF..0x404], [0, 3, 4, 5, 0]);
+        assert_eq!(u16::from(proc.i_register()), 0x400);
+
+        proc.run_cycles(2).unwrap();
+        assert_eq!(&proc.memory[0x40F..0x414], [0, 5, 4, 3, 0]);
+
+        proc.step().unwrap();
+        let loaded = [
+            GeneralRegister::V7,
+            GeneralRegister::V8,
+            GeneralRegister::V9,
+        ];
+        for (reg, value) in loaded.into_iter().zip([5, 4, 3]) {
+            assert_eq!(proc.registers.get_general(reg), value);
+        }
+        assert_eq!(proc.registers.get_general(GeneralRegister::V6), 7);
+
+        proc.step().unwrap();
+        let loaded = [
+            GeneralRegister::VC,
+            GeneralRegister::VB,
+            GeneralRegister::VA,
+        ];
+        for (reg, value) in loaded.into_iter().zip([5, 4, 3]) {
+            assert_eq!(proc.registers.get_general(reg), value);
+        }
+        assert_eq!(proc.registers.get_general(GeneralRegister::VD), 14);
+        assert_eq!(u16::from(proc.i_register()), 0x410);
+    }
+
+    #[test]
+    fn test_audio_pattern_and_pitch() {
+        let pattern: Vec<u8> = (0..AUDIO_PATTERN_BYTES as u8).map(|n| n * 0x11).collect();
+        let mut program = vec![
+            0xA2, 0x08, // LD I, 0x208
+            0xF0, 0x02, // AUDIO
+            0x60, 0x70, // LD V0, 0x70
+            0xF0, 0x3A, // PITCH V0
+        ];
+        program.extend(&pattern);
+        let mut proc = xo_chip_processor(program);
+        assert_eq!(proc.audio_pattern(), &[0; AUDIO_PATTERN_BYTES]);
+        assert_eq!(proc.pitch(), DEFAULT_PITCH);
+        #[cfg(feature = "std")]
+        assert_eq!(proc.audio_bit_rate(), 4000.0);
+
+        proc.run_cycles(2).unwrap();
+        assert_eq!(proc.audio_pattern()[..], pattern[..]);
+
+        proc.run_cycles(2).unwrap();
+        assert_eq!(proc.pitch(), 0x70);
+        #[cfg(feature = "std")]
+        assert_eq!(proc.audio_bit_rate(), 8000.0);
+
+        proc.reset();
+        assert_eq!(proc.audio_pattern(), &[0; AUDIO_PATTERN_BYTES]);
+        assert_eq!(proc.pitch(), DEFAULT_PITCH);
+    }
+
+    #[test]
+    fn test_load_long_i() {
+        let mut proc = xo_chip_processor(vec![
+            0xF0, 0x00, 0xAB, 0xCD, // LD I, LONG 0xabcd
+            0xF0, 0x1E, // ADD I, V0
+        ]);
+        proc.registers.set_general(GeneralRegister::V0, 0x01);
+        assert_eq!(
+            proc.next_instruction(),
+            Some(Instruction::LoadLongI {
+                addr: Address::extended(0xABCD)
+            })
+        );
+
+        proc.step().unwrap();
+        assert_eq!(u16::from(proc.i_register()), 0xABCD);
+        assert_eq!(u16::from(proc.program_counter()), 0x204);
+
+        proc.step().unwrap();
+        assert_eq!(u16::from(proc.i_register()), 0xABCE);
+        assert_eq!(proc.memory_map().total_size, XO_CHIP_MEMORY_SIZE_BYTES);
+    }
+
+    #[test]
+    fn test_load_long_i_needs_xo_chip() {
+        let mut proc = Processor::new(vec![0xF0, 0x00, 0xAB, 0xCD]).unwrap();
+        assert_eq!(
+            proc.step(),
+            Err(ProcessorError::DecodeFailure {
+                instruction: instructions::InstructionBytePair(0xF000),
+                reason: DecodeError::Misc { byte: 0x00 },
+            })
+        );
+        assert_eq!(proc.memory_map().total_size, MEMORY_SIZE_BYTES);
+    }
+
+    #[test]
+    fn test_xo_chip_instructions_need_xo_chip() {
+        let mut proc = Processor::new(vec![0xF2, 0x01]).unwrap();
+        assert_eq!(
+            proc.step(),
+            Err(ProcessorError::DecodeFailure {
+                instruction: instructions::InstructionBytePair(0xF201),
+                reason: DecodeError::XoChipDisabled,
+            })
+        );
+    }
+
+    #[test]
+    fn test_skip_over_load_long_i() {
+        let mut proc = xo_chip_processor(vec![
+            0x30, 0x00, // SE V0, 0x00
+            0xF0, 0x00, 0x12, 0x34, // LD I, LONG 0x1234
+            0x00, 0xE0, // CLS
+        ]);
+        proc.step().unwrap();
+        assert_eq!(u16::from(proc.program_counter()), 0x206);
+        assert_eq!(u16::from(proc.i_register()), 0);
+    }
+
+    #[test]
+    fn test_xo_chip_memory_reads_and_writes() {
+        let mut proc = xo_chip_processor(vec![
+            0xF0, 0x00, 0xFF, 0xF0, // LD I, LONG 0xfff0
+            0x60, 0x42, // LD V0, 0x42
+            0xF0, 0x55, // LD [I], V0
+        ]);
+        proc.run_cycles(3).unwrap();
+        assert_eq!(proc.read_memory(0xFFF0), 0x42);
+        assert_eq!(u16::from(proc.i_register()), 0xFFF1);
+    }
+
+    #[test]
+    fn test_scroll_instructions() {
+        let mut proc = Processor::new(vec![
+            0x00, 0xC3, // SCD 3
+            0x00, 0xFB, // SCR
+            0x00, 0xFC, // SCL
+        ])
+        .unwrap();
+        proc.display.draw_sprite(0, 0, &[0x80]);
+
+        proc.step().unwrap();
+        assert_eq!(
+            proc.display().to_ascii().lines().nth(3),
+            Some(&*format!("#{}", ".".repeat(63)))
+        );
+
+        proc.step().unwrap();
+        assert_eq!(
+            proc.display().to_ascii().lines().nth(3),
+            Some(&*format!("....#{}", ".".repeat(59)))
+        );
+
+        proc.step().unwrap();
+        assert_eq!(
+            proc.display().to_ascii().lines().nth(3),
+            Some(&*format!("#{}", ".".repeat(63)))
+        );
+    }
+
+    #[test]
+    fn test_hires_wide_sprite_collision_sets_vf() {
+        let mut program = vec![
+            0x00, 0xFF, // HIGH
+            0xA2, 0x0A, // LD I, 0x20A
+            0xD0, 0x10, // DRW V0, V1, 0
+            0xD2, 0x30, // DRW V2, V3, 0
+            0x12, 0x08, // JP 0x208
+        ];
+        program.extend([0xFF; WIDE_SPRITE_BYTES]);
+        let mut proc = Processor::new(program).unwrap();
+        proc.set_quirks(Quirks {
+            clip_sprites: false,
+            ..Quirks::default()
+        });
+        proc.registers.set_general(GeneralRegister::V2, 120);
+        proc.registers.set_general(GeneralRegister::V3, 56);
+
+        proc.step().unwrap();
+        proc.step().unwrap();
+        proc.step().unwrap();
+        assert_eq!(proc.registers.get_vf_flag(), Some(Flag::Low));
+
+        // the second block wraps round into the first
+        proc.step().unwrap();
+        assert_eq!(proc.registers.get_vf_flag(), Some(Flag::High));
+    }
+
+    #[test]
+    fn test_hires_sprite_read_at_memory_boundary() {
+        // a 16x16 sprite takes 32 bytes
+        let mut proc = Processor::new(vec![]).unwrap();
+        proc.memory[MEMORY_SIZE_BYTES - 1] = 0xAB;
+        proc.registers.i = Address::from((MEMORY_SIZE_BYTES - 32) as u16);
+        let sprite = proc.read_sprite(32).unwrap();
+        assert_eq!(sprite.len(), 32);
+        assert_eq!(sprite[31], 0xAB);
+
+        proc.registers.i = Address::from((MEMORY_SIZE_BYTES - 31) as u16);
+        assert!(matches!(
+            proc.read_sprite(32),
+            Err(ProcessorError::MemoryOverrun { .. })
+        ));
+    }
+
+    #[test]
+    fn test_wrap_memory_load_register_range() {
+        let mut proc = Processor::new(vec![
+            0xF3, 0x65, // LD V3, [I]
+        ])
+        .unwrap();
+        proc.set_quirks(Quirks {
+            wrap_memory: true,
+            ..Quirks::default()
+        });
+        proc.memory[MEMORY_SIZE_BYTES - 2] = 0x11;
+        proc.memory[MEMORY_SIZE_BYTES - 1] = 0x22;
+        proc.registers.i = Address::from((MEMORY_SIZE_BYTES - 2) as u16);
+
+        proc.step().unwrap();
+
+        // V2 and V3 come from the start of the font
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 0x11);
+        assert_eq!(proc.registers.get_general(GeneralRegister::V1), 0x22);
+        assert_eq!(
+            proc.registers.get_general(GeneralRegister::V2),
+            HEX_SPRITE_DATA[0]
+        );
+        assert_eq!(
+            proc.registers.get_general(GeneralRegister::V3),
+            HEX_SPRITE_DATA[1]
+        );
+    }
+
+    #[test]
+    fn test_wrap_memory_store_and_draw() {
+        let mut proc = Processor::new(vec![
+            0xF1, 0x55, // LD [I], V1
+            0xF0, 0x33, // LD B, V0
+            0xD2, 0x22, // DRW V2, V2, 2
+        ])
+        .unwrap();
+        proc.set_quirks(Quirks {
+            wrap_memory: true,
+            increment_i_on_store: false,
+            ..Quirks::default()
+        });
+        proc.registers.set_general(GeneralRegister::V0, 0xFF);
+        proc.registers.set_general(GeneralRegister::V1, 0x81);
+        proc.registers.i = Address::from((MEMORY_SIZE_BYTES - 1) as u16);
+
+        proc.step().unwrap();
+        assert_eq!(proc.memory[MEMORY_SIZE_BYTES - 1], 0xFF);
+        assert_eq!(proc.memory[0], 0x81);
+
+        proc.step().unwrap();
+        assert_eq!(proc.memory[MEMORY_SIZE_BYTES - 1], 2);
+        assert_eq!(proc.memory[0..2], [5, 5]);
+
+        proc.step().unwrap();
+        let display = proc.get_display_buffer().unwrap();
+        assert_eq!(display[(0, 0)], Pixel::Off);
+        assert_eq!(display[(1, 0)], Pixel::Off);
+        assert_eq!(display[(1, 5)], Pixel::On);
+        assert_eq!(display[(1, 7)], Pixel::On);
+    }
+
+    #[test]
+    fn test_rewind_frame() {
+        let mut proc = Processor::new(vec![
+            0xF0, 0x29, // LD F, V0      : addr 0x200
+            0xD1, 0x15, // DRW V1, V1, 5 : addr 0x202
+            0x70, 0x01, // ADD V0, 0x01  : addr 0x204
+            0x71, 0x08, // ADD V1, 0x08  : addr 0x206
+            0x12, 0x00, // JP 0x200      : addr 0x208
+        ])
+        .unwrap();
+
+        // one full trip around the loop per frame, drawing the next digit each time
+        let mut frames = Vec::new();
+        for _ in 0..4 {
+            proc.record_frame();
+            frames.push(proc.display.clone());
+            for _ in 0..5 {
+                proc.step().unwrap();
+            }
+        }
+
+        assert!(proc.rewind_frame(1));
+        assert_eq!(proc.display, frames[3]);
+        assert_eq!(proc.program_counter, Address::from(0x200));
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 3);
+
+        assert!(proc.rewind_frame(2));
+        assert_eq!(proc.display, frames[1]);
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 1);
+
+        // the rewound display is sent out again
+        assert!(proc.get_display_buffer().is_some());
+    }
+
+    #[test]
+    fn test_rewind_frame_without_history() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x01, // LD V0, 0x01
+        ])
+        .unwrap();
+
+        assert!(!proc.rewind_frame(1));
+
+        proc.record_frame();
+        proc.step().unwrap();
+
+        assert!(!proc.rewind_frame(0));
+        assert!(!proc.rewind_frame(2));
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 1);
+
+        assert!(proc.rewind_frame(1));
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 0);
+        assert!(!proc.rewind_frame(1));
+    }
+
+    #[test]
+    fn test_frame_history_length() {
+        let mut proc = Processor::new(vec![
+            0x70, 0x01, // ADD V0, 0x01
+            0x12, 0x00, // JP 0x200
+        ])
+        .unwrap();
+        for _ in 0..4 {
+            proc.record_frame();
+            proc.run_cycles(2).unwrap();
+        }
+
+        proc.set_frame_history_length(2);
+        assert!(!proc.rewind_frame(3));
+        assert!(proc.rewind_frame(2));
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 2);
+
+        proc.set_frame_history_length(0);
+        proc.record_frame();
+        assert!(!proc.rewind_frame(1));
+    }
+
+    #[test]
+    fn test_vf_clobber_warning() {
+        let mut proc = Processor::new(vec![
+            0x6F, 0x05, // LD VF, 0x05 : addr 0x200
+            0x81, 0x24, // ADD V1, V2  : addr 0x202
+        ])
+        .unwrap();
+        proc.set_warn_vf_clobber(true);
+
+        proc.step().unwrap();
+        assert!(proc.take_warnings().is_empty());
+
+        proc.step().unwrap();
+        assert_eq!(
+            proc.take_warnings(),
+            vec![ProcessorWarning::VfClobbered {
+                written_at: Address::from(0x200),
+                clobbered_at: Address::from(0x202),
+            }]
+        );
+        assert!(proc.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_skip_invalid_instructions() {
+        let program = vec![
+            0xF0, 0xFF, // invalid
+            0x60, 0x01, // LD V0, 0x01
+        ];
+        let mut strict = Processor::new(program.clone()).unwrap();
+        assert_eq!(
+            strict.step(),
+            Err(ProcessorError::DecodeFailure {
+                instruction: instructions::InstructionBytePair(0xF0FF),
+                reason: DecodeError::Misc { byte: 0xFF },
+            })
+        );
+        assert_eq!(u16::from(strict.program_counter()), 0x200);
+
+        let config = Config {
+            skip_invalid_instructions: true,
+            ..Config::default()
+        };
+        let mut lenient = Processor::new_with_config(program, config, Quirks::default()).unwrap();
+        assert_eq!(lenient.step_traced(), Ok(None));
+        assert_eq!(u16::from(lenient.program_counter()), 0x202);
+        assert_eq!(
+            lenient.take_warnings(),
+            vec![ProcessorWarning::InvalidInstructionSkipped {
+                address: Address::from(0x200),
+                instruction: instructions::InstructionBytePair(0xF0FF),
+                reason: DecodeError::Misc { byte: 0xFF },
+            }]
+        );
+
+        lenient.step().unwrap();
+        assert_eq!(lenient.registers.get_general(GeneralRegister::V0), 0x01);
+    }
+
+    #[test]
+    fn test_vf_read_before_clobber() {
+        let mut proc = Processor::new(vec![
+            0x8F, 0x10, // LD VF, V1     : addr 0x200
+            0x32, 0x01, // SE V2, 0x01   : addr 0x202
+            0x83, 0xF0, // LD V3, VF     : addr 0x204
+            0x81, 0x24, // ADD V1, V2    : addr 0x206
+        ])
+        .unwrap();
+        proc.set_warn_vf_clobber(true);
+
+        for _ in 0..4 {
+            proc.step().unwrap();
+        }
+
+        assert!(proc.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_vf_clobber_warning_disabled() {
+        let mut proc = Processor::new(vec![
+            0x6F, 0x05, // LD VF, 0x05 : addr 0x200
+            0x81, 0x24, // ADD V1, V2  : addr 0x202
+        ])
+        .unwrap();
+
+        proc.step().unwrap();
+        proc.step().unwrap();
+
+        assert!(proc.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_set_timers() {
+        let mut proc = Processor::new(vec![
+            0xF4, 0x07, // LD V4, DT
+        ])
+        .unwrap();
+        proc.set_timers(0x2A, 0x05);
+        assert_eq!(proc.sound(), 0x05);
+
+        proc.step().unwrap();
+        assert_eq!(proc.registers.get_general(GeneralRegister::V4), 0x2A);
+    }
+
+    #[test]
+    fn test_register_csv_row() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x0C, // LD V0, 0x0C
+            0x6F, 0xFF, // LD VF, 0xFF
+            0xA3, 0x45, // LD I, 0x345
+        ])
+        .unwrap();
+        proc.set_timers(60, 3);
+        for _ in 0..3 {
+            proc.step().unwrap();
+        }
+
+        let row = proc.register_csv_row(7);
+        assert_eq!(row, "7,518,837,12,0,0,0,0,0,0,0,0,0,0,0,0,0,0,255,60,3");
+        assert_eq!(
+            row.split(',').count(),
+            REGISTER_CSV_HEADER.split(',').count()
+        );
+    }
+
+    #[test]
+    fn test_register_dump() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x0C, // LD V0, 0x0C
+            0x6F, 0xFF, // LD VF, 0xFF
+            0xA3, 0x45, // LD I, 0x345
+            0x22, 0x08, // CALL 0x208
+        ])
+        .unwrap();
+        proc.set_timers(60, 3);
+        for _ in 0..4 {
+            proc.step().unwrap();
+        }
+
+        assert_eq!(
+            proc.register_dump(),
+            "V0=0c V1=00 V2=00 V3=00 V4=00 V5=00 V6=00 V7=00\n\
+             V8=00 V9=00 VA=00 VB=00 VC=00 VD=00 VE=00 VF=ff\n\
+             I=0x345 PC=0x208 SP=1 DT=3c ST=03"
+        );
+    }
+
+    #[test]
+    fn test_memory_snapshot() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x7B, // LD V0, 0x7B
+            0xA3, 0x00, // LD I, 0x300
+            0xF0, 0x33, // LD B, V0
+        ])
+        .unwrap();
+        for _ in 0..3 {
+            proc.step().unwrap();
+        }
+
+        let memory = proc.memory_snapshot();
+        assert_eq!(memory.len(), MEMORY_SIZE_BYTES);
+        assert_eq!(memory[..HEX_SPRITE_DATA.len()], HEX_SPRITE_DATA);
+        assert_eq!(memory[0x200..0x202], [0x60, 0x7B]);
+        assert_eq!(memory[0x300..0x303], [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_seeded_random_and_state_hash() {
+        let program = vec![
+            0xC0, 0xFF, // RND V0, 0xFF
+            0xC1, 0xFF, // RND V1, 0xFF
+            0xC2, 0xFF, // RND V2, 0xFF
+            0xC3, 0xFF, // RND V3, 0xFF
+        ];
+        let run = |seed| {
+            let mut proc = Processor::new(program.clone()).unwrap();
+            proc.seed_rng(seed);
+            for _ in 0..4 {
+                proc.step().unwrap();
+            }
+            proc.state_hash()
+        };
+
+        assert_eq!(run(1234), run(1234));
+        assert_ne!(run(1234), run(4321));
+    }
+
+    #[test]
+    fn test_state_hash_is_stable() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x7B, // LD V0, 0x7B
+            0xA3, 0x00, // LD I, 0x300
+            0xD0, 0x05, // DRW V0, V0, 5
+        ])
+        .unwrap();
+        proc.run_cycles(3).unwrap();
+        assert_eq!(proc.state_hash(), 0xEDAA_F78B_7F6B_960D);
+    }
+
+    #[test]
+    fn test_same_seed_same_random_registers() {
+        let program = vec![
+            0xC0, 0xFF, // RND V0, 0xFF
+            0xC1, 0x0F, // RND V1, 0x0F
+            0xC2, 0xF0, // RND V2, 0xF0
+            0xCE, 0xFF, // RND VE, 0xFF
+        ];
+        let run = |seed| {
+            let mut proc = Processor::new_with_seed(program.clone(), seed).unwrap();
+            for _ in 0..4 {
+                proc.step().unwrap();
+            }
+            GeneralRegister::iter()
+                .map(|reg| proc.registers.get_general(reg))
+                .collect::<Vec<_>>()
+        };
+
+        let registers = run(99);
+        assert_eq!(registers, run(99));
+        assert_eq!(registers[1] & 0xF0, 0);
+        assert_eq!(registers[2] & 0x0F, 0);
+    }
+
+    #[test]
+    fn test_reset_matches_fresh_processor() {
+        let program = vec![
+            0x60, 0x05, // LD V0, 0x05
+            0xF0, 0x15, // LD DT, V0
+            0xA3, 0x00, // LD I, 0x300
+            0xF0, 0x55, // LD [I], V0
+            0x00, 0xFF, // HIGH
+            0xD0, 0x05, // DRW V0, V0, 5
+            0x22, 0x00, // CALL 0x200
+        ];
+        let fresh = Processor::new(program.clone()).unwrap();
+
+        let mut proc = Processor::new(program).unwrap();
+        for _ in 0..7 {
+            proc.step().unwrap();
+        }
+        assert_ne!(proc.state_hash(), fresh.state_hash());
+
+        proc.reset();
+        assert_eq!(proc.state_hash(), fresh.state_hash());
+        assert_eq!(proc.registers.i, Address::from(0));
+        assert_eq!(proc.registers.delay, 0);
+        assert!(!proc.display().is_hires());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_save_state_round_trip() {
+        let mut proc = Processor::new_with_seed(
+            vec![
+                0xC0, 0xFF, // RND V0, 0xFF
+                0xA3, 0x00, // LD I, 0x300
+                0xF0, 0x33, // LD B, V0
+                0xD0, 0x05, // DRW V0, V0, 5
+                0x22, 0x00, // CALL 0x200
+            ],
+            3,
+        )
+        .unwrap();
+        for _ in 0..12 {
+            proc.step().unwrap();
+        }
+        let saved = proc.save_state();
+        let saved_hash = proc.state_hash();
+
+        for _ in 0..12 {
+            proc.step().unwrap();
+        }
+        assert_ne!(proc.state_hash(), saved_hash);
+
+        proc.load_state(&saved).unwrap();
+        assert_eq!(proc.save_state(), saved);
+        assert_eq!(proc.state_hash(), saved_hash);
+        assert!(proc.get_display_buffer().is_some());
+
+        // the fetch wraps, so the last byte of memory is somewhere to stop
+        let mut proc = Processor::new(vec![
+            0x1F, 0xFF, // JP 0xFFF
+        ])
+        .unwrap();
+        proc.step().unwrap();
+        let saved = proc.save_state();
+        proc.reset();
+        proc.load_state(&saved).unwrap();
+        assert_eq!(proc.program_counter(), Address::from(0xFFF));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_xo_chip_save_state() {
+        let mut proc = xo_chip_processor(vec![]);
+        proc.write_memory(0xFFFF, 0x5A);
+        let saved = proc.save_state();
+
+        let restored: Processor = save_state_options().deserialize(&saved).unwrap();
+        assert_eq!(restored.read_memory(0xFFFF), 0x5A);
+        assert!(Processor::new(vec![]).unwrap().load_state(&saved).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_load_state_rejects_bad_buffers() {
+        let mut proc = Processor::new(vec![0x60, 0x01]).unwrap();
+        let saved = proc.save_state();
+        let hash = proc.state_hash();
+
+        let mut trailing = saved.clone();
+        trailing.push(0);
+        for bytes in [&[][..], &saved[..saved.len() / 2], &trailing] {
+            assert_eq!(
+                proc.load_state(bytes),
+                Err(ProcessorError::InvalidSaveState { size: bytes.len() })
+            );
+        }
+
+        let mut small = Processor::new_with_config(
+            vec![],
+            Config {
+                display_width: 32,
+                display_height: 16,
+                ..Config::default()
+            },
+            Quirks::default(),
+        )
+        .unwrap();
+        assert!(small.load_state(&saved).is_err());
+
+        // nothing changed after the failed loads
+        assert_eq!(proc.state_hash(), hash);
+    }
+
+    #[test]
+    fn test_custom_config() {
+        let proc =
+            Processor::new_with_config(vec![], Config::new(128, 64), Quirks::default()).unwrap();
+        assert_eq!(proc.display_dimensions(), (128, 64));
+        let ascii = proc.display().to_ascii();
+        assert_eq!(ascii.lines().count(), 64);
+        assert!(ascii.lines().all(|row| row.len() == 128));
+
+        let config = Config::default();
+        assert_eq!((config.display_width, config.display_height), (64, 32));
+    }
+
+    #[test]
+    fn test_memory_map() {
+        let mut proc = Processor::new(vec![]).unwrap();
+        let map = proc.memory_map();
+
+        assert_eq!(map.font, 0x000..0x050);
+        assert_eq!(map.program.start, 0x200);
+        assert_eq!(map.display, None);
+
+        // the regions tile memory in order without gaps or overlaps
+        assert_eq!(map.font.start, 0);
+        assert_eq!(map.font.end, map.large_font.start);
+        assert_eq!(map.large_font.end, map.reserved.start);
+        assert_eq!(map.reserved.end, map.program.start);
+        assert_eq!(map.program.end, map.total_size);
+        assert_eq!(map.total_size, proc.memory.len());
+
+        proc.set_quirks(Quirks {
+            memory_mapped_display: true,
+            ..Quirks::default()
+        });
+        let display = proc.memory_map().display.unwrap();
+        assert_eq!(display.len(), 256);
+        assert_eq!(display.end, map.total_size);
+    }
+
+    #[test]
+    fn test_next_instruction() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x01, // LD V0, 0x01
+            0xD0, 0x05, // DRW V0, V0, 5
+        ])
+        .unwrap();
+
+        assert_eq!(
+            proc.next_instruction(),
+            Some(Instruction::LoadValue {
+                dest: GeneralRegister::V0,
+                value: 0x01
+            })
+        );
+        proc.step().unwrap();
+        assert!(matches!(
+            proc.next_instruction(),
+            Some(Instruction::Draw { .. })
+        ));
+        assert_eq!(proc.program_counter(), Address::from(0x202));
+    }
+
+    #[test]
+    fn test_halt_on_zero_opcode() {
+        let program = vec![
+            0x60, 0x01, // LD V0, 0x01
+            0x00, 0x00, // SYS 0x000
+            0x60, 0x02, // LD V0, 0x02
+        ];
+
+        let mut proc = Processor::new(program.clone()).unwrap();
+        for _ in 0..3 {
+            proc.step().unwrap();
+        }
+        assert!(!proc.is_halted());
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 0x02);
+        assert_eq!(proc.program_counter, Address::from(0x206));
+
+        let mut proc = Processor::new(program).unwrap();
+        proc.set_quirks(Quirks {
+            halt_on_zero_opcode: true,
+            ..Quirks::default()
+        });
+        assert_eq!(proc.step(), Ok(StepOutcome::Running));
+        assert_eq!(proc.step(), Ok(StepOutcome::Halted));
+        assert_eq!(proc.step(), Ok(StepOutcome::Halted));
+        assert!(proc.is_halted());
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 0x01);
+        assert_eq!(proc.program_counter, Address::from(0x202));
+
+        proc.load_program(vec![0x60, 0x03]).unwrap();
+        assert!(!proc.is_halted());
+    }
+
+    #[test]
+    fn test_sys_is_invalid() {
+        let program = vec![
+            0x03, 0x45, // SYS 0x345
+            0x60, 0x01, // LD V0, 0x01
+        ];
+
+        let mut proc = Processor::new(program.clone()).unwrap();
+        proc.run_cycles(2).unwrap();
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 0x01);
+
+        let quirks = Quirks {
+            sys_is_invalid: true,
+            ..Quirks::default()
+        };
+        let mut proc = Processor::new(program.clone()).unwrap();
+        proc.set_quirks(quirks);
+        assert_eq!(proc.next_instruction(), None);
+        assert_eq!(
+            proc.step(),
+            Err(ProcessorError::DecodeFailure {
+                instruction: instructions::InstructionBytePair(0x0345),
+                reason: DecodeError::SysDisabled,
+            })
+        );
+
+        let config = Config {
+            skip_invalid_instructions: true,
+            ..Config::default()
+        };
+        let mut proc = Processor::new_with_config(program, config, quirks).unwrap();
+        proc.run_cycles(2).unwrap();
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 0x01);
+        assert_eq!(proc.take_warnings().len(), 1);
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn test_profile_records_executed_addresses() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x01, // LD V0, 0x01
+            0x12, 0x00, // JP 0x200
+        ])
+        .unwrap();
+
+        for _ in 0..10 {
+            proc.step().unwrap();
+        }
+
+        for address in [0x200, 0x202] {
+            let entry = proc.profile().get(Address::from(address)).unwrap();
+            assert_eq!(entry.count, 5);
+            assert!(entry.total_nanos > 0);
+        }
+        assert_eq!(proc.profile().get(Address::from(0x204)), None);
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn test_instruction_counts() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x00, // LD V0, 0x00
+            0x70, 0x01, // ADD V0, 0x01
+            0x30, 0x05, // SE V0, 0x05
+            0x12, 0x02, // JP 0x202
+            0x00, 0xFD, // EXIT
+        ])
+        .unwrap();
+
+        proc.run_cycles(100).unwrap();
+
+        assert_eq!(
+            proc.instruction_counts(),
+            &BTreeMap::from([
+                ("AddValue", 5),
+                ("Exit", 1),
+                ("Jump", 4),
+                ("LoadValue", 1),
+                ("SkipIfEqByte", 5),
+            ])
+        );
+        proc.reset();
+        assert!(proc.instruction_counts().is_empty());
+    }
+
+    #[test]
+    fn test_state_getters() {
+        let mut proc = Processor::new(vec![
+            0x65, 0x2A, // LD V5, 0x2A
+            0xA3, 0x00, // LD I, 0x300
+            0xF5, 0x15, // LD DT, V5
+            0x22, 0x0A, // CALL 0x20A
+        ])
+        .unwrap();
+
+        assert_eq!(proc.program_counter(), Address::from(0x200));
+        assert_eq!(proc.general_register(GeneralRegister::V5), 0);
+
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter(), Address::from(0x202));
+        assert_eq!(proc.general_register(GeneralRegister::V5), 0x2A);
+        assert_eq!(proc.general_register(GeneralRegister::V0), 0);
+
+        proc.step().unwrap();
+        assert_eq!(proc.i_register(), Address::from(0x300));
+
+        proc.step().unwrap();
+        assert_eq!(proc.delay(), 0x2A);
+        assert_eq!(proc.stack_pointer(), 0);
+
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter(), Address::from(0x20A));
+        assert_eq!(proc.stack_pointer(), 1);
+    }
+
+    #[test]
+    fn test_sound_getter() {
+        let mut proc = Processor::new(vec![
+            0xFB, 0x18, // LD ST, VB
+        ])
+        .unwrap();
+
+        proc.registers.set_general(GeneralRegister::VB, 0x02);
+        assert_eq!(proc.sound(), 0);
+
+        proc.step().unwrap();
+        assert_eq!(proc.sound(), 0x02);
+
+        proc.tick_timers(1);
+        assert_eq!(proc.sound(), 0x01);
+    }
+
+    #[test]
+    fn test_display_wait() {
+        let mut proc = Processor::new(vec![
+            0xD0, 0x01, // DRW V0, V0, 1
+            0xD0, 0x01, // DRW V0, V0, 1
+            0x60, 0x01, // LD V0, 0x01
+        ])
+        .unwrap();
+        proc.set_quirks(Quirks {
+            display_wait: true,
+            ..Quirks::default()
+        });
+
+        proc.run_cycles(10).unwrap();
+        assert_eq!(proc.program_counter(), Address::from(0x200));
+        assert_eq!(proc.step_traced(), Ok(None));
+
+        proc.tick_timers(1);
+        proc.run_cycles(10).unwrap();
+        assert_eq!(proc.program_counter(), Address::from(0x202));
+        assert!(proc.display().to_ascii().starts_with('#'));
+
+        proc.tick_timers(1);
+        proc.run_cycles(10).unwrap();
+        assert_eq!(proc.registers.get_general(GeneralRegister::V0), 0x01);
+        assert!(proc.display().to_ascii().starts_with('.'));
+    }
+
+    #[test]
+    fn test_rewind_restores_vblank() {
+        let mut proc = Processor::new(vec![
+            0xD0, 0x01, // DRW V0, V0, 1
+        ])
+        .unwrap();
+        proc.set_quirks(Quirks {
+            display_wait: true,
+            ..Quirks::default()
+        });
+        proc.tick_timers(1);
+        proc.record_frame();
+
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter(), Address::from(0x202));
+
+        // the draw after rewinding doesn't wait for another tick
+        assert!(proc.rewind_frame(1));
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter(), Address::from(0x202));
+    }
+
+    #[test]
+    fn test_clone_is_independent() {
+        let mut proc = Processor::new_with_seed(
+            vec![
+                0xC0, 0xFF, // RND V0, 0xFF
+                0xA3, 0x00, // LD I, 0x300
+                0xF0, 0x33, // LD B, V0
+                0xD0, 0x05, // DRW V0, V0, 5
+                0x12, 0x00, // JP 0x200
+            ],
+            7,
+        )
+        .unwrap();
+        proc.run_cycles(7).unwrap();
+        let hash = proc.state_hash();
+        let pc = proc.program_counter();
+
+        proc.record_frame();
+        let mut clone = proc.clone();
+        assert_eq!(clone.state_hash(), hash);
+        assert!(!clone.rewind_frame(1));
+        clone.run_cycles(8).unwrap();
+        assert_ne!(clone.state_hash(), hash);
+
+        assert_eq!(proc.state_hash(), hash);
+        assert_eq!(proc.program_counter(), pc);
+
+        // both go on to do the same thing, random numbers included
+        proc.run_cycles(8).unwrap();
+        assert_eq!(proc.state_hash(), clone.state_hash());
+    }
+
+    #[test]
+    fn test_tick_timers() {
+        let mut proc = Processor::new(vec![
+            0xF0, 0x15, // LD DT, V0
+            0xF1, 0x18, // LD ST, V1
+        ])
+        .unwrap();
+        proc.registers.set_general(GeneralRegister::V0, 10);
+        proc.registers.set_general(GeneralRegister::V1, 4);
+        proc.step().unwrap();
+        proc.step().unwrap();
+
+        proc.tick_timers(3);
+        assert_eq!(proc.registers.delay, 7);
+        assert_eq!(proc.sound(), 1);
+
+        proc.tick_timers(7);
+        assert_eq!(proc.registers.delay, 0);
+        assert_eq!(proc.sound(), 0);
+
+        // both timers stop at zero rather than wrapping
+        proc.tick_timers(5);
+        assert_eq!(proc.registers.delay, 0);
+        assert_eq!(proc.sound(), 0);
+    }
+
+    #[test]
+    fn test_tick() {
+        let mut proc = Processor::new(vec![0x12, 0x00]).unwrap();
+        proc.set_timers(2, 4);
+
+        proc.tick();
+        assert_eq!((proc.delay(), proc.sound()), (1, 3));
+        proc.tick();
+        assert_eq!((proc.delay(), proc.sound()), (0, 2));
+        // the delay timer stays at zero while the sound timer carries on
+        proc.tick();
+        assert_eq!((proc.delay(), proc.sound()), (0, 1));
+        proc.tick();
+        proc.tick();
+        assert_eq!((proc.delay(), proc.sound()), (0, 0));
+
+        proc.set_timers(3, 1);
+        proc.tick();
+        proc.tick();
+        assert_eq!((proc.delay(), proc.sound()), (1, 0));
+    }
+
+    #[test]
+    fn test_breakpoint() {
+        let mut proc = Processor::new(vec![
+            0x60, 0x01, // LD V0, 0x01
+            0x70, 0x01, // ADD V0, 0x01
+            0x12, 0x02, // JP 0x202
+        ])
+        .unwrap();
+        proc.add_breakpoint(0x202).unwrap();
+
+        assert_eq!(proc.step(), Ok(StepOutcome::Running));
+        assert_eq!(
+            proc.step(),
+            Ok(StepOutcome::Breakpoint {
+                address: Address::from(0x202)
+            })
+        );
+        // stopped before the ADD ran
+        assert_eq!(proc.program_counter(), Address::from(0x202));
+        assert_eq!(proc.general_register(GeneralRegister::V0), 1);
+
+        assert_eq!(proc.step(), Ok(StepOutcome::Running));
+        assert_eq!(proc.general_register(GeneralRegister::V0), 2);
+        assert_eq!(proc.step(), Ok(StepOutcome::Running));
+        // and stops again the next time round the loop
+        assert_eq!(
+            proc.step(),
+            Ok(StepOutcome::Breakpoint {
+                address: Address::from(0x202)
+            })
+        );
+        assert_eq!(proc.general_register(GeneralRegister::V0), 2);
+    }
+
+    #[test]
+    fn test_breakpoint_on_key_wait() {
+        let mut proc = Processor::new(vec![
+            0xF0, 0x0A, // LD V0, K
+            0x12, 0x02, // JP 0x202
+        ])
+        .unwrap();
+        proc.add_breakpoint(0x200).unwrap();
+
+        assert_eq!(
+            proc.step(),
+            Ok(StepOutcome::Breakpoint {
+                address: Address::from(0x200)
+            })
+        );
+        // waiting for the key runs the instruction again in place, which
+        // mustn't keep tripping the breakpoint
+        for _ in 0..4 {
+            assert_eq!(proc.step(), Ok(StepOutcome::Running));
+            assert_eq!(proc.program_counter(), Address::from(0x200));
+        }
+
+        proc.add_key_event(7, KeyStatus::Pressed);
+        proc.step().unwrap();
+        proc.add_key_event(7, KeyStatus::Released);
+        proc.step().unwrap();
+        assert_eq!(proc.program_counter(), Address::from(0x202));
+        assert_eq!(proc.general_register(GeneralRegister::V0), 7);
+    }
+
+    #[test]
+    fn test_breakpoint_out_of_range() {
+        let mut proc = Processor::new(vec![0x12, 0x00]).unwrap();
+        let out_of_range = Err(ProcessorError::AddressOutOfRange { address: 0x1200 });
+        assert_eq!(proc.add_breakpoint(0x1200), out_of_range);
+        assert_eq!(proc.add_watchpoint(0x1200), out_of_range);
+        // rather than landing on 0x200
+        assert_eq!(proc.step(), Ok(StepOutcome::Idle));
+
+        let mut proc = xo_chip_processor(vec![0x12, 0x00]);
+        assert_eq!(proc.add_breakpoint(0x1200), Ok(()));
+        assert_eq!(proc.add_watchpoint(0xFFFF), Ok(()));
+        assert_eq!(proc.step(), Ok(StepOutcome::Idle));
+    }
+
+    #[test]
+    fn test_step_traced_ignores_breakpoints() {
+        let mut proc = Processor::new(vec![0x60, 0x01, 0x12, 0x00]).unwrap();
+        proc.add_breakpoint(0x200).unwrap();
+        assert!(proc.step_traced().unwrap().is_some());
+        assert_eq!(proc.general_register(GeneralRegister::V0), 1);
+    }
+
+    #[test]
+    fn test_watchpoint() {
+        let mut proc = Processor::new(vec![
+            0xA3, 0x00, // LD I, 0x300
+            0x60, 0x00, // LD V0, 0x00
+            0x61, 0x07, // LD V1, 0x07
+            0xF1, 0x55, // LD [I], V1
+            0x12, 0x08, // JP 0x208
+        ])
+        .unwrap();
+        proc.add_watchpoint(0x301).unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(proc.step(), Ok(StepOutcome::Running));
+        }
+        assert_eq!(proc.step(), Ok(StepOutcome::Watchpoint { address: 0x301 }));
+        // the write has already happened
+        assert_eq!(proc.read_memory(0x301), 0x07);
+        assert_eq!(proc.step(), Ok(StepOutcome::Idle));
+
+        // writes from outside the program don't trip it
+        proc.write_memory(0x301, 0);
+        assert_eq!(proc.step(), Ok(StepOutcome::Idle));
+    }
 }
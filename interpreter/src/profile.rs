@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::instructions::Instruction;
+use crate::types::Address;
+
+const HISTOGRAM_WIDTH: u64 = 40;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileEntry {
+    pub count: u64,
+    pub total_nanos: u64,
+}
+
+// Cumulative wall-clock time spent in `Processor::step`, keyed by the address
+// of the instruction that was executed, along with how often each kind of
+// instruction ran.
+#[derive(Debug, Default, Clone)]
+pub struct Profile {
+    entries: BTreeMap<Address, ProfileEntry>,
+    instruction_counts: BTreeMap<&'static str, u64>,
+}
+
+impl Profile {
+    pub fn record(&mut self, address: Address, elapsed: Duration) {
+        let entry = self.entries.entry(address).or_default();
+        entry.count += 1;
+        entry.total_nanos = entry
+            .total_nanos
+            .saturating_add(elapsed.as_nanos().try_into().unwrap_or(u64::MAX));
+    }
+
+    pub fn record_instruction(&mut self, instruction: &Instruction) {
+        *self
+            .instruction_counts
+            .entry(instruction.into())
+            .or_default() += 1;
+    }
+
+    pub fn get(&self, address: Address) -> Option<ProfileEntry> {
+        self.entries.get(&address).copied()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.instruction_counts.clear();
+    }
+
+    // Keyed by the name of the `Instruction` variant.
+    pub fn instruction_counts(&self) -> &BTreeMap<&'static str, u64> {
+        &self.instruction_counts
+    }
+
+    // Addresses ordered from most to least total time.
+    pub fn hotspots(&self) -> Vec<(Address, ProfileEntry)> {
+        let mut hotspots: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(address, entry)| (*address, *entry))
+            .collect();
+        hotspots.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.total_nanos));
+        hotspots
+    }
+
+    pub fn report(&self, limit: usize) -> String {
+        let mut report = String::from("address      count   total ns  avg ns\n");
+        for (address, entry) in self.hotspots().into_iter().take(limit) {
+            let _ = writeln!(
+                report,
+                "{}  {:>10} {:>10} {:>7}",
+                address,
+                entry.count,
+                entry.total_nanos,
+                entry.total_nanos / entry.count
+            );
+        }
+        report
+    }
+
+    // Instructions ordered from most to least executed, each with a bar
+    // scaled against the most executed one.
+    pub fn instruction_histogram(&self) -> String {
+        let mut counts: Vec<_> = self.instruction_counts.iter().collect();
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+        let most = counts.first().map_or(0, |(_, count)| **count);
+
+        let mut histogram = String::new();
+        for (name, count) in counts {
+            let bar_length = (count * HISTOGRAM_WIDTH).div_ceil(most);
+            let _ = writeln!(
+                histogram,
+                "{:<20} {:>10} {}",
+                name,
+                count,
+                "#".repeat(bar_length as usize)
+            );
+        }
+        histogram
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hotspots_sorted_by_total_time() {
+        let mut profile = Profile::default();
+        profile.record(Address::from(0x200), Duration::from_nanos(10));
+        profile.record(Address::from(0x202), Duration::from_nanos(30));
+        profile.record(Address::from(0x200), Duration::from_nanos(5));
+
+        assert_eq!(
+            profile.hotspots(),
+            vec![
+                (
+                    Address::from(0x202),
+                    ProfileEntry {
+                        count: 1,
+                        total_nanos: 30
+                    }
+                ),
+                (
+                    Address::from(0x200),
+                    ProfileEntry {
+                        count: 2,
+                        total_nanos: 15
+                    }
+                ),
+            ]
+        );
+        assert_eq!(profile.report(1).lines().count(), 2);
+    }
+
+    #[test]
+    fn test_instruction_histogram() {
+        let mut profile = Profile::default();
+        for _ in 0..4 {
+            profile.record_instruction(&Instruction::Clear);
+        }
+        profile.record_instruction(&Instruction::Return);
+
+        assert_eq!(
+            profile.instruction_histogram(),
+            format!(
+                "{:<20} {:>10} {}\n{:<20} {:>10} {}\n",
+                "Clear",
+                4,
+                "#".repeat(40),
+                "Return",
+                1,
+                "#".repeat(10)
+            )
+        );
+        profile.clear();
+        assert!(profile.instruction_counts().is_empty());
+        assert_eq!(profile.instruction_histogram(), "");
+    }
+}
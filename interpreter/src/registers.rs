@@ -10,6 +10,8 @@ pub enum Flag {
     High,
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Registers {
     pub i: Address,
     pub delay: u8,
@@ -68,18 +70,10 @@ impl Registers {
         }
     }
 
-    #[allow(dead_code)] // TODO
-    pub fn decrement_delay(&mut self) {
-        if self.delay != 0 {
-            self.delay -= 1;
-        }
-    }
-
-    #[allow(dead_code)] // TODO
-    pub fn decrement_sound(&mut self) {
-        if self.sound != 0 {
-            self.sound -= 1;
-        }
+    // Both timers count down together, each stopping at zero.
+    pub fn tick(&mut self) {
+        self.delay = self.delay.saturating_sub(1);
+        self.sound = self.sound.saturating_sub(1);
     }
 
     pub fn set_vf_flag(&mut self, flag: Flag) {
@@ -150,40 +144,30 @@ mod tests {
     }
 
     #[test]
-    fn test_delay_decrement() {
-        let mut registers = Registers::new();
-        let test_valule = 123_u8;
-        let existing_sound_value = registers.sound;
-        registers.delay = test_valule;
-        registers.decrement_delay();
-        assert_eq!(test_valule - 1, registers.delay);
-        assert_eq!(existing_sound_value, registers.sound);
-    }
-
-    #[test]
-    fn test_sound_decrement() {
+    fn test_tick() {
         let mut registers = Registers::new();
-        let test_valule = 123_u8;
-        let existing_delay_value = registers.delay;
-        registers.sound = test_valule;
-        registers.decrement_sound();
-        assert_eq!(test_valule - 1, registers.sound);
-        assert_eq!(existing_delay_value, registers.delay);
+        registers.delay = 123_u8;
+        registers.sound = 45_u8;
+        registers.tick();
+        assert_eq!(registers.delay, 122_u8);
+        assert_eq!(registers.sound, 44_u8);
     }
 
     #[test]
-    fn test_zero_delay_decrement() {
+    fn test_zero_delay_tick() {
         let mut registers = Registers::new();
-        registers.delay = 0_u8;
-        registers.decrement_delay();
+        registers.sound = 2_u8;
+        registers.tick();
         assert_eq!(registers.delay, 0_u8);
+        assert_eq!(registers.sound, 1_u8);
     }
 
     #[test]
-    fn test_zero_sound_decrement() {
+    fn test_zero_sound_tick() {
         let mut registers = Registers::new();
-        registers.sound = 0_u8;
-        registers.decrement_sound();
+        registers.delay = 2_u8;
+        registers.tick();
+        assert_eq!(registers.delay, 1_u8);
         assert_eq!(registers.sound, 0_u8);
     }
 
@@ -0,0 +1,97 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+
+use crate::display::{parse_pbm, Display};
+use crate::processor::{Processor, StepOutcome};
+
+// Random numbers are seeded so runs are repeatable.
+const SEED: u64 = 0;
+
+// Runs `rom` for exactly `cycles` instructions, or until it exits, with no
+// timers or input, and returns the resulting display.
+pub fn display_at_cycle(rom: &[u8], cycles: usize) -> Result<Display, String> {
+    let mut processor =
+        Processor::new_with_seed(rom.to_vec(), SEED).map_err(|err| err.to_string())?;
+    for cycle in 0..cycles {
+        let outcome = processor
+            .step()
+            .map_err(|err| format!("Cycle {}: {}", cycle, err.explanation()))?;
+        if outcome == StepOutcome::Exited {
+            break;
+        }
+    }
+    Ok(processor.display().clone())
+}
+
+// Panics with the differing pixels, and the whole display as a PBM for updating
+// the reference, when the display after `cycles` instructions doesn't match.
+pub fn assert_display_at_cycle(rom: &[u8], cycles: usize, expected_pbm: &str) {
+    let expected =
+        parse_pbm(expected_pbm).unwrap_or_else(|err| panic!("Invalid reference image: {}", err));
+    let display = display_at_cycle(rom, cycles).unwrap_or_else(|err| panic!("{}", err));
+
+    if !display.equals_grid(&expected) {
+        panic!(
+            "Display at cycle {} doesn't match the reference:\n{}\nActual display:\n{}",
+            cycles,
+            display.diff_grid(&expected),
+            display.to_pbm()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common_test_data::IBM_LOGO_ROM;
+
+    const IBM_LOGO_PBM: &str = "P1
+64 32
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000001111111101111111110001111100000000011111000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000001111111101111111111101111110000000111111000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000011110000011100011100011111000001111100000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000011110000011111110000011111110111111100000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000011110000011111110000011101111111011100000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000011110000011100011100011100111110011100000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000001111111101111111111101111100011100011111000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000001111111101111111110001111100001000011111000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+0000000000000000000000000000000000000000000000000000000000000000
+";
+
+    // The last sprite, the right-hand edge of the M, is drawn by the 20th
+    // instruction.
+    #[test]
+    fn test_ibm_logo_at_cycle() {
+        assert_display_at_cycle(&IBM_LOGO_ROM, 20, IBM_LOGO_PBM);
+        assert_display_at_cycle(&IBM_LOGO_ROM, 100, IBM_LOGO_PBM);
+    }
+
+    #[test]
+    #[should_panic(expected = "Display at cycle 19 doesn't match the reference:\nrow 8, col 49")]
+    fn test_ibm_logo_one_cycle_early() {
+        assert_display_at_cycle(&IBM_LOGO_ROM, 19, IBM_LOGO_PBM);
+    }
+}
@@ -1,9 +1,10 @@
-use std::fmt;
+use core::fmt;
 
 use strum_macros::{Display, EnumIter};
 
 #[repr(transparent)]
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Address(u16);
 
 impl fmt::Display for Address {
@@ -13,8 +14,15 @@ impl fmt::Display for Address {
 }
 
 impl Address {
+    // An address from XO-CHIP's 16-bit address space, which From would mask to
+    // 12 bits.
+    pub fn extended(value: u16) -> Self {
+        Address(value)
+    }
+
+    // Wraps round within the 12-bit address space.
     pub fn increment(&mut self, value: usize) {
-        *self = Address(self.0 + value as u16);
+        *self = Address(((self.0 as usize + value) & 0x0FFF) as u16);
     }
 }
 
@@ -52,6 +60,10 @@ pub enum Nibble {
 }
 
 impl Nibble {
+    pub fn as_u8(self) -> u8 {
+        self as u8
+    }
+
     pub fn from_upper(byte: u8) -> Nibble {
         match (byte & 0xF0_u8) >> 4 {
             0x00 => Nibble::Zero,
@@ -140,3 +152,65 @@ impl From<Nibble> for GeneralRegister {
         }
     }
 }
+
+impl From<GeneralRegister> for Nibble {
+    fn from(value: GeneralRegister) -> Self {
+        Nibble::from_lower(value as u8)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NibbleOutOfRange {
+    pub value: u8,
+}
+
+impl fmt::Display for NibbleOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#04x} doesn't fit in a nibble", self.value)
+    }
+}
+
+impl core::error::Error for NibbleOutOfRange {}
+
+impl TryFrom<u8> for Nibble {
+    type Error = NibbleOutOfRange;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        if value > 0x0F {
+            return Err(NibbleOutOfRange { value });
+        }
+        Ok(Nibble::from_lower(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn test_nibble_round_trip() {
+        for value in 0x0..=0xF_u8 {
+            let nibble = Nibble::try_from(value).unwrap();
+            assert_eq!(nibble.as_u8(), value);
+            let reg = GeneralRegister::from(nibble);
+            assert_eq!(reg as u8, value);
+            assert_eq!(Nibble::from(reg), nibble);
+        }
+        for nibble in Nibble::iter() {
+            assert_eq!(Nibble::try_from(nibble.as_u8()), Ok(nibble));
+        }
+    }
+
+    #[test]
+    fn test_nibble_out_of_range() {
+        for value in 0x10..=0xFF_u8 {
+            assert_eq!(Nibble::try_from(value), Err(NibbleOutOfRange { value }));
+        }
+        assert_eq!(
+            NibbleOutOfRange { value: 0x10 }.to_string(),
+            "0x10 doesn't fit in a nibble"
+        );
+    }
+}